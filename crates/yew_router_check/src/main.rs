@@ -0,0 +1,261 @@
+//! Checks route matcher strings without compiling them into an app.
+//!
+//! Every matcher string is parsed the same way the `Switch` derive parses a `#[to]` attribute,
+//! printing where it stopped parsing if it has a syntax error, or the tokens it was parsed into.
+//! Any paths that are given are then matched against it, printing what they capture.
+
+#![deny(
+    missing_docs,
+    missing_debug_implementations,
+    trivial_casts,
+    trivial_numeric_casts,
+    unsafe_code,
+    unstable_features,
+    unused_qualifications
+)]
+
+use std::{
+    env, fs,
+    io::{self, BufRead, Write},
+    process,
+};
+use yew_router_min::{
+    matcher::{MatcherSettings, RouteMatcher},
+    yew_router_route_parser::{parse_str_and_optimize_tokens, FieldNamingScheme},
+};
+
+const USAGE: &str = "\
+Usage: yew-router-check [OPTIONS] [MATCHER]...
+
+Parses each route matcher string, printing its tokens or where it stopped parsing.
+Matcher strings are read one per line from the file, or from stdin, when none are given.
+
+Options:
+  --scheme <named|unnamed|unit>  The fields the matcher populates [default: named]
+  --path <PATH>                  A path to match against every matcher, which can be repeated
+  --file <FILE>                  Reads matcher strings from the file, one per line
+  --case-sensitive               Matches literals in paths case sensitively
+  --decode                       Percent-decodes captured sections
+  -h, --help                     Prints this message";
+
+/// What to check, as read from the command line.
+#[derive(Debug, PartialEq)]
+struct Options {
+    scheme: FieldNamingScheme,
+    paths: Vec<String>,
+    file: Option<String>,
+    settings: MatcherSettings,
+    matchers: Vec<String>,
+}
+
+/// The result of reading the command line.
+#[derive(Debug, PartialEq)]
+enum Command {
+    Check(Options),
+    Help,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut options = Options {
+        scheme: FieldNamingScheme::Named,
+        paths: vec![],
+        file: None,
+        settings: MatcherSettings {
+            case_insensitive: true,
+            ..MatcherSettings::default()
+        },
+        matchers: vec![],
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |option: &str| {
+            args.next()
+                .ok_or_else(|| format!("`{}` expects a value", option))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--scheme" => {
+                options.scheme = match value("--scheme")?.as_str() {
+                    "named" => FieldNamingScheme::Named,
+                    "unnamed" => FieldNamingScheme::Unnamed,
+                    "unit" => FieldNamingScheme::Unit,
+                    scheme => {
+                        return Err(format!(
+                            "unknown scheme `{}`, expected `named`, `unnamed` or `unit`",
+                            scheme
+                        ))
+                    }
+                }
+            }
+            "--path" => options.paths.push(value("--path")?),
+            "--file" => options.file = Some(value("--file")?),
+            "--case-sensitive" => options.settings.case_insensitive = false,
+            "--decode" => options.settings.decode_captures = true,
+            // Everything after `--` is a matcher string, even if it looks like an option.
+            "--" => options.matchers.extend(args.by_ref()),
+            option if option.starts_with("--") => {
+                return Err(format!("unknown option `{}`", option))
+            }
+            _ => options.matchers.push(arg),
+        }
+    }
+    Ok(Command::Check(options))
+}
+
+/// Parses the matcher string and matches the paths against it, writing a report of both.
+///
+/// Returns whether the matcher string parsed and every path matched it.
+fn check(matcher: &str, options: &Options, out: &mut impl Write) -> io::Result<bool> {
+    writeln!(out, "{}", matcher)?;
+    let tokens = match parse_str_and_optimize_tokens(matcher, options.scheme) {
+        Ok(tokens) => tokens,
+        Err(error) => {
+            writeln!(out, "{:?}", error)?;
+            return Ok(false);
+        }
+    };
+    for token in &tokens {
+        writeln!(out, "  {:?}", token)?;
+    }
+
+    let matcher = RouteMatcher {
+        tokens,
+        settings: options.settings,
+    };
+    let mut matched = true;
+    for path in &options.paths {
+        match matcher.try_capture_route_into_map(path) {
+            Ok((rest, captures)) => {
+                write!(out, "  {} matches", path)?;
+                if !rest.is_empty() {
+                    write!(out, ", leaving '{}'", rest)?;
+                }
+                writeln!(out)?;
+                let mut captures = captures.into_iter().collect::<Vec<_>>();
+                captures.sort();
+                for (name, value) in captures {
+                    let name = if name.is_empty() { "{}" } else { name };
+                    writeln!(out, "    {} = '{}'", name, value)?;
+                }
+            }
+            Err(error) => {
+                writeln!(out, "  {} doesn't match: {}", path, error)?;
+                matched = false;
+            }
+        }
+    }
+    Ok(matched)
+}
+
+/// Reads matcher strings one per line, skipping blank lines.
+fn read_matchers(reader: impl BufRead) -> io::Result<Vec<String>> {
+    let mut matchers = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            matchers.push(line);
+        }
+    }
+    Ok(matchers)
+}
+
+fn run(options: Options) -> io::Result<bool> {
+    let mut matchers = options.matchers.clone();
+    if let Some(file) = &options.file {
+        matchers.extend(read_matchers(io::BufReader::new(fs::File::open(file)?))?);
+    } else if matchers.is_empty() {
+        matchers = read_matchers(io::stdin().lock())?;
+    }
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut ok = true;
+    for (index, matcher) in matchers.iter().enumerate() {
+        if index > 0 {
+            writeln!(out)?;
+        }
+        ok &= check(matcher, &options, &mut out)?;
+    }
+    Ok(ok)
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Command::Check(options)) => options,
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+    match run(options) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(error) => {
+            eprintln!("error: {}", error);
+            process::exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Options {
+        match parse_args(args.iter().map(ToString::to_string)) {
+            Ok(Command::Check(options)) => options,
+            result => panic!("expected options, got {:?}", result),
+        }
+    }
+
+    fn check_report(matcher: &str, args: &[&str]) -> (bool, String) {
+        let mut out = vec![];
+        let ok = check(matcher, &options(args), &mut out).expect("should write");
+        (ok, String::from_utf8(out).expect("should be utf-8"))
+    }
+
+    #[test]
+    fn parses_options_and_matchers() {
+        let options = options(&["--scheme", "unnamed", "--path", "/a", "/{}", "--", "--b"]);
+        assert_eq!(options.scheme, FieldNamingScheme::Unnamed);
+        assert_eq!(options.paths, vec!["/a"]);
+        assert_eq!(options.matchers, vec!["/{}", "--b"]);
+        assert!(parse_args(vec!["--scheme".to_string()]).is_err());
+        assert!(parse_args(vec!["--unknown".to_string()]).is_err());
+        assert_eq!(parse_args(vec!["-h".to_string()]), Ok(Command::Help));
+    }
+
+    #[test]
+    fn prints_tokens_and_captures() {
+        let (ok, report) = check_report("/users/{id}", &["--path", "/users/5"]);
+        assert!(ok);
+        assert_eq!(
+            report,
+            "/users/{id}\n  Exact(\"/users/\")\n  Capture(Named(\"id\"))\n  /users/5 matches\n    id = '5'\n"
+        );
+    }
+
+    #[test]
+    fn reports_parse_errors_and_mismatches() {
+        let (ok, report) = check_report("/users/{id", &[]);
+        assert!(!ok);
+        assert!(report.contains("Could not parse route."));
+
+        let (ok, report) = check_report("/users/{id}", &["--path", "/posts/5"]);
+        assert!(!ok);
+        assert!(report.contains("/posts/5 doesn't match: token 0 didn't match at offset 0"));
+
+        let (ok, _) = check_report("/users/{id}", &["--scheme", "unit"]);
+        assert!(!ok);
+    }
+
+    #[test]
+    fn skips_blank_lines() {
+        let matchers = read_matchers("/a\n\n  \n/b\n".as_bytes()).expect("should read");
+        assert_eq!(matchers, vec!["/a", "/b"]);
+    }
+}
@@ -25,11 +25,44 @@ mod switch;
 /// _everything_, and the next 3 path sections respectively.
 /// `{1:field_name}` is the same as `{field_name}`.
 ///
+/// A capture can be constrained with `{field_name:constraint}`, so it only matches sections that satisfy
+/// the constraint, which is checked before any conversion of the field is attempted.
+/// The constraint is either a type - `u8` through `u128`, `i8` through `i128`, `usize`, `isize`, `bool`,
+/// `date` (as `YYYY-MM-DD`), or `uuid` - or a pattern like `[a-z0-9_-]+` or `draft|published`.
+/// Patterns are made of characters and bracketed character classes, which may be followed by `?`, `*` or `+`,
+/// and which are separated into alternatives with `|`.
+/// This allows `#[to = "/post/{id:u32}"]` and `#[to = "/post/{action}"]` to route to different variants,
+/// even if both of their fields are `String`s.
+///
+/// Sections of the path can be made optional by wrapping them in brackets, like `/users[/{id}][/edit]`.
+/// An optional section is matched if it can be, and skipped otherwise.
+/// Fields that are captured within an optional section should be `Option`s, which are `None` when it is skipped.
+/// When producing a route, an optional section is only written if it captures a field,
+/// and every `Option` field that it captures is `Some`.
+/// Optional sections can't be nested, and can't start with, or be followed by, a capture.
+///
+/// A part of the path can match any one of several literals, like `/(posts|articles)/{slug}`.
+/// When producing a route, the first of the literals is written.
+/// A pattern constraint can also be wrapped in parentheses, like `{lang:(en|de|fr)}`.
+///
+/// The parameters of a query, like `/search?q={query}&sort=new`, are matched in any order,
+/// and parameters that the matcher doesn't list are ignored.
+/// Ending the matcher after the query, like `/search?q={query}!`, rejects any other parameters.
+/// When producing a route, the parameters are written in the order the matcher lists them.
+/// A repeated parameter, like `/filter?tag={*:tags}`, is captured into a `Vec` field,
+/// with an element for every time it appears, and is written once for each element.
+/// A parameter that is captured into an `Option` field can be left out of the query,
+/// in which case the field is `None`, and the parameter is left out of the route when producing it.
+///
 /// Tuple-structs and Tuple-enum-variants are also supported.
 /// If you don't want to specify keys that don't correspond to any specific field,
 /// `{}`, `{*}`, and `{4}` also denote valid capture sections when used on structs and variants without named fields.
 /// In datastructures without field names, the captures will be assigned in order - left to right.
 ///
+/// Every capture has to populate a field, and every field has to be populated by a capture,
+/// except for `Option` fields, which are `None` without one.
+/// Otherwise the struct or variant could never be produced, so deriving `Switch` for it fails.
+///
 /// # Note
 /// It should be mentioned that the derived function for matching will try enum variants in order,
 /// from top to bottom, and that the whole route doesn't need to be matched by the route
@@ -41,11 +74,108 @@ mod switch;
 /// any characters are left after matching the route matcher string, the match should fail.
 /// This means that `[to = "/!"]` will match "/" and _only_ "/".
 ///
+/// A variant that could never be matched, because an earlier variant matches every route that it does,
+/// fails to compile, suggesting where to add the `!`.
+/// Only earlier variants made of literals and captures of single sections into `String` fields are
+/// considered, since whether other captures match depends on whether their fields can be converted.
+/// ```compile_fail
+/// use yew_router_min::Switch;
+///
+/// #[derive(Switch, Clone)]
+/// enum AppRoute {
+///     #[to = "/variant"]
+///     Variant,
+///     #[to = "/variant/stuff"] // Shadowed by `Variant`, which should be `#[to = "/variant!"]`.
+///     Stuff,
+/// }
+/// ```
+/// ```compile_fail
+/// use yew_router_min::Switch;
+///
+/// #[derive(Switch, Clone)]
+/// enum AppRoute {
+///     #[to = "/variant/{item}"]
+///     Variant { item: String },
+///     #[to = "/variant/stuff"] // Shadowed by `Variant`, since `item` can be "stuff".
+///     Stuff,
+/// }
+/// ```
+/// A variant that is only used to make paths with `Switch::to_path` can be allowed to be shadowed
+/// with `#[switch(allow_shadowed)]`, which can also be put on an enum to allow it for every variant.
+///
+/// The derive also implements `Switch::to_path`, which writes the route matcher string back out,
+/// replacing every capture section with the `to_path` representation of its field.
+/// So for `#[to = "/route/{id}"]` and `Route{id: usize}`, `Route{id: 5}.to_path()` produces "/route/5",
+/// and fields captured with `#[rest]` delegate to the `to_path` implementation of the wrapped type.
+///
+/// It also implements `Switch::routes`, which describes every variant with a `RouteInfo`, listing its
+/// route matcher string, its captures and the types of the fields they populate.
+///
+/// It also implements `Switch::try_from_path`, which returns a `SwitchError` listing every variant
+/// that was tried when no variant matched, along with the token of its route matcher string
+/// that didn't match, or the field that couldn't be converted from what was captured.
+///
 /// -----
 /// There are other attributes as well.
 /// `#[rest]`, `#[rest="field_name"]` and `#[end]` attributes exist as well.
 /// `#[rest]` and `#[rest="field_name"]` are equivalent to `{*}` and `{*:field_name}` respectively.
 /// `#[end]` is equivalent to `!`.
+///
+/// Literals in the route matcher string are matched regardless of case, while captures keep the case
+/// they have in the route. `#[switch(case_sensitive)]` makes the literals case sensitive,
+/// and `#[switch(lowercase_captures)]` lowercases the captures, independently of each other.
+/// These can be put on a struct, on an enum, or on a variant, which overrides the setting of its enum,
+/// and are undone by `#[switch(case_insensitive)]` and `#[switch(keep_capture_case)]` respectively.
+///
+/// Putting `#[switch(rank = "specificity")]` on an enum tries its variants from the most specific
+/// to the least specific, instead of in the order they are declared in.
+/// A matcher is more specific if it has more literal segments, then if it has fewer `{*}` captures,
+/// then if it has more other captures, more of which are constrained, and finally if it ends with `!`.
+/// Variants that are equally specific, and whose leading literals don't rule out matching the same route,
+/// fail to compile.
+/// ```
+/// use yew_router_min::Switch;
+///
+/// #[derive(Switch, Clone, Debug, PartialEq)]
+/// #[switch(rank = "specificity")]
+/// enum AppRoute {
+///     #[to = "/users/{id}"]
+///     User { id: String },
+///     #[to = "/users/new"]
+///     NewUser,
+/// }
+///
+/// assert_eq!(AppRoute::from_path("/users/new"), Some(AppRoute::NewUser));
+/// ```
+///
+/// `#[switch(prefix = "/api/v2")]` on an enum prepends a route matcher string to the matcher of every
+/// variant, and to the routes written by `to_path`. A `/` that ends the prefix is dropped when the
+/// variant starts with one, so `"/api/"` and `#[to = "/users"]` match "/api/users".
+/// The prefix can't capture anything, or have a query or fragment, so a prefix like `"/api?v=2"`
+/// fails to compile.
+/// ```
+/// use yew_router_min::Switch;
+///
+/// #[derive(Switch, Clone, Debug, PartialEq)]
+/// #[switch(prefix = "/api/v2")]
+/// enum ApiRoute {
+///     #[to = "/users/{id}"]
+///     User { id: u32 },
+/// }
+///
+/// assert_eq!(ApiRoute::from_path("/api/v2/users/5"), Some(ApiRoute::User { id: 5 }));
+/// assert_eq!(ApiRoute::User { id: 5 }.to_path(), "/api/v2/users/5");
+/// ```
+/// ```compile_fail
+/// use yew_router_min::Switch;
+///
+/// #[derive(Switch, Clone)]
+/// #[switch(prefix = "/api?v=2")] // A prefix can't have a query.
+/// enum ApiRoute {
+///     #[to = "/users"]
+///     Users,
+/// }
+/// ```
 /// The `#[rest]` attributes are good if you just want to delegate the whole matching of a variant to a specific
 /// wrapped struct or enum that also implements `Switch`.
 ///
@@ -75,8 +205,68 @@ mod switch;
 ///     second: String,
 /// }
 /// ```
+///
+/// A route matcher string with a syntax error is reported as a compile error on the attribute,
+/// pointing out where the string stopped parsing.
+/// ```compile_fail
+/// use yew_router_min::Switch;
+///
+/// #[derive(Switch, Clone)]
+/// enum AppRoute {
+///     #[to = "/unclosed/{capture"]
+///     Unclosed { capture: String },
+/// }
+/// ```
+/// The same goes for a capture that doesn't have a corresponding field.
+/// ```compile_fail
+/// use yew_router_min::Switch;
+///
+/// #[derive(Switch, Clone)]
+/// enum AppRoute {
+///     #[to = "/item/{item}"]
+///     Item { id: usize },
+/// }
+/// ```
+/// As does a field that no capture populates, which leaves the variant impossible to produce.
+/// ```compile_fail
+/// use yew_router_min::Switch;
+///
+/// #[derive(Switch, Clone)]
+/// enum AppRoute {
+///     #[to = "/variant"]
+///     Variant(String),
+/// }
+/// ```
+/// Or a matcher with more captures than its struct or variant has fields.
+/// ```compile_fail
+/// use yew_router_min::Switch;
+///
+/// #[derive(Switch, Clone)]
+/// enum AppRoute {
+///     #[to = "/pair/{}/{}"]
+///     Pair(String),
+/// }
+/// ```
+/// Only type parameters are supported, so a struct or enum with a lifetime or const parameter
+/// fails to compile.
+/// ```compile_fail
+/// use yew_router_min::Switch;
+///
+/// #[derive(Switch, Clone)]
+/// #[to = "/user/{name}"]
+/// struct User<'a> {
+///     name: &'a str,
+/// }
+/// ```
+/// ```compile_fail
+/// use yew_router_min::Switch;
+///
+/// #[derive(Switch, Clone)]
+/// #[to = "/page"]
+/// struct Page<const N: usize>;
+/// ```
 /// Check out the examples directory in the repository to see some more usages of the routing syntax.
-#[proc_macro_derive(Switch, attributes(to, rest, end))]
+#[proc_macro_derive(Switch, attributes(to, rest, end, switch))]
 pub fn switch(tokens: TokenStream) -> TokenStream {
     crate::switch::switch_impl(tokens)
 }
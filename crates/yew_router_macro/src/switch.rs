@@ -1,11 +1,15 @@
 use crate::switch::{
-    enum_impl::generate_enum_impl, shadow::ShadowMatcherToken, struct_impl::generate_struct_impl,
+    enum_impl::generate_enum_impl,
+    shadow::{ShadowCaptureVariant, ShadowMatcherToken, ShadowQueryValue},
+    struct_impl::generate_struct_impl,
 };
 use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use std::cmp::Reverse;
 use syn::{
-    export::TokenStream2, parse_macro_input, Data, DeriveInput, Fields, GenericParam, Generics,
-    Ident, Variant,
+    parse_macro_input, Data, DeriveInput, Fields, GenericArgument, GenericParam, Generics, Ident,
+    PathArguments, Type, TypePath, Variant,
 };
 
 mod attribute;
@@ -13,13 +17,16 @@ mod enum_impl;
 mod shadow;
 mod struct_impl;
 
-use self::attribute::AttrToken;
+use self::attribute::{prefix_tokens, AttrToken, Rank, SwitchSettings};
 use syn::punctuated::Punctuated;
 use yew_router_route_parser::FieldNamingScheme;
 
 /// Holds data that is required to derive Switch for a struct or a single enum variant.
 pub struct SwitchItem {
     pub matcher: Vec<ShadowMatcherToken>,
+    /// The route matcher string that the matcher was parsed from, for describing the item.
+    pub matcher_string: String,
+    pub settings: SwitchSettings,
     pub ident: Ident,
     pub fields: Fields,
 }
@@ -37,21 +44,45 @@ pub fn switch_impl(input: TokenStream) -> TokenStream {
                 Fields::Unit => FieldNamingScheme::Unit,
                 Fields::Named(_) => FieldNamingScheme::Named,
             };
-            let matcher = AttrToken::convert_attributes_to_tokens(input.attrs)
-                .into_iter()
-                .enumerate()
-                .map(|(index, at)| at.into_shadow_matcher_tokens(index, field_naming_scheme))
-                .flatten()
-                .collect::<Vec<_>>();
+            let matcher_span = matcher_span(&input.attrs, &ident);
+            let settings = match SwitchSettings::from_attributes(&input.attrs, Default::default()) {
+                Ok(settings) => settings,
+                Err(error) => return error.to_compile_error().into(),
+            };
+            let (mut matcher, matcher_string) =
+                match matcher_from_attributes(input.attrs, field_naming_scheme) {
+                    Ok(matcher) => matcher,
+                    Err(error) => return error.to_compile_error().into(),
+                };
+            if let Fields::Unnamed(_) = ds.fields {
+                name_captures_by_position(&mut matcher);
+            }
+            if let Err(error) =
+                check_captures_match_fields(&matcher, &ds.fields, &ident, matcher_span)
+            {
+                return error.to_compile_error().into();
+            }
+            make_option_query_params_optional(&mut matcher, &ds.fields);
 
             let switch_item = SwitchItem {
                 matcher,
+                matcher_string,
+                settings,
                 ident,
                 fields: ds.fields,
             };
             generate_struct_impl(switch_item, generics)
         }
         Data::Enum(de) => {
+            let (enum_settings, options) = match SwitchSettings::from_enum_attributes(&input.attrs)
+            {
+                Ok(settings) => settings,
+                Err(error) => return error.to_compile_error().into(),
+            };
+            let prefix = match options.prefix.as_ref().map(prefix_tokens).transpose() {
+                Ok(prefix) => prefix,
+                Err(error) => return error.to_compile_error().into(),
+            };
             let switch_variants = de
                 .variants
                 .into_iter()
@@ -61,75 +92,1300 @@ pub fn switch_impl(input: TokenStream) -> TokenStream {
                         Fields::Unit => FieldNamingScheme::Unit,
                         Fields::Named(_) => yew_router_route_parser::FieldNamingScheme::Named,
                     };
-                    let matcher = AttrToken::convert_attributes_to_tokens(variant.attrs)
-                        .into_iter()
-                        .enumerate()
-                        .map(|(index, at)| at.into_shadow_matcher_tokens(index, field_type))
-                        .flatten()
-                        .collect::<Vec<_>>();
-                    SwitchItem {
+                    let matcher_span = matcher_span(&variant.attrs, &variant.ident);
+                    let settings = SwitchSettings::from_attributes(&variant.attrs, enum_settings)?;
+                    let (mut matcher, mut matcher_string) =
+                        matcher_from_attributes(variant.attrs, field_type)?;
+                    if let (Some(prefix), Some(prefix_lit)) = (&prefix, &options.prefix) {
+                        prepend_prefix(
+                            prefix,
+                            &prefix_lit.value(),
+                            &mut matcher,
+                            &mut matcher_string,
+                        );
+                    }
+                    if let Fields::Unnamed(_) = variant.fields {
+                        name_captures_by_position(&mut matcher);
+                    }
+                    check_captures_match_fields(
+                        &matcher,
+                        &variant.fields,
+                        &variant.ident,
+                        matcher_span.clone(),
+                    )?;
+                    make_option_query_params_optional(&mut matcher, &variant.fields);
+                    let switch_item = SwitchItem {
                         matcher,
+                        matcher_string,
+                        settings,
                         ident: variant.ident,
                         fields: variant.fields,
-                    }
+                    };
+                    Ok((switch_item, matcher_span))
                 })
-                .collect::<Vec<SwitchItem>>();
-            generate_enum_impl(ident, switch_variants, generics)
+                .collect::<Vec<syn::Result<(SwitchItem, TokenStream2)>>>();
+            // Every variant is checked, so all of their errors are reported at once.
+            let mut items = vec![];
+            let mut matcher_spans = vec![];
+            let mut errors = vec![];
+            for switch_variant in switch_variants {
+                match switch_variant {
+                    Ok((item, matcher_span)) => {
+                        items.push(item);
+                        matcher_spans.push(matcher_span);
+                    }
+                    Err(error) => errors.push(error),
+                }
+            }
+            if errors.is_empty() && options.rank == Rank::Specificity {
+                errors.extend(check_specificity_ties(&items, &matcher_spans));
+                let (ranked_items, ranked_spans) = rank_by_specificity(items, matcher_spans);
+                items = ranked_items;
+                matcher_spans = ranked_spans;
+            }
+            if errors.is_empty() {
+                errors.extend(check_variants_are_reachable(&items, &matcher_spans));
+            }
+            match combine_errors(errors) {
+                None => generate_enum_impl(ident, items, generics),
+                Some(errors) => errors.to_compile_error().into(),
+            }
+        }
+        Data::Union(du) => {
+            syn::Error::new_spanned(du.union_token, "Switch can't be derived for unions")
+                .to_compile_error()
+                .into()
         }
-        Data::Union(_du) => panic!("Deriving FromCaptures not supported for Unions."),
     }
 }
 
-trait Flatten<T> {
-    /// Because flatten is a nightly feature. I'm making a new variant of the function here for
-    /// stable use. The naming is changed to avoid this getting clobbered when object_flattening
-    /// 60258 is stabilized.
-    fn flatten_stable(self) -> Option<T>;
+/// Parses the `to`, `rest` and `end` attributes of a struct or variant into its matcher,
+/// along with the route matcher string they amount to.
+fn matcher_from_attributes(
+    attributes: Vec<syn::Attribute>,
+    field_naming_scheme: FieldNamingScheme,
+) -> syn::Result<(Vec<ShadowMatcherToken>, String)> {
+    let mut matcher = vec![];
+    let mut matcher_string = String::new();
+    for (index, at) in AttrToken::convert_attributes_to_tokens(attributes)?
+        .into_iter()
+        .enumerate()
+    {
+        matcher_string.push_str(&at.to_matcher_string());
+        matcher.extend(at.into_shadow_matcher_tokens(index, field_naming_scheme)?);
+    }
+    Ok((matcher, matcher_string))
 }
 
-impl<T> Flatten<T> for Option<Option<T>> {
-    fn flatten_stable(self) -> Option<T> {
-        match self {
-            None => None,
-            Some(v) => v,
+/// Prepends the prefix of an enum to the matcher of a variant, and to its route matcher string,
+/// joining the literals where they meet so the variant still starts with a single literal.
+///
+/// A prefix that ends with `/` loses it when the variant starts with one,
+/// so `"/api/"` and `"/users"` make `"/api/users"` rather than `"/api//users"`.
+fn prepend_prefix(
+    prefix: &[ShadowMatcherToken],
+    prefix_string: &str,
+    matcher: &mut Vec<ShadowMatcherToken>,
+    matcher_string: &mut String,
+) {
+    let mut tokens: Vec<ShadowMatcherToken> = prefix.to_vec();
+    let mut prefix_string = prefix_string;
+    let rest = std::mem::take(matcher);
+    let mut rest = rest.into_iter().peekable();
+    if let (Some(ShadowMatcherToken::Exact(literal)), Some(ShadowMatcherToken::Exact(next))) =
+        (tokens.last_mut(), rest.peek())
+    {
+        if literal.ends_with('/') && next.starts_with('/') {
+            literal.pop();
+            prefix_string = prefix_string.strip_suffix('/').unwrap_or(prefix_string);
         }
+        literal.push_str(next);
+        rest.next();
     }
+    tokens.extend(rest);
+    *matcher = tokens;
+    matcher_string.insert_str(0, prefix_string);
 }
 
-fn build_matcher_from_tokens(tokens: &[ShadowMatcherToken]) -> TokenStream2 {
-    quote! {
-        let settings = ::yew_router_min::matcher::MatcherSettings {
-            case_insensitive: true,
+/// Finds the tokens that errors about the matcher of a struct or variant are reported on,
+/// which are its first `to` or `rest` attribute, or its name if it has neither.
+fn matcher_span(attributes: &[syn::Attribute], ident: &Ident) -> TokenStream2 {
+    attributes
+        .iter()
+        .find(|attr| attr.path.is_ident("to") || attr.path.is_ident("rest"))
+        .map_or_else(|| quote! {#ident}, |attr| quote! {#attr})
+}
+
+/// Checks that every capture populates a field, and that every field is populated by a capture,
+/// because a struct or variant that doesn't line up with its matcher can never be produced.
+///
+/// `Option` fields don't need a capture, since they can be `None`.
+/// The captures of tuple structs and variants are expected to already be named by position,
+/// so they are only counted against the fields.
+fn check_captures_match_fields(
+    tokens: &[ShadowMatcherToken],
+    fields: &Fields,
+    ident: &Ident,
+    matcher_span: TokenStream2,
+) -> syn::Result<()> {
+    let names: Vec<Option<&str>> = capture_kinds(tokens)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    let mut errors: Vec<syn::Error> = vec![];
+    match fields {
+        Fields::Named(named_fields) => {
+            let field_names: Vec<String> = named_fields
+                .named
+                .iter()
+                .filter_map(|field| field.ident.as_ref().map(Ident::to_string))
+                .collect();
+            for name in &names {
+                match name {
+                    Some(name) if field_names.iter().any(|field| field == name) => {}
+                    Some(name) => errors.push(syn::Error::new_spanned(
+                        &matcher_span,
+                        format!(
+                            "The capture `{}` doesn't match a field of `{}`",
+                            name, ident
+                        ),
+                    )),
+                    None => errors.push(syn::Error::new_spanned(
+                        &matcher_span,
+                        format!("The captures of `{}` must be named after its fields", ident),
+                    )),
+                }
+            }
+            for field in &named_fields.named {
+                if let Some(field_ident) = &field.ident {
+                    if !names.contains(&Some(field_ident.to_string().as_str()))
+                        && option_inner_type(&field.ty).is_none()
+                    {
+                        errors.push(syn::Error::new_spanned(
+                            field_ident,
+                            format!(
+                                "The field `{}` isn't captured by the matcher of `{}`",
+                                field_ident, ident
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        Fields::Unnamed(unnamed_fields) => {
+            let field_count = unnamed_fields.unnamed.len();
+            if names.len() > field_count {
+                errors.push(syn::Error::new_spanned(
+                    &matcher_span,
+                    format!(
+                        "The matcher of `{}` has {} captures, but it only has {} field{}",
+                        ident,
+                        names.len(),
+                        field_count,
+                        if field_count == 1 { "" } else { "s" }
+                    ),
+                ));
+            }
+            for (index, field) in unnamed_fields.unnamed.iter().enumerate().skip(names.len()) {
+                if option_inner_type(&field.ty).is_none() {
+                    errors.push(syn::Error::new_spanned(
+                        &field.ty,
+                        format!(
+                            "The field `{}` isn't captured by the matcher of `{}`",
+                            index, ident
+                        ),
+                    ));
+                }
+            }
+        }
+        Fields::Unit => {
+            if !names.is_empty() {
+                errors.push(syn::Error::new_spanned(
+                    &matcher_span,
+                    format!(
+                        "The matcher of `{}` has captures, but it has no fields",
+                        ident
+                    ),
+                ));
+            }
+        }
+    }
+
+    combine_errors(errors).map_or(Ok(()), Err)
+}
+
+/// The literals that every route matched by a matcher starts with.
+struct LiteralPrefix {
+    /// Every combination of the alternatives in the literals.
+    alternatives: Vec<String>,
+    /// Whether the literals are matched regardless of case.
+    case_insensitive: bool,
+    /// Whether the matcher only consists of the literals, so they are all it needs to match.
+    complete: bool,
+    /// Whether the matcher ends with `!`, so nothing can follow the literals.
+    end: bool,
+}
+
+impl LiteralPrefix {
+    fn new(tokens: &[ShadowMatcherToken], case_insensitive: bool) -> Self {
+        let normalize = |literal: &str| {
+            if case_insensitive {
+                literal.to_lowercase()
+            } else {
+                literal.to_string()
+            }
         };
-        let matcher = ::yew_router_min::matcher::RouteMatcher {
-            tokens: ::std::vec![#(#tokens),*],
-            settings
+        let mut prefix = LiteralPrefix {
+            alternatives: vec![String::new()],
+            case_insensitive,
+            complete: true,
+            end: false,
+        };
+        for (index, token) in tokens.iter().enumerate() {
+            match token {
+                ShadowMatcherToken::Exact(literal) => {
+                    let literal = normalize(literal);
+                    for alternative in &mut prefix.alternatives {
+                        alternative.push_str(&literal);
+                    }
+                }
+                ShadowMatcherToken::OneOf(literals) => {
+                    prefix.alternatives = prefix
+                        .alternatives
+                        .iter()
+                        .flat_map(|alternative| {
+                            literals
+                                .iter()
+                                .map(move |literal| alternative.clone() + &normalize(literal))
+                        })
+                        .collect();
+                    // The longest alternative that matches is used without backtracking,
+                    // so a shorter one isn't tried when the tokens after it don't match.
+                    let is_last = tokens[index + 1..]
+                        .iter()
+                        .all(|token| matches!(token, ShadowMatcherToken::End));
+                    if !is_last {
+                        prefix.complete = false;
+                        break;
+                    }
+                }
+                ShadowMatcherToken::End => prefix.end = true,
+                ShadowMatcherToken::Capture(_)
+                | ShadowMatcherToken::Optional(_)
+                | ShadowMatcherToken::Query { .. } => {
+                    prefix.complete = false;
+                    break;
+                }
+            }
+        }
+        prefix
+    }
+
+    /// Whether a route could start with the literals of both matchers.
+    fn overlaps(&self, other: &LiteralPrefix) -> bool {
+        let case_insensitive = self.case_insensitive || other.case_insensitive;
+        let normalize = |literal: &str| {
+            if case_insensitive {
+                literal.to_lowercase()
+            } else {
+                literal.to_string()
+            }
+        };
+        self.alternatives.iter().any(|alternative| {
+            let alternative = normalize(alternative);
+            other.alternatives.iter().any(|other| {
+                let other = normalize(other);
+                alternative.starts_with(&other) || other.starts_with(&alternative)
+            })
+        })
+    }
+}
+
+/// A piece of a matcher that is certain to match, regardless of how the fields convert.
+enum CoveringPart {
+    /// Literals, one of which has to match.
+    Literals(Vec<String>),
+    /// A capture of a section into a `String` field, which converts from any capture.
+    Capture,
+}
+
+/// A matcher that matches routes regardless of how its fields convert,
+/// for telling whether it shadows the variants after it.
+struct Covering {
+    parts: Vec<CoveringPart>,
+    /// Whether the literals are matched regardless of case.
+    case_insensitive: bool,
+    /// Whether the matcher ends with `!`, so nothing can follow the parts.
+    end: bool,
+}
+
+impl Covering {
+    /// Returns `None` if the matcher might not match a route that it looks like it does,
+    /// like when it has a constrained capture, or a capture into a field that might not convert.
+    fn new(item: &SwitchItem) -> Option<Self> {
+        let case_insensitive = item.settings.case_insensitive;
+        let normalize = |literal: &str| {
+            if case_insensitive {
+                literal.to_lowercase()
+            } else {
+                literal.to_string()
+            }
+        };
+        let mut covering = Covering {
+            parts: vec![],
+            case_insensitive,
+            end: false,
+        };
+        for (index, token) in item.matcher.iter().enumerate() {
+            let part = match token {
+                ShadowMatcherToken::Exact(literal) => {
+                    CoveringPart::Literals(vec![normalize(literal)])
+                }
+                ShadowMatcherToken::OneOf(literals) => {
+                    // The longest alternative that matches is used without backtracking,
+                    // so a shorter one isn't tried when the tokens after it don't match.
+                    let is_last = item.matcher[index + 1..]
+                        .iter()
+                        .all(|token| matches!(token, ShadowMatcherToken::End));
+                    if !is_last {
+                        return None;
+                    }
+                    CoveringPart::Literals(
+                        literals.iter().map(|literal| normalize(literal)).collect(),
+                    )
+                }
+                ShadowMatcherToken::Capture(ShadowCaptureVariant::Named(name)) if matches!(captured_field_type(&item.fields, name), Some(ty) if is_string(ty)) => {
+                    CoveringPart::Capture
+                }
+                ShadowMatcherToken::End => {
+                    covering.end = true;
+                    continue;
+                }
+                ShadowMatcherToken::Capture(_)
+                | ShadowMatcherToken::Optional(_)
+                | ShadowMatcherToken::Query { .. } => return None,
+            };
+            covering.parts.push(part);
+        }
+        Some(covering)
+    }
+
+    /// Whether every route that a matcher with the prefix matches is also matched by this one.
+    fn covers(&self, prefix: &LiteralPrefix) -> bool {
+        // A case sensitive matcher doesn't match the routes that differ only by case from its
+        // literals, which a case insensitive one does.
+        if !self.case_insensitive && prefix.case_insensitive {
+            return false;
+        }
+        let whole = prefix.complete && prefix.end;
+        prefix.alternatives.iter().all(|alternative| {
+            if self.case_insensitive {
+                self.matches(&self.parts, &alternative.to_lowercase(), whole)
+            } else {
+                self.matches(&self.parts, alternative, whole)
+            }
+        })
+    }
+
+    /// Whether the parts match every route that starts with the rest,
+    /// or only the rest itself if it is the whole route.
+    fn matches(&self, parts: &[CoveringPart], rest: &str, whole: bool) -> bool {
+        match parts {
+            [] => !self.end || (whole && rest.is_empty()),
+            [CoveringPart::Literals(literals), parts @ ..] => literals.iter().any(|literal| {
+                rest.starts_with(literal.as_str())
+                    && self.matches(parts, &rest[literal.len()..], whole)
+            }),
+            [CoveringPart::Capture, parts @ ..] => match parts.first() {
+                // The capture takes everything up to where the literals after it first match,
+                // which has to be within the rest, since the route could continue with anything.
+                Some(CoveringPart::Literals(literals)) => {
+                    let delimiter = rest.char_indices().map(|(index, _)| index).find(|&index| {
+                        literals
+                            .iter()
+                            .any(|literal| rest[index..].starts_with(literal.as_str()))
+                    });
+                    match delimiter {
+                        Some(index) if index > 0 => self.matches(parts, &rest[index..], whole),
+                        _ => false,
+                    }
+                }
+                Some(CoveringPart::Capture) => false,
+                // A capture at the end of the route takes the rest of it.
+                None if self.end => !rest.is_empty(),
+                // Otherwise it takes the characters up to the first one that can't be captured,
+                // and anything after that is ignored.
+                None => matches!(rest.chars().next(), Some(c) if !" */#&?{}=".contains(c)),
+            },
+        }
+    }
+}
+
+/// How specifically a matcher matches routes, where greater is more specific.
+///
+/// The fields are compared in order, so literal segments outweigh everything else,
+/// and a `!` only breaks ties.
+/// Optional sections aren't counted, since they aren't needed to match.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+struct Specificity {
+    literal_segments: usize,
+    /// Captures of any number of sections (`{*}`) are the least specific,
+    /// so having fewer of them is more specific.
+    many_captures: Reverse<usize>,
+    fixed_captures: usize,
+    constrained_captures: usize,
+    end: bool,
+}
+
+impl Specificity {
+    fn new(tokens: &[ShadowMatcherToken]) -> Self {
+        let mut specificity = Specificity {
+            literal_segments: 0,
+            many_captures: Reverse(0),
+            fixed_captures: 0,
+            constrained_captures: 0,
+            end: false,
+        };
+        for token in tokens {
+            match token {
+                ShadowMatcherToken::Exact(literal) => {
+                    specificity.literal_segments += literal
+                        .split(&['/', '#'][..])
+                        .filter(|segment| !segment.is_empty())
+                        .count()
+                }
+                ShadowMatcherToken::OneOf(_) => specificity.literal_segments += 1,
+                ShadowMatcherToken::Capture(capture) => specificity.add_capture(capture),
+                ShadowMatcherToken::Query { params, .. } => {
+                    for param in params {
+                        match &param.value {
+                            ShadowQueryValue::Exact(_) => specificity.literal_segments += 1,
+                            ShadowQueryValue::Capture(capture) => specificity.add_capture(capture),
+                        }
+                    }
+                }
+                ShadowMatcherToken::End => specificity.end = true,
+                ShadowMatcherToken::Optional(_) => {}
+            }
+        }
+        specificity
+    }
+
+    fn add_capture(&mut self, capture: &ShadowCaptureVariant) {
+        match capture {
+            ShadowCaptureVariant::ManyNamed(_) | ShadowCaptureVariant::ManyUnnamed => {
+                self.many_captures.0 += 1
+            }
+            ShadowCaptureVariant::Constrained { .. } => {
+                self.fixed_captures += 1;
+                self.constrained_captures += 1;
+            }
+            ShadowCaptureVariant::Named(_)
+            | ShadowCaptureVariant::Unnamed
+            | ShadowCaptureVariant::NumberedNamed { .. }
+            | ShadowCaptureVariant::NumberedUnnamed { .. } => self.fixed_captures += 1,
+        }
+    }
+}
+
+/// Checks that no two variants are equally specific, unless their leading literals show that
+/// they never match the same route, since there would be no telling which of them to try first.
+fn check_specificity_ties(items: &[SwitchItem], matcher_spans: &[TokenStream2]) -> Vec<syn::Error> {
+    let ranked: Vec<(Specificity, LiteralPrefix)> = items
+        .iter()
+        .map(|item| {
+            (
+                Specificity::new(&item.matcher),
+                LiteralPrefix::new(&item.matcher, item.settings.case_insensitive),
+            )
+        })
+        .collect();
+    let mut errors = vec![];
+    for (index, (item, (specificity, prefix))) in items.iter().zip(&ranked).enumerate() {
+        let tied =
+            items[..index]
+                .iter()
+                .zip(&ranked)
+                .find(|(_, (other_specificity, other_prefix))| {
+                    other_specificity == specificity && other_prefix.overlaps(prefix)
+                });
+        if let Some((tied_item, _)) = tied {
+            errors.push(syn::Error::new_spanned(
+                &matcher_spans[index],
+                format!(
+                    "The variants `{}` and `{}` are equally specific, so neither is tried first. \
+                     Make one of them more specific, like by ending its matcher with `!`",
+                    tied_item.ident, item.ident
+                ),
+            ));
+        }
+    }
+    errors
+}
+
+/// Sorts the variants so that the most specific ones are tried first.
+fn rank_by_specificity(
+    items: Vec<SwitchItem>,
+    matcher_spans: Vec<TokenStream2>,
+) -> (Vec<SwitchItem>, Vec<TokenStream2>) {
+    let mut ranked: Vec<(SwitchItem, TokenStream2)> =
+        items.into_iter().zip(matcher_spans).collect();
+    ranked.sort_by_key(|(item, _)| Reverse(Specificity::new(&item.matcher)));
+    ranked.into_iter().unzip()
+}
+
+/// Checks that no variant is shadowed by an earlier one, which matches every route that it does.
+///
+/// Only variants whose matchers are made of literals and captures of single sections into `String`
+/// fields are considered to shadow others, because they match regardless of how any fields convert.
+/// Variants with `#[switch(allow_shadowed)]` aren't checked.
+fn check_variants_are_reachable(
+    items: &[SwitchItem],
+    matcher_spans: &[TokenStream2],
+) -> Vec<syn::Error> {
+    let coverings: Vec<Option<Covering>> = items.iter().map(Covering::new).collect();
+    let mut errors = vec![];
+    for (index, item) in items.iter().enumerate() {
+        if item.settings.allow_shadowed {
+            continue;
+        }
+        let prefix = LiteralPrefix::new(&item.matcher, item.settings.case_insensitive);
+        let shadowing =
+            items[..index]
+                .iter()
+                .zip(&coverings)
+                .find_map(|(earlier_item, earlier)| {
+                    earlier
+                        .as_ref()
+                        .filter(|earlier| earlier.covers(&prefix))
+                        .map(|earlier| (earlier_item, earlier))
+                });
+        if let Some((earlier_item, earlier)) = shadowing {
+            let suggestion = if earlier.end {
+                format!(
+                    "Remove `{}`, or narrow the matcher of `{}`",
+                    item.ident, earlier_item.ident
+                )
+            } else {
+                format!(
+                    "Add `!` to the end of the matcher of `{}` so it only matches whole routes",
+                    earlier_item.ident
+                )
+            };
+            errors.push(syn::Error::new_spanned(
+                &matcher_spans[index],
+                format!(
+                    "The variant `{}` can never be matched, because the earlier variant `{}` \
+                     matches every route that it does. {}",
+                    item.ident, earlier_item.ident, suggestion
+                ),
+            ));
+        }
+    }
+    errors
+}
+
+/// Combines the errors into one, so that all of them are reported at once.
+fn combine_errors(errors: impl IntoIterator<Item = syn::Error>) -> Option<syn::Error> {
+    let mut errors = errors.into_iter();
+    let mut combined = errors.next()?;
+    for error in errors {
+        combined.combine(error);
+    }
+    Some(combined)
+}
+
+/// Names the captures of a tuple struct or variant after the positions of the fields they populate.
+///
+/// This lets the fields be looked up by name, which keeps them lined up with their captures
+/// when an optional section is skipped.
+fn name_captures_by_position(tokens: &mut [ShadowMatcherToken]) {
+    fn name_capture(capture: &mut ShadowCaptureVariant, position: &mut usize) {
+        use ShadowCaptureVariant as SCV;
+        let name = position.to_string();
+        *position += 1;
+        *capture = match std::mem::replace(capture, SCV::Unnamed) {
+            SCV::Named(_) | SCV::Unnamed => SCV::Named(name),
+            SCV::ManyNamed(_) | SCV::ManyUnnamed => SCV::ManyNamed(name),
+            SCV::NumberedNamed { sections, .. } | SCV::NumberedUnnamed { sections } => {
+                SCV::NumberedNamed { sections, name }
+            }
+            SCV::Constrained { constraint, .. } => SCV::Constrained { name, constraint },
+        };
+    }
+    fn name_captures(tokens: &mut [ShadowMatcherToken], position: &mut usize) {
+        for token in tokens {
+            match token {
+                ShadowMatcherToken::Capture(capture) => name_capture(capture, position),
+                ShadowMatcherToken::Optional(section) => name_captures(section, position),
+                ShadowMatcherToken::Query { params, .. } => {
+                    for param in params {
+                        if let ShadowQueryValue::Capture(capture) = &mut param.value {
+                            name_capture(capture, position)
+                        }
+                    }
+                }
+                ShadowMatcherToken::Exact(_)
+                | ShadowMatcherToken::OneOf(_)
+                | ShadowMatcherToken::End => {}
+            }
+        }
+    }
+    name_captures(tokens, &mut 0)
+}
+
+/// Makes the query parameters that are captured into `Option` fields optional,
+/// so the route still matches when they are left out, and the fields are `None`.
+fn make_option_query_params_optional(tokens: &mut [ShadowMatcherToken], fields: &Fields) {
+    for token in tokens {
+        if let ShadowMatcherToken::Query { params, .. } = token {
+            for param in params {
+                let name = match &param.value {
+                    ShadowQueryValue::Capture(ShadowCaptureVariant::Named(name))
+                    | ShadowQueryValue::Capture(ShadowCaptureVariant::Constrained {
+                        name, ..
+                    }) => name,
+                    _ => continue,
+                };
+                param.optional = captured_field_type(fields, name)
+                    .and_then(option_inner_type)
+                    .is_some();
+            }
+        }
+    }
+}
+
+/// Finds the type of the field that the capture with the name populates.
+///
+/// The captures of tuple structs and variants are expected to already be named by position.
+fn captured_field_type<'a>(fields: &'a Fields, name: &str) -> Option<&'a Type> {
+    match fields {
+        Fields::Named(named_fields) => named_fields
+            .named
+            .iter()
+            .find(|field| matches!(&field.ident, Some(ident) if ident == name))
+            .map(|field| &field.ty),
+        Fields::Unnamed(unnamed_fields) => name
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| unnamed_fields.unnamed.iter().nth(index))
+            .map(|field| &field.ty),
+        Fields::Unit => None,
+    }
+}
+
+/// Lists the tokens of the matcher, with the contents of each optional section in place of it.
+fn flatten_tokens(tokens: &[ShadowMatcherToken]) -> Vec<&ShadowMatcherToken> {
+    tokens
+        .iter()
+        .flat_map(|token| match token {
+            ShadowMatcherToken::Optional(section) => section.iter().collect(),
+            token => vec![token],
+        })
+        .collect()
+}
+
+/// The part of the route that a capture is taken from.
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Path,
+    Query,
+    Fragment,
+}
+
+/// Lists every capture in the matcher, in order, along with the section of the route it is in.
+///
+/// This includes the captures within optional sections and the query.
+/// The section is tracked by looking for the `#` character in the literals before the capture.
+fn captures(tokens: &[ShadowMatcherToken]) -> Vec<(&ShadowCaptureVariant, Section)> {
+    let mut section = Section::Path;
+    let mut captures = vec![];
+    for token in flatten_tokens(tokens) {
+        match token {
+            ShadowMatcherToken::Exact(literal) => {
+                if literal.contains('#') {
+                    section = Section::Fragment;
+                }
+            }
+            ShadowMatcherToken::Capture(capture) => captures.push((capture, section)),
+            ShadowMatcherToken::Query { params, .. } => {
+                section = Section::Query;
+                captures.extend(params.iter().filter_map(|param| match &param.value {
+                    ShadowQueryValue::Capture(capture) => Some((capture, Section::Query)),
+                    ShadowQueryValue::Exact(_) => None,
+                }));
+            }
+            ShadowMatcherToken::OneOf(_)
+            | ShadowMatcherToken::End
+            | ShadowMatcherToken::Optional(_) => {}
+        }
+    }
+    captures
+}
+
+/// Gets `T` from a field type that is spelled `Option<T>`.
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    wrapped_type(ty, "Option")
+}
+
+/// Gets `T` from a field type that is spelled `Vec<T>`.
+fn vec_inner_type(ty: &Type) -> Option<&Type> {
+    wrapped_type(ty, "Vec")
+}
+
+/// Whether the field type is spelled `String`.
+fn is_string(ty: &Type) -> bool {
+    match ty {
+        Type::Path(TypePath { qself: None, path }) => matches!(
+            path.segments.last(),
+            Some(segment) if segment.ident == "String" && segment.arguments.is_empty()
+        ),
+        _ => false,
+    }
+}
+
+/// Gets `T` from a field type that is spelled `Wrapper<T>`.
+fn wrapped_type<'a>(ty: &'a Type, wrapper: &str) -> Option<&'a Type> {
+    let segment = match ty {
+        Type::Path(TypePath { qself: None, path }) => path.segments.last()?,
+        _ => return None,
+    };
+    match &segment.arguments {
+        PathArguments::AngleBracketed(arguments)
+            if segment.ident == wrapper && arguments.args.len() == 1 =>
+        {
+            match arguments.args.first()? {
+                GenericArgument::Type(inner) => Some(inner),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Creates an expression that converts the capture with the key into a field of the type,
+/// returning a `SwitchFailure` from the enclosing closure if that isn't possible.
+///
+/// An `Option` field is instead `None` if its capture is missing,
+/// which happens when it is in an optional section that was skipped.
+///
+/// A `Vec` field is populated from a repeated query capture, which joins its values with `&`
+/// as they appear in the route, so each element is only decoded once it has been split apart.
+/// The field is empty if the capture is missing.
+fn field_from_capture(key: &str, field_ty: &Type, kind: &TokenStream2) -> TokenStream2 {
+    let failure = |capture: TokenStream2| {
+        quote! {
+            return ::std::result::Result::Err(::yew_router_min::route::SwitchFailure::Field {
+                field: #key,
+                capture: #capture,
+            })
+        }
+    };
+    let failed_conversion =
+        failure(quote! {::std::option::Option::Some(::std::string::ToString::to_string(&value))});
+    if let Some(element_ty) = vec_inner_type(field_ty) {
+        return quote! {
+            match captures.remove(#key) {
+                ::std::option::Option::Some(value) => {
+                    let elements = value
+                        .split('&')
+                        .map(|element| {
+                            <#element_ty as ::yew_router_min::Switch>::from_capture(element, #kind)
+                        })
+                        .collect::<::std::option::Option<::std::vec::Vec<#element_ty>>>();
+                    match elements {
+                        ::std::option::Option::Some(val) => val,
+                        ::std::option::Option::None => #failed_conversion,
+                    }
+                }
+                ::std::option::Option::None => ::std::vec::Vec::new(),
+            }
         };
     }
+    let (field_ty, found, missing) = match option_inner_type(field_ty) {
+        Some(inner_ty) => (
+            inner_ty,
+            quote! {::std::option::Option::Some(val)},
+            quote! {::std::option::Option::None},
+        ),
+        None => (
+            field_ty,
+            quote! {val},
+            failure(quote! {::std::option::Option::None}),
+        ),
+    };
+    quote! {
+        match captures.remove(#key) {
+            ::std::option::Option::Some(value) => {
+                match <#field_ty as ::yew_router_min::Switch>::from_capture(&value, #kind) {
+                    ::std::option::Option::Some(val) => #found,
+                    ::std::option::Option::None => #failed_conversion,
+                }
+            }
+            ::std::option::Option::None => #missing,
+        }
+    }
+}
+
+/// Creates an expression that constructs the `RouteMatcher` for the provided tokens.
+///
+/// This is used to initialize the thread local matchers shared by the derived `from_path` and `try_from_path`,
+/// so the tokens are only allocated the first time a route is matched against them.
+fn build_matcher_from_tokens(
+    tokens: &[ShadowMatcherToken],
+    settings: SwitchSettings,
+) -> TokenStream2 {
+    let SwitchSettings {
+        case_insensitive,
+        lowercase_captures,
+        ..
+    } = settings;
+    quote! {
+        ::yew_router_min::matcher::RouteMatcher {
+            tokens: ::std::vec![#(#tokens),*],
+            settings: ::yew_router_min::matcher::MatcherSettings {
+                case_insensitive: #case_insensitive,
+                decode_captures: false,
+                lowercase_captures: #lowercase_captures,
+            },
+        }
+    }
+}
+
+/// Determines the `CaptureKind` of every capture in the matcher, in order,
+/// along with the name of the capture, if it has one.
+///
+/// The kind depends on whether the capture is in the path, query or fragment.
+fn capture_kinds(tokens: &[ShadowMatcherToken]) -> Vec<(Option<&str>, TokenStream2)> {
+    captures(tokens)
+        .into_iter()
+        .map(|(capture, section)| {
+            let (name, many) = match capture {
+                ShadowCaptureVariant::Named(name)
+                | ShadowCaptureVariant::Constrained { name, .. } => (Some(name.as_str()), false),
+                ShadowCaptureVariant::ManyNamed(name)
+                | ShadowCaptureVariant::NumberedNamed { name, .. } => (Some(name.as_str()), true),
+                ShadowCaptureVariant::Unnamed => (None, false),
+                ShadowCaptureVariant::ManyUnnamed
+                | ShadowCaptureVariant::NumberedUnnamed { .. } => (None, true),
+            };
+            let kind = match section {
+                Section::Path if many => quote! {Segments},
+                Section::Path | Section::Fragment => quote! {Segment},
+                Section::Query => quote! {Query},
+            };
+            (name, quote! {::yew_router_min::matcher::CaptureKind::#kind})
+        })
+        .collect()
+}
+
+/// Determines the `CaptureKind` that is used to convert each field from its capture.
+///
+/// Fields are paired with captures the same way `capture_bindings` pairs them.
+/// Fields without a capture never get converted, but are given a kind all the same.
+fn field_capture_kinds(tokens: &[ShadowMatcherToken], fields: &Fields) -> Vec<TokenStream2> {
+    let kinds = capture_kinds(tokens);
+    let segment = quote! {::yew_router_min::matcher::CaptureKind::Segment};
+    match fields {
+        Fields::Named(named_fields) => named_fields
+            .named
+            .iter()
+            .map(|field| {
+                kinds
+                    .iter()
+                    .find(|(name, _)| {
+                        field.ident.as_ref().map(Ident::to_string).as_deref() == *name
+                    })
+                    .map_or_else(|| segment.clone(), |(_, kind)| kind.clone())
+            })
+            .collect(),
+        Fields::Unnamed(unnamed_fields) => (0..unnamed_fields.unnamed.len())
+            .map(|index| {
+                kinds
+                    .get(index)
+                    .map_or_else(|| segment.clone(), |(_, kind)| kind.clone())
+            })
+            .collect(),
+        Fields::Unit => vec![],
+    }
+}
+
+/// Creates the `from_capture` and `to_capture` methods of a derived implementation.
+///
+/// A derived type matches a capture as a route in its own right, so it is passed on without being
+/// decoded, leaving any decoding to the fields that finally consume it.
+fn capture_methods() -> TokenStream2 {
+    quote! {
+        fn from_capture(
+            capture: &str,
+            _kind: ::yew_router_min::matcher::CaptureKind,
+        ) -> ::std::option::Option<Self> {
+            <Self as ::yew_router_min::Switch>::from_path(capture)
+        }
+
+        fn to_capture(
+            &self,
+            _kind: ::yew_router_min::matcher::CaptureKind,
+        ) -> ::std::string::String {
+            <Self as ::yew_router_min::Switch>::to_path(self)
+        }
+    }
+}
+
+/// Creates an expression that describes a struct or variant as a `RouteInfo`,
+/// which the derived `Switch::routes` lists.
+fn route_info(item: &SwitchItem) -> TokenStream2 {
+    let name = item.ident.to_string();
+    let matcher_string = &item.matcher_string;
+    let end = item
+        .matcher
+        .iter()
+        .any(|token| matches!(token, ShadowMatcherToken::End));
+    let captures = captures(&item.matcher)
+        .into_iter()
+        .filter_map(|(capture, _)| {
+            let (name, kind) = match capture {
+                ShadowCaptureVariant::Named(name)
+                | ShadowCaptureVariant::Constrained { name, .. } => (name, quote! {Named}),
+                ShadowCaptureVariant::ManyNamed(name) => (name, quote! {ManyNamed}),
+                ShadowCaptureVariant::NumberedNamed { sections, name } => {
+                    (name, quote! {NumberedNamed { sections: #sections }})
+                }
+                // Captures that populate fields are always named by now.
+                ShadowCaptureVariant::Unnamed
+                | ShadowCaptureVariant::ManyUnnamed
+                | ShadowCaptureVariant::NumberedUnnamed { .. } => return None,
+            };
+            let field_ty = captured_field_type(&item.fields, name)?;
+            let field_type = type_name(field_ty);
+            let switch_ty = option_inner_type(field_ty)
+                .or_else(|| vec_inner_type(field_ty))
+                .unwrap_or(field_ty);
+            Some(quote! {
+                ::yew_router_min::route::CaptureInfo {
+                    name: #name,
+                    kind: ::yew_router_min::route::CaptureInfoKind::#kind,
+                    field_type: #field_type,
+                    nested: <#switch_ty as ::yew_router_min::Switch>::routes,
+                }
+            })
+        });
+    quote! {
+        ::yew_router_min::route::RouteInfo {
+            name: #name,
+            matcher: #matcher_string,
+            captures: &[#(#captures),*],
+            end: #end,
+        }
+    }
+}
+
+/// Writes a type the way it would usually be spelled,
+/// without the spaces that separate all of its tokens when they are printed.
+fn type_name(ty: &Type) -> String {
+    let is_ident_char = |c: char| c.is_alphanumeric() || c == '_';
+    let spaced = quote! {#ty}.to_string();
+    let mut name = String::new();
+    let mut chars = spaced.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ' ' {
+            let previous = name.chars().last();
+            let separates_idents = matches!(
+                (previous, chars.peek()),
+                (Some(previous), Some(next)) if is_ident_char(previous) && is_ident_char(*next)
+            );
+            if !separates_idents && previous != Some(',') {
+                continue;
+            }
+        }
+        name.push(c);
+    }
+    name
+}
+
+/// Creates a pattern that destructures a struct or variant at `path`,
+/// binding only the fields that `build_path_from_tokens` will write into the route.
+fn destructure_fields(
+    path: TokenStream2,
+    tokens: &[ShadowMatcherToken],
+    fields: &Fields,
+) -> TokenStream2 {
+    let bindings = field_bindings(tokens, fields);
+    match fields {
+        Fields::Named(_) => {
+            let bindings = bindings.into_iter().flatten();
+            quote! { #path { #(#bindings,)* .. } }
+        }
+        Fields::Unnamed(_) => {
+            let bindings = bindings
+                .into_iter()
+                .map(|binding| binding.map_or_else(|| quote! {_}, |ident| quote! {#ident}));
+            quote! { #path ( #(#bindings),* ) }
+        }
+        Fields::Unit => path,
+    }
+}
+
+/// Pairs every capture in the matcher with the binding for the field that it populates.
+///
+/// Named fields are found by the name of the capture, while unnamed fields are assigned to
+/// captures in order, left to right - mirroring how `from_path` populates them.
+/// Captures without a corresponding field are paired with `None`.
+fn capture_bindings(tokens: &[ShadowMatcherToken], fields: &Fields) -> Vec<Option<Ident>> {
+    let mut unnamed_index = 0;
+    captures(tokens)
+        .into_iter()
+        .map(|(capture, _)| match fields {
+            Fields::Named(named_fields) => {
+                let name = match capture {
+                    ShadowCaptureVariant::Named(name)
+                    | ShadowCaptureVariant::ManyNamed(name)
+                    | ShadowCaptureVariant::NumberedNamed { name, .. }
+                    | ShadowCaptureVariant::Constrained { name, .. } => name.as_str(),
+                    ShadowCaptureVariant::Unnamed
+                    | ShadowCaptureVariant::ManyUnnamed
+                    | ShadowCaptureVariant::NumberedUnnamed { .. } => return None,
+                };
+                named_fields
+                    .named
+                    .iter()
+                    .filter_map(|field| field.ident.clone())
+                    .find(|ident| ident == name)
+            }
+            Fields::Unnamed(unnamed_fields) => {
+                let index = unnamed_index;
+                unnamed_index += 1;
+                if index < unnamed_fields.unnamed.len() {
+                    Some(format_ident!("field_{}", index))
+                } else {
+                    None
+                }
+            }
+            Fields::Unit => None,
+        })
+        .collect()
+}
+
+/// Lists the bindings used when destructuring the fields.
+///
+/// For named fields, only the fields that appear in the matcher are listed.
+/// For unnamed fields, every field is listed, with `None` for fields that don't get written.
+fn field_bindings(tokens: &[ShadowMatcherToken], fields: &Fields) -> Vec<Option<Ident>> {
+    let captured = capture_bindings(tokens, fields)
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    match fields {
+        Fields::Named(_) => {
+            let mut bindings: Vec<Option<Ident>> = vec![];
+            for ident in captured {
+                if !bindings.contains(&Some(ident.clone())) {
+                    bindings.push(Some(ident))
+                }
+            }
+            bindings
+        }
+        Fields::Unnamed(unnamed_fields) => (0..unnamed_fields.unnamed.len())
+            .map(|index| format_ident!("field_{}", index))
+            .map(|ident| {
+                if captured.contains(&ident) {
+                    Some(ident)
+                } else {
+                    None
+                }
+            })
+            .collect(),
+        Fields::Unit => vec![],
+    }
+}
+
+/// Creates the statements that write the route for a struct or variant into `buf`.
+///
+/// Literals are written as they appear in the matcher, with the first of any alternatives,
+/// and every capture is replaced by the
+/// `Switch::to_capture` representation of its field, the bindings for which are established by
+/// `destructure_fields`. `Option` fields that are `None` are left out.
+///
+/// An optional section is only written if it has a capture for a field, and all of the `Option`
+/// fields captured within it are `Some`.
+/// Likewise, a query parameter is left out if the `Option` field it captures is `None`,
+/// and is repeated for every element of a `Vec` field.
+fn build_path_from_tokens(tokens: &[ShadowMatcherToken], fields: &Fields) -> TokenStream2 {
+    let mut bindings = capture_bindings(tokens, fields)
+        .into_iter()
+        .zip(capture_kinds(tokens).into_iter().map(|(_, kind)| kind))
+        .collect::<Vec<_>>()
+        .into_iter();
+    let writes = tokens
+        .iter()
+        .map(|token| build_path_from_token(token, fields, &mut bindings))
+        .collect::<Vec<_>>();
+    quote! {
+        #(#writes)*
+    }
+}
+
+fn build_path_from_token(
+    token: &ShadowMatcherToken,
+    fields: &Fields,
+    bindings: &mut std::vec::IntoIter<(Option<Ident>, TokenStream2)>,
+) -> TokenStream2 {
+    let is_option = |binding: &Ident| {
+        binding_type(fields, binding)
+            .and_then(option_inner_type)
+            .is_some()
+    };
+    let is_option_or_vec = |binding: &Ident| {
+        is_option(binding)
+            || binding_type(fields, binding)
+                .and_then(vec_inner_type)
+                .is_some()
+    };
+    match token {
+        ShadowMatcherToken::Exact(literal) => quote! {
+            buf.push_str(#literal);
+        },
+        ShadowMatcherToken::OneOf(alternatives) => {
+            let first = &alternatives[0];
+            quote! {
+                buf.push_str(#first);
+            }
+        }
+        ShadowMatcherToken::Capture(_) => match bindings.next() {
+            Some((Some(binding), kind)) if is_option(&binding) => quote! {
+                if let ::std::option::Option::Some(#binding) = #binding {
+                    buf.push_str(&::yew_router_min::Switch::to_capture(#binding, #kind));
+                }
+            },
+            Some((Some(binding), kind)) => quote! {
+                buf.push_str(&::yew_router_min::Switch::to_capture(#binding, #kind));
+            },
+            _ => quote! {},
+        },
+        ShadowMatcherToken::End => quote! {},
+        ShadowMatcherToken::Query { params, .. } => {
+            let count = params.len();
+            let pairs = params
+                .iter()
+                .map(|param| {
+                    let key = &param.key;
+                    match &param.value {
+                        ShadowQueryValue::Exact(literal) => {
+                            let pair = format!("{}={}", key, literal);
+                            quote! {::std::vec![::std::string::String::from(#pair)]}
+                        }
+                        ShadowQueryValue::Capture(_) => match bindings.next() {
+                            Some((Some(binding), kind)) if is_option_or_vec(&binding) => quote! {
+                                #binding
+                                    .iter()
+                                    .map(|value| ::std::format!(
+                                        "{}={}",
+                                        #key,
+                                        ::yew_router_min::Switch::to_capture(value, #kind)
+                                    ))
+                                    .collect()
+                            },
+                            Some((Some(binding), kind)) => quote! {
+                                ::std::vec![::std::format!(
+                                    "{}={}",
+                                    #key,
+                                    ::yew_router_min::Switch::to_capture(#binding, #kind)
+                                )]
+                            },
+                            _ => quote! {::std::vec::Vec::new()},
+                        },
+                    }
+                })
+                .collect::<Vec<_>>();
+            quote! {
+                let pairs: [::std::vec::Vec<::std::string::String>; #count] = [#(#pairs),*];
+                for (index, pair) in pairs.iter().flatten().enumerate() {
+                    buf.push(if index == 0 { '?' } else { '&' });
+                    buf.push_str(pair);
+                }
+            }
+        }
+        ShadowMatcherToken::Optional(section) => {
+            let section_bindings = bindings.take(captures(section).len()).collect::<Vec<_>>();
+            let option_bindings = section_bindings
+                .iter()
+                .filter_map(|(binding, _)| binding.as_ref())
+                .filter(|binding| is_option(binding))
+                .cloned()
+                .collect::<Vec<_>>();
+            if section_bindings
+                .iter()
+                .all(|(binding, _)| binding.is_none())
+            {
+                return quote! {};
+            }
+
+            let mut section_bindings = section_bindings.into_iter();
+            let writes = section
+                .iter()
+                .map(|token| build_path_from_token(token, fields, &mut section_bindings))
+                .collect::<Vec<_>>();
+            if option_bindings.is_empty() {
+                quote! {
+                    #(#writes)*
+                }
+            } else {
+                quote! {
+                    if #(#option_bindings.is_some())&&* {
+                        #(#writes)*
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Gets the type of the field that was destructured into the binding.
+fn binding_type<'a>(fields: &'a Fields, binding: &Ident) -> Option<&'a Type> {
+    match fields {
+        Fields::Named(named_fields) => named_fields
+            .named
+            .iter()
+            .find(|field| field.ident.as_ref() == Some(binding))
+            .map(|field| &field.ty),
+        Fields::Unnamed(unnamed_fields) => unnamed_fields
+            .unnamed
+            .iter()
+            .enumerate()
+            .find(|(index, _)| format_ident!("field_{}", index) == *binding)
+            .map(|(_, field)| &field.ty),
+        Fields::Unit => None,
+    }
 }
 
 /// Creates the "impl <X,Y,Z> ::yew_router_min::Switch for TypeName<X,Y,Z> where etc.." line.
-pub fn impl_line(ident: &Ident, generics: &Generics) -> TokenStream2 {
+///
+/// Only type parameters are supported, so lifetime and const parameters are reported as errors.
+pub fn impl_line(ident: &Ident, generics: &Generics) -> syn::Result<TokenStream2> {
     if generics.params.is_empty() {
-        quote! {
+        Ok(quote! {
             impl ::yew_router_min::Switch for #ident
-        }
+        })
     } else {
         let params = &generics.params;
         let param_idents = params
             .iter()
-            .map(|p: &GenericParam| {
-                match p {
-                    GenericParam::Type(ty) => ty.ident.clone(),
-//                    GenericParam::Lifetime(lt) => lt.lifetime, // TODO different type here, must be handled by collecting into a new enum and defining how to convert _that_ to tokens.
-                    _ => unimplemented!("Not all type parameter variants (lifetimes and consts) are supported in Switch")
-                }
+            .map(|p: &GenericParam| match p {
+                GenericParam::Type(ty) => Ok(ty.ident.clone()),
+                GenericParam::Lifetime(_) => Err(syn::Error::new_spanned(
+                    p,
+                    "Switch can't be derived for types with lifetime parameters",
+                )),
+                GenericParam::Const(_) => Err(syn::Error::new_spanned(
+                    p,
+                    "Switch can't be derived for types with const parameters",
+                )),
             })
-            .collect::<Punctuated<_,syn::token::Comma>>();
+            .collect::<syn::Result<Punctuated<_, syn::token::Comma>>>()?;
 
         let where_clause = &generics.where_clause;
-        quote! {
+        Ok(quote! {
             impl <#params> ::yew_router_min::Switch for #ident <#param_idents> #where_clause
-        }
+        })
     }
 }
@@ -1,19 +1,171 @@
 use crate::switch::shadow::{ShadowCaptureVariant, ShadowMatcherToken};
-use syn::{Attribute, Lit, Meta, MetaNameValue};
-use yew_router_route_parser::FieldNamingScheme;
+use proc_macro2::Span;
+use syn::{Attribute, Error, Lit, LitStr, Meta, MetaNameValue, NestedMeta};
+use yew_router_route_parser::{
+    parse_str_and_optimize_tokens, FieldNamingScheme, MatcherToken, PrettyParseError,
+};
 
 pub enum AttrToken {
-    To(String),
+    To(LitStr),
     End,
     Rest(Option<String>),
 }
 
+/// Settings of the matcher of a struct or variant, which are set with the `switch` attribute.
+///
+/// Variants inherit the settings of their enum, and can override them.
+#[derive(Clone, Copy)]
+pub struct SwitchSettings {
+    pub case_insensitive: bool,
+    pub lowercase_captures: bool,
+    /// Whether the variant may be shadowed by an earlier one, like when it is only used to make paths.
+    pub allow_shadowed: bool,
+}
+
+impl Default for SwitchSettings {
+    fn default() -> Self {
+        SwitchSettings {
+            case_insensitive: true,
+            lowercase_captures: false,
+            allow_shadowed: false,
+        }
+    }
+}
+
+/// The order in which the variants of an enum are tried, which is set with `#[switch(rank = "...")]`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Rank {
+    /// The variants are tried in the order they are declared in.
+    Declaration,
+    /// The variants with the most specific matchers are tried first.
+    Specificity,
+}
+
+/// Options that only apply to enums, which are set with the `switch` attribute of the enum.
+pub struct EnumOptions {
+    pub rank: Rank,
+    /// The matcher string that is prepended to the matcher of every variant.
+    pub prefix: Option<LitStr>,
+}
+
+impl Default for EnumOptions {
+    fn default() -> Self {
+        EnumOptions {
+            rank: Rank::Declaration,
+            prefix: None,
+        }
+    }
+}
+
+impl SwitchSettings {
+    /// Applies the options of any `#[switch(...)]` attributes to the inherited settings.
+    pub fn from_attributes(attributes: &[Attribute], inherited: Self) -> syn::Result<Self> {
+        Self::parse(attributes, inherited, None)
+    }
+
+    /// Reads the settings of an enum, along with the options that only apply to enums.
+    pub fn from_enum_attributes(attributes: &[Attribute]) -> syn::Result<(Self, EnumOptions)> {
+        let mut options = EnumOptions::default();
+        let settings = Self::parse(attributes, Default::default(), Some(&mut options))?;
+        Ok((settings, options))
+    }
+
+    /// Parses the options, rejecting the options of enums unless there are enum options to set.
+    fn parse(
+        attributes: &[Attribute],
+        inherited: Self,
+        mut enum_options: Option<&mut EnumOptions>,
+    ) -> syn::Result<Self> {
+        let mut settings = inherited;
+        for attr in attributes
+            .iter()
+            .filter(|attr| attr.path.is_ident("switch"))
+        {
+            let list = match attr.parse_meta()? {
+                Meta::List(list) => list,
+                meta => {
+                    return Err(Error::new_spanned(
+                        meta,
+                        "Expected a list of options, like `#[switch(case_sensitive)]`",
+                    ))
+                }
+            };
+            for nested in list.nested {
+                if let NestedMeta::Meta(Meta::NameValue(mnv)) = &nested {
+                    let enum_option = ["rank", "prefix"]
+                        .iter()
+                        .find(|option| mnv.path.is_ident(option));
+                    if let Some(option) = enum_option {
+                        let enum_options = enum_options.as_deref_mut().ok_or_else(|| {
+                            Error::new_spanned(
+                                &nested,
+                                format!("Only enums can set the `{}` option", option),
+                            )
+                        })?;
+                        match (*option, &mnv.lit) {
+                            ("rank", Lit::Str(s)) if s.value() == "declaration" => {
+                                enum_options.rank = Rank::Declaration
+                            }
+                            ("rank", Lit::Str(s)) if s.value() == "specificity" => {
+                                enum_options.rank = Rank::Specificity
+                            }
+                            ("rank", lit) => {
+                                return Err(Error::new_spanned(
+                                    lit,
+                                    "Expected `\"declaration\"` or `\"specificity\"`",
+                                ))
+                            }
+                            (_, Lit::Str(s)) => enum_options.prefix = Some(s.clone()),
+                            (_, lit) => {
+                                return Err(Error::new_spanned(
+                                    lit,
+                                    "Expected a matcher string, like `\"/api/v2\"`",
+                                ))
+                            }
+                        }
+                        continue;
+                    }
+                }
+                let option = match &nested {
+                    NestedMeta::Meta(Meta::Path(path)) => path.get_ident().map(ToString::to_string),
+                    _ => None,
+                };
+                match option.as_deref() {
+                    Some("case_sensitive") => settings.case_insensitive = false,
+                    Some("case_insensitive") => settings.case_insensitive = true,
+                    Some("lowercase_captures") => settings.lowercase_captures = true,
+                    Some("keep_capture_case") => settings.lowercase_captures = false,
+                    Some("allow_shadowed") => settings.allow_shadowed = true,
+                    _ => {
+                        return Err(Error::new_spanned(
+                            nested,
+                            "Unknown option, expected `case_sensitive`, `case_insensitive`, \
+                             `lowercase_captures`, `keep_capture_case`, `allow_shadowed`, `rank` \
+                             or `prefix`",
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(settings)
+    }
+}
+
 impl AttrToken {
-    pub fn convert_attributes_to_tokens(attributes: Vec<Attribute>) -> Vec<Self> {
-        fn get_meta_name_value_str(mnv: &MetaNameValue) -> Option<String> {
+    pub fn convert_attributes_to_tokens(attributes: Vec<Attribute>) -> syn::Result<Vec<Self>> {
+        fn get_meta_name_value_str(mnv: &MetaNameValue) -> syn::Result<LitStr> {
             match &mnv.lit {
-                Lit::Str(s) => Some(s.value()),
-                _ => None,
+                Lit::Str(s) => Ok(s.clone()),
+                lit => Err(Error::new_spanned(
+                    lit,
+                    format!(
+                        "Value provided after `{}` must be a String",
+                        mnv.path
+                            .get_ident()
+                            .map(ToString::to_string)
+                            .unwrap_or_default()
+                    ),
+                )),
             }
         }
 
@@ -27,14 +179,11 @@ impl AttrToken {
                     .get_ident()
                     .into_iter()
                     .filter_map(|ident| match ident.to_string().as_str() {
-                        "to" => Some(AttrToken::To(
-                            get_meta_name_value_str(&mnv)
-                                .expect("Value provided after `to` must be a String"),
-                        )),
-                        "rest" => Some(AttrToken::Rest(Some(
+                        "to" => Some(get_meta_name_value_str(&mnv).map(AttrToken::To)),
+                        "rest" => Some(
                             get_meta_name_value_str(&mnv)
-                                .expect("Value provided after `rest` must be a String"),
-                        ))),
+                                .map(|lit| AttrToken::Rest(Some(lit.value()))),
+                        ),
                         _ => None,
                     })
                     .next(),
@@ -42,8 +191,8 @@ impl AttrToken {
                     .get_ident()
                     .into_iter()
                     .filter_map(|ident| match ident.to_string().as_str() {
-                        "end" => Some(AttrToken::End),
-                        "rest" => Some(AttrToken::Rest(None)),
+                        "end" => Some(Ok(AttrToken::End)),
+                        "rest" => Some(Ok(AttrToken::Rest(None))),
                         _ => None,
                     })
                     .next(),
@@ -52,28 +201,95 @@ impl AttrToken {
             .collect()
     }
 
+    /// Writes the attribute as the route matcher string it is equivalent to.
+    pub fn to_matcher_string(&self) -> String {
+        match self {
+            AttrToken::To(lit) => lit.value(),
+            AttrToken::End => "!".to_string(),
+            AttrToken::Rest(Some(capture_name)) => format!("{{*:{}}}", capture_name),
+            AttrToken::Rest(None) => "{*}".to_string(),
+        }
+    }
+
     /// The id is an unique identifier that allows otherwise unnamed captures to still be captured
     /// with unique names.
     pub fn into_shadow_matcher_tokens(
         self,
         id: usize,
         field_naming_scheme: FieldNamingScheme,
-    ) -> Vec<ShadowMatcherToken> {
+    ) -> syn::Result<Vec<ShadowMatcherToken>> {
         match self {
-            AttrToken::To(matcher_string) => {
-                yew_router_route_parser::parse_str_and_optimize_tokens(&matcher_string, field_naming_scheme)
-                    .expect("Invalid Matcher") // This is the point where users should see an error message if their matcher string has some syntax error.
-                    .into_iter()
-                    .map(crate::switch::shadow::ShadowMatcherToken::from)
-                    .collect()
+            AttrToken::To(lit) => {
+                let matcher_string = lit.value();
+                // This is the point where users should see an error message if their matcher string has some syntax error.
+                match yew_router_route_parser::parse_str_and_optimize_tokens(
+                    &matcher_string,
+                    field_naming_scheme,
+                ) {
+                    Ok(tokens) => Ok(tokens.into_iter().map(ShadowMatcherToken::from).collect()),
+                    Err(error) => Err(Error::new(error_span(&lit, &error), format!("{:?}", error))),
+                }
             }
-            AttrToken::End => vec![ShadowMatcherToken::End],
-            AttrToken::Rest(Some(capture_name)) => vec![ShadowMatcherToken::Capture(
+            AttrToken::End => Ok(vec![ShadowMatcherToken::End]),
+            AttrToken::Rest(Some(capture_name)) => Ok(vec![ShadowMatcherToken::Capture(
                 ShadowCaptureVariant::ManyNamed(capture_name),
-            )],
-            AttrToken::Rest(None) => vec![ShadowMatcherToken::Capture(
+            )]),
+            AttrToken::Rest(None) => Ok(vec![ShadowMatcherToken::Capture(
                 ShadowCaptureVariant::ManyNamed(id.to_string()),
-            )],
+            )]),
         }
     }
 }
+
+/// Parses the prefix of an enum into the tokens that are prepended to the matcher of every variant.
+///
+/// A prefix can't capture anything, since the captures wouldn't line up with the fields of every
+/// variant, and it can't have a query or fragment, or end the route, since the path of each variant
+/// comes after it.
+pub fn prefix_tokens(lit: &LitStr) -> syn::Result<Vec<ShadowMatcherToken>> {
+    fn check(tokens: &[MatcherToken], lit: &LitStr) -> syn::Result<()> {
+        for token in tokens {
+            let message = match token {
+                MatcherToken::Query { .. } => "A prefix can't have a query",
+                MatcherToken::Exact(literal) if literal.contains('#') => {
+                    "A prefix can't have a fragment"
+                }
+                MatcherToken::End => "A prefix can't end the route",
+                MatcherToken::Optional(section) => {
+                    check(section, lit)?;
+                    continue;
+                }
+                MatcherToken::Capture(_) => "A prefix can't capture anything",
+                MatcherToken::Exact(_) | MatcherToken::OneOf(_) => continue,
+            };
+            return Err(Error::new_spanned(lit, message));
+        }
+        Ok(())
+    }
+
+    let tokens = parse_str_and_optimize_tokens(&lit.value(), FieldNamingScheme::Named)
+        .map_err(|error| Error::new(error_span(lit, &error), format!("{:?}", error)))?;
+    check(&tokens, lit)?;
+    Ok(tokens.into_iter().map(ShadowMatcherToken::from).collect())
+}
+
+/// Finds the span of the character in the matcher string literal that the error points to.
+///
+/// Spans of single characters can only be created by some compilers, and a literal that contains
+/// escape sequences doesn't line up with its value, so the span of the whole literal is used otherwise.
+fn error_span(lit: &LitStr, error: &PrettyParseError) -> Span {
+    let offset = error.input.len() - error.remaining.len() + error.error.offset;
+    let source = lit.token().to_string();
+    let value = lit.value();
+    let start = source.find('"').map(|quote| quote + 1);
+    start
+        .filter(|&start| source[start..].starts_with(&value) && offset <= value.len())
+        .and_then(|start| {
+            let end = source[start + offset..]
+                .chars()
+                .next()
+                .map_or(0, char::len_utf8);
+            lit.token().subspan(start + offset..start + offset + end)
+        })
+        .unwrap_or_else(|| lit.span())
+}
@@ -1,126 +1,208 @@
-use crate::switch::{impl_line, SwitchItem};
+use crate::switch::{
+    build_path_from_tokens, capture_methods, destructure_fields, field_capture_kinds,
+    field_from_capture, impl_line, route_info, shadow::ShadowMatcherToken, SwitchItem,
+};
 use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
-use syn::{export::TokenStream2, Field, Fields, Generics, Ident, Type};
+use syn::{Field, Fields, Generics, Ident, Type};
 
 pub fn generate_enum_impl(
     enum_ident: Ident,
     switch_variants: Vec<SwitchItem>,
     generics: Generics,
 ) -> TokenStream {
-    let variant_matchers = switch_variants.iter().map(|sv| {
+    let matchers = switch_variants
+        .iter()
+        .map(|sv| super::build_matcher_from_tokens(&sv.matcher, sv.settings));
+
+    let variant_builds = switch_variants.iter().enumerate().map(|(index, sv)| {
+        let SwitchItem {
+            matcher,
+            ident,
+            fields,
+            ..
+        } = sv;
+        let build_from_captures = build_variant_from_captures(&enum_ident, ident, matcher, fields);
+
+        quote! {
+            #index => ::std::result::Result::Ok(#build_from_captures),
+        }
+    });
+    let captures = if switch_variants
+        .iter()
+        .any(|sv| sv.fields.iter().next().is_some())
+    {
+        quote! {mut captures}
+    } else {
+        quote! {_captures}
+    };
+    let build = quote! {
+        let build = |index: usize, #captures: ::yew_router_min::matcher::Captures| -> ::std::result::Result<Self, ::yew_router_min::route::SwitchFailure> {
+            match index {
+                #(#variant_builds)*
+                _ => ::std::unreachable!(),
+            }
+        };
+    };
+    let count = switch_variants.len();
+    let names = switch_variants.iter().map(|sv| sv.ident.to_string());
+    let route_infos = switch_variants.iter().map(route_info);
+
+    let variant_paths = switch_variants.iter().map(|sv| {
         let SwitchItem {
             matcher,
             ident,
             fields,
+            ..
         } = sv;
-        let build_from_captures = build_variant_from_captures(&enum_ident, ident, fields);
-        let matcher = super::build_matcher_from_tokens(&matcher);
+        let pattern = destructure_fields(quote! {#enum_ident::#ident}, matcher, fields);
+        let build_path = build_path_from_tokens(matcher, fields);
 
         quote! {
-            #matcher
-            #build_from_captures
+            #pattern => {
+                #build_path
+            }
         }
     });
 
-    let impl_line = impl_line(&enum_ident, &generics);
+    let impl_line = match impl_line(&enum_ident, &generics) {
+        Ok(impl_line) => impl_line,
+        Err(error) => return error.to_compile_error().into(),
+    };
+    let capture_methods = capture_methods();
 
     let token_stream = quote! {
-        #impl_line
-        {
-            fn from_path(route: &str) -> ::std::option::Option<Self> {
-                #(#variant_matchers)*
+        const _: () = {
+            ::std::thread_local! {
+                static MATCHERS: ::yew_router_min::matcher::MatcherTrie =
+                    ::yew_router_min::matcher::MatcherTrie::new(::std::vec![
+                        #(#matchers),*
+                    ]);
+            }
+
+            #impl_line
+            {
+                fn from_path(route: &str) -> ::std::option::Option<Self> {
+                    #build
+                    MATCHERS.with(|matchers| {
+                        for candidate in matchers.candidates(route) {
+                            for (_, captures) in candidate.capture_route_into_maps() {
+                                if let ::std::result::Result::Ok(item) = build(candidate.index(), captures) {
+                                    return ::std::option::Option::Some(item);
+                                }
+                            }
+                        }
+
+                        ::std::option::Option::None
+                    })
+                }
 
-                return ::std::option::Option::None
+                fn try_from_path(route: &str) -> ::std::result::Result<Self, ::yew_router_min::route::SwitchError> {
+                    #build
+                    const NAMES: [&str; #count] = [#(#names),*];
+                    MATCHERS.with(|matchers| {
+                        let mut attempts = ::std::vec::Vec::new();
+                        for (index, matcher) in matchers.matchers().iter().enumerate() {
+                            let failure = match matcher.try_capture_route_into_map(route) {
+                                ::std::result::Result::Ok((_, captures)) => match build(index, captures) {
+                                    ::std::result::Result::Ok(item) => return ::std::result::Result::Ok(item),
+                                    // A later way of including the optional sections might still convert.
+                                    ::std::result::Result::Err(failure) => {
+                                        for (_, captures) in matcher.capture_route_into_maps(route).skip(1) {
+                                            if let ::std::result::Result::Ok(item) = build(index, captures) {
+                                                return ::std::result::Result::Ok(item);
+                                            }
+                                        }
+                                        failure
+                                    }
+                                },
+                                ::std::result::Result::Err(error) => {
+                                    ::yew_router_min::route::SwitchFailure::Match(error)
+                                }
+                            };
+                            attempts.push(::yew_router_min::route::SwitchAttempt {
+                                name: NAMES[index],
+                                failure,
+                            });
+                        }
+
+                        ::std::result::Result::Err(::yew_router_min::route::SwitchError {
+                            route: ::std::string::ToString::to_string(route),
+                            attempts,
+                        })
+                    })
+                }
+
+                #capture_methods
+
+                fn to_path(&self) -> ::std::string::String {
+                    let mut buf = ::std::string::String::new();
+                    match self {
+                        #(#variant_paths),*
+                    }
+                    buf
+                }
+
+                fn routes() -> &'static [::yew_router_min::route::RouteInfo] {
+                    &[#(#route_infos),*]
+                }
             }
-        }
+        };
     };
     TokenStream::from(token_stream)
 }
 
-/// Once the 'captures' exists, attempt to populate the fields from the list of captures.
+/// Creates an expression that populates the fields of the variant from the list of captures,
+/// which are expected to be bound to `captures`.
 fn build_variant_from_captures(
     enum_ident: &Ident,
     variant_ident: &Ident,
+    matcher: &[ShadowMatcherToken],
     fields: &Fields,
 ) -> TokenStream2 {
+    let kinds = field_capture_kinds(matcher, fields);
     match fields {
         Fields::Named(named_fields) => {
             let fields: Vec<TokenStream2> = named_fields
                 .named
                 .iter()
-                .filter_map(|field: &Field| {
+                .zip(kinds)
+                .filter_map(|(field, kind): (&Field, TokenStream2)| {
                     let field_ty: &Type = &field.ty;
                     field.ident.as_ref().map(|i: &Ident| {
                         let key = i.to_string();
-                        (i, key, field_ty)
+                        (i, key, field_ty, kind)
                     })
                 })
-                .map(|(field_name, key, field_ty): (&Ident, String, &Type)| {
+                .map(|(field_name, key, field_ty, kind)| {
+                    let field = field_from_capture(&key, field_ty, &kind);
                     quote! {
-                        #field_name: {
-                            let v = match captures.remove(#key) {
-                                ::std::option::Option::Some(value) => {
-                                    <#field_ty as ::yew_router_min::Switch>::from_route(value)
-                                }
-                                ::std::option::Option::None => ::std::option::Option::None,
-                            };
-                            match v {
-                                ::std::option::Option::Some(val) => {
-                                    val
-                                },
-                                ::std::option::Option::None => return None // Failed
-                            }
-                        }
+                        #field_name: #field
                     }
                 })
                 .collect();
 
             quote! {
-                if let ::std::option::Option::Some(mut captures) = matcher.capture_route_into_map(route).ok().map(|x| x.1) {
-                    return ::std::option::Option::Some(
-                        #enum_ident::#variant_ident {
-                            #(#fields),*
-                        }
-                    );
-                };
+                #enum_ident::#variant_ident {
+                    #(#fields),*
+                }
             }
         }
         Fields::Unnamed(unnamed_fields) => {
-            let fields = unnamed_fields.unnamed.iter().map(|f: &Field| {
-                let field_ty = &f.ty;
-                quote! {
-                    {
-                        let v = match drain.next() {
-                            ::std::option::Option::Some(value) => {
-                                <#field_ty as ::yew_router_min::Switch>::from_route(value)
-                            },
-                            ::std::option::Option::None => ::std::option::Option::None,
-                        };
-                        match v {
-                            ::std::option::Option::Some(val) => val,
-                            ::std::option::Option::None => return None // Failed
-                        }
-                    }
-                }
-            });
+            let fields = unnamed_fields.unnamed.iter().zip(kinds).enumerate().map(
+                |(index, (field, kind))| field_from_capture(&index.to_string(), &field.ty, &kind),
+            );
 
             quote! {
-                if let ::std::option::Option::Some(mut captures) = matcher.capture_route_into_vec(route).ok().map(|x| x.1) {
-                    let mut drain = captures.drain(..);
-                    return ::std::option::Option::Some(
-                        #enum_ident::#variant_ident(
-                            #(#fields),*
-                        )
-                    );
-                };
+                #enum_ident::#variant_ident(
+                    #(#fields),*
+                )
             }
         }
         Fields::Unit => {
             quote! {
-                if let ::std::option::Option::Some(_captures) = matcher.capture_route_into_map(route).ok().map(|x| x.1) {
-                    return ::std::option::Option::Some(#enum_ident::#variant_ident);
-                };
+                #enum_ident::#variant_ident
             }
         }
     }
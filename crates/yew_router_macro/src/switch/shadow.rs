@@ -1,6 +1,8 @@
-use quote::{quote, ToTokens};
-use syn::export::TokenStream2;
-use yew_router_route_parser::{CaptureVariant, MatcherToken};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, ToTokens};
+use yew_router_route_parser::{
+    CaptureVariant, Constraint, MatcherToken, QueryParam, QueryValue, Repetition,
+};
 
 impl ToTokens for ShadowMatcherToken {
     fn to_tokens(&self, ts: &mut TokenStream2) {
@@ -9,25 +11,83 @@ impl ToTokens for ShadowMatcherToken {
             SOT::Exact(s) => quote! {
                 ::yew_router_min::matcher::MatcherToken::Exact(#s.to_string())
             },
+            SOT::OneOf(alternatives) => quote! {
+                ::yew_router_min::matcher::MatcherToken::OneOf(
+                    ::std::vec![#(#alternatives.to_string()),*]
+                )
+            },
             SOT::Capture(variant) => quote! {
                 ::yew_router_min::matcher::MatcherToken::Capture(#variant)
             },
             SOT::End => quote! {
                 ::yew_router_min::matcher::MatcherToken::End
             },
+            SOT::Optional(section) => quote! {
+                ::yew_router_min::matcher::MatcherToken::Optional(::std::vec![#(#section),*])
+            },
+            SOT::Query { params, strict } => quote! {
+                ::yew_router_min::matcher::MatcherToken::Query {
+                    params: ::std::vec![#(#params),*],
+                    strict: #strict,
+                }
+            },
         };
         ts.extend(t)
     }
 }
 
+impl ToTokens for ShadowQueryParam {
+    fn to_tokens(&self, ts: &mut TokenStream2) {
+        let key = &self.key;
+        let optional = self.optional;
+        let value = match &self.value {
+            ShadowQueryValue::Exact(s) => quote! {
+                ::yew_router_min::matcher::QueryValue::Exact(#s.to_string())
+            },
+            ShadowQueryValue::Capture(variant) => quote! {
+                ::yew_router_min::matcher::QueryValue::Capture(#variant)
+            },
+        };
+        ts.extend(quote! {
+            ::yew_router_min::matcher::QueryParam {
+                key: #key.to_string(),
+                value: #value,
+                optional: #optional,
+            }
+        })
+    }
+}
+
 /// A shadow of the OptimizedToken type.
 /// It should match it exactly so that this macro can expand to the original.
+#[derive(Clone)]
 pub enum ShadowMatcherToken {
     Exact(String),
+    OneOf(Vec<String>),
     Capture(ShadowCaptureVariant),
     End,
+    Optional(Vec<ShadowMatcherToken>),
+    Query {
+        params: Vec<ShadowQueryParam>,
+        strict: bool,
+    },
+}
+
+/// A shadow of the QueryParam type.
+#[derive(Clone)]
+pub struct ShadowQueryParam {
+    pub key: String,
+    pub value: ShadowQueryValue,
+    pub optional: bool,
+}
+
+#[derive(Clone)]
+pub enum ShadowQueryValue {
+    Exact(String),
+    Capture(ShadowCaptureVariant),
 }
 
+#[derive(Clone)]
 pub enum ShadowCaptureVariant {
     /// {}
     Unnamed,
@@ -44,8 +104,17 @@ pub enum ShadowCaptureVariant {
     ManyNamed(String),
     /// {2:name} - captures a fixed number of sections with a given name.
     NumberedNamed { sections: usize, name: String },
+    /// {name:constraint} - captures a section that satisfies a constraint with a given name.
+    Constrained {
+        name: String,
+        constraint: ShadowConstraint,
+    },
 }
 
+/// A shadow of the Constraint type, which expands to an expression that constructs it.
+#[derive(Clone)]
+pub struct ShadowConstraint(pub Constraint);
+
 impl ToTokens for ShadowCaptureVariant {
     fn to_tokens(&self, ts: &mut TokenStream2) {
         let t = match self {
@@ -58,6 +127,9 @@ impl ToTokens for ShadowCaptureVariant {
             ShadowCaptureVariant::NumberedNamed { sections, name } => {
                 quote! {::yew_router_min::matcher::CaptureVariant::NumberedNamed{sections: #sections, name: #name.to_string()}}
             }
+            ShadowCaptureVariant::Constrained { name, constraint } => {
+                quote! {::yew_router_min::matcher::CaptureVariant::Constrained{name: #name.to_string(), constraint: #constraint}}
+            }
             ShadowCaptureVariant::Unnamed => {
                 quote! {::yew_router_min::matcher::CaptureVariant::Unnamed}
             }
@@ -72,14 +144,77 @@ impl ToTokens for ShadowCaptureVariant {
     }
 }
 
+impl ToTokens for ShadowConstraint {
+    fn to_tokens(&self, ts: &mut TokenStream2) {
+        let t = match &self.0 {
+            Constraint::Type(ty) => {
+                let ty = format_ident!("{}", format!("{:?}", ty));
+                quote! {
+                    ::yew_router_min::matcher::Constraint::Type(
+                        ::yew_router_min::matcher::TypeConstraint::#ty
+                    )
+                }
+            }
+            Constraint::Pattern(alternatives) => {
+                let alternatives = alternatives.iter().map(|sequence| {
+                    let items = sequence.iter().map(|item| {
+                        let negated = item.class.negated;
+                        let starts = item.class.ranges.iter().map(|(start, _)| start);
+                        let ends = item.class.ranges.iter().map(|(_, end)| end);
+                        let repetition = match item.repetition {
+                            Repetition::Once => quote! {Once},
+                            Repetition::Optional => quote! {Optional},
+                            Repetition::ZeroOrMore => quote! {ZeroOrMore},
+                            Repetition::OneOrMore => quote! {OneOrMore},
+                        };
+                        quote! {
+                            ::yew_router_min::matcher::PatternItem {
+                                class: ::yew_router_min::matcher::CharClass {
+                                    negated: #negated,
+                                    ranges: ::std::vec![#((#starts, #ends)),*],
+                                },
+                                repetition: ::yew_router_min::matcher::Repetition::#repetition,
+                            }
+                        }
+                    });
+                    quote! { ::std::vec![#(#items),*] }
+                });
+                quote! {
+                    ::yew_router_min::matcher::Constraint::Pattern(::std::vec![#(#alternatives),*])
+                }
+            }
+        };
+        ts.extend(t)
+    }
+}
+
 impl From<MatcherToken> for ShadowMatcherToken {
     fn from(mt: MatcherToken) -> Self {
         use MatcherToken as MT;
         use ShadowMatcherToken as SOT;
         match mt {
             MT::Exact(s) => SOT::Exact(s),
+            MT::OneOf(alternatives) => SOT::OneOf(alternatives),
             MT::Capture(capture) => SOT::Capture(capture.into()),
             MT::End => SOT::End,
+            MT::Optional(section) => SOT::Optional(section.into_iter().map(SOT::from).collect()),
+            MT::Query { params, strict } => SOT::Query {
+                params: params.into_iter().map(ShadowQueryParam::from).collect(),
+                strict,
+            },
+        }
+    }
+}
+
+impl From<QueryParam> for ShadowQueryParam {
+    fn from(param: QueryParam) -> Self {
+        ShadowQueryParam {
+            key: param.key,
+            value: match param.value {
+                QueryValue::Exact(s) => ShadowQueryValue::Exact(s),
+                QueryValue::Capture(capture) => ShadowQueryValue::Capture(capture.into()),
+            },
+            optional: param.optional,
         }
     }
 }
@@ -93,6 +228,10 @@ impl From<CaptureVariant> for ShadowCaptureVariant {
             CaptureVariant::NumberedNamed { sections, name } => {
                 SCV::NumberedNamed { sections, name }
             }
+            CaptureVariant::Constrained { name, constraint } => SCV::Constrained {
+                name,
+                constraint: ShadowConstraint(constraint),
+            },
             CaptureVariant::Unnamed => SCV::Unnamed,
             CaptureVariant::ManyUnnamed => SCV::ManyUnnamed,
             CaptureVariant::NumberedUnnamed { sections } => SCV::NumberedUnnamed { sections },
@@ -1,117 +1,159 @@
-use crate::switch::{impl_line, SwitchItem};
-use proc_macro2::Ident;
-use quote::quote;
-use syn::{
-    export::{TokenStream, TokenStream2},
-    Field, Fields, Generics, Type,
+use crate::switch::{
+    build_path_from_tokens, capture_methods, destructure_fields, field_capture_kinds,
+    field_from_capture, impl_line, route_info, shadow::ShadowMatcherToken, SwitchItem,
 };
-
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{Field, Fields, Generics, Type};
 
 pub fn generate_struct_impl(item: SwitchItem, generics: Generics) -> TokenStream {
     let SwitchItem {
         matcher,
+        settings,
         ident,
         fields,
+        ..
     } = &item;
-    let build_from_captures = build_struct_from_captures(&ident, &fields);
-    let matcher = super::build_matcher_from_tokens(&matcher);
+    let build_from_captures = build_struct_from_captures(ident, matcher, fields);
+    let pattern = destructure_fields(quote! {#ident}, matcher, fields);
+    let build_path = build_path_from_tokens(matcher, fields);
+    let matcher = super::build_matcher_from_tokens(matcher, *settings);
 
+    let impl_line = match impl_line(ident, &generics) {
+        Ok(impl_line) => impl_line,
+        Err(error) => return error.to_compile_error().into(),
+    };
+    let capture_methods = capture_methods();
 
-    let impl_line = impl_line(ident, &generics);
+    let captures = if fields.iter().next().is_some() {
+        quote! {mut captures}
+    } else {
+        quote! {_captures}
+    };
+    let build = quote! {
+        let build = |#captures: ::yew_router_min::matcher::Captures| -> ::std::result::Result<Self, ::yew_router_min::route::SwitchFailure> {
+            ::std::result::Result::Ok(#build_from_captures)
+        };
+    };
+    let name = ident.to_string();
+    let route_info = route_info(&item);
 
     let token_stream = quote! {
-        #impl_line
-        {
-            fn from_path(route: &str) -> ::std::option::Option<Self> {
-                #matcher
-                #build_from_captures
-                return ::std::option::Option::None
+        const _: () = {
+            ::std::thread_local! {
+                static MATCHER: ::yew_router_min::matcher::RouteMatcher = #matcher;
             }
-        }
+
+            #impl_line
+            {
+                fn from_path(route: &str) -> ::std::option::Option<Self> {
+                    #build
+                    MATCHER.with(|matcher| {
+                        matcher
+                            .capture_route_into_maps(route)
+                            .find_map(|(_, captures)| build(captures).ok())
+                    })
+                }
+
+                fn try_from_path(route: &str) -> ::std::result::Result<Self, ::yew_router_min::route::SwitchError> {
+                    #build
+                    MATCHER.with(|matcher| {
+                        let failure = match matcher.try_capture_route_into_map(route) {
+                            ::std::result::Result::Ok((_, captures)) => match build(captures) {
+                                ::std::result::Result::Ok(item) => return ::std::result::Result::Ok(item),
+                                // A later way of including the optional sections might still convert.
+                                ::std::result::Result::Err(failure) => {
+                                    for (_, captures) in matcher.capture_route_into_maps(route).skip(1) {
+                                        if let ::std::result::Result::Ok(item) = build(captures) {
+                                            return ::std::result::Result::Ok(item);
+                                        }
+                                    }
+                                    failure
+                                }
+                            },
+                            ::std::result::Result::Err(error) => {
+                                ::yew_router_min::route::SwitchFailure::Match(error)
+                            }
+                        };
+
+                        ::std::result::Result::Err(::yew_router_min::route::SwitchError {
+                            route: ::std::string::ToString::to_string(route),
+                            attempts: ::std::vec![::yew_router_min::route::SwitchAttempt {
+                                name: #name,
+                                failure,
+                            }],
+                        })
+                    })
+                }
+
+                #capture_methods
+
+                fn to_path(&self) -> ::std::string::String {
+                    let mut buf = ::std::string::String::new();
+                    let #pattern = self;
+                    #build_path
+                    buf
+                }
+
+                fn routes() -> &'static [::yew_router_min::route::RouteInfo] {
+                    &[#route_info]
+                }
+            }
+        };
     };
     TokenStream::from(token_stream)
 }
 
-fn build_struct_from_captures(ident: &Ident, fields: &Fields) -> TokenStream2 {
+/// Creates an expression that populates the fields of the struct from the list of captures,
+/// which are expected to be bound to `captures`.
+fn build_struct_from_captures(
+    ident: &Ident,
+    matcher: &[ShadowMatcherToken],
+    fields: &Fields,
+) -> TokenStream2 {
+    let kinds = field_capture_kinds(matcher, fields);
     match fields {
         Fields::Named(named_fields) => {
             let fields: Vec<TokenStream2> = named_fields
                 .named
                 .iter()
-                .filter_map(|field: &Field| {
+                .zip(kinds)
+                .filter_map(|(field, kind): (&Field, TokenStream2)| {
                     let field_ty: &Type = &field.ty;
                     field.ident.as_ref().map(|i| {
                         let key = i.to_string();
-                        (i, key, field_ty)
+                        (i, key, field_ty, kind)
                     })
                 })
-                .map(|(field_name, key, field_ty): (&Ident, String, &Type)| {
+                .map(|(field_name, key, field_ty, kind)| {
+                    let field = field_from_capture(&key, field_ty, &kind);
                     quote! {
-                        #field_name: {
-                            let v = match captures.remove(#key) {
-                                ::std::option::Option::Some(value) => {
-                                    <#field_ty as ::yew_router_min::Switch>::from_route(value)
-                                }
-                                ::std::option::Option::None => ::std::option::Option::None,
-                            };
-                            match v {
-                                ::std::option::Option::Some(val) => {
-                                    val
-                                },
-                                ::std::option::Option::None => return ::std::option::Option::None // Failed
-                            }
-                        }
+                        #field_name: #field
                     }
                 })
                 .collect();
 
-            return quote! {
-                if let ::std::option::Option::Some(mut captures) = matcher.capture_route_into_map(route).ok().map(|x| x.1) {
-                    return ::std::option::Option::Some(
-                        #ident {
-                            #(#fields),*
-                        }
-                    );
-                };
-            };
+            quote! {
+                #ident {
+                    #(#fields),*
+                }
+            }
         }
         Fields::Unnamed(unnamed_fields) => {
-            let fields = unnamed_fields.unnamed.iter().map(|f: &Field| {
-                let field_ty = &f.ty;
-                quote! {
-                    {
-                        let v = match drain.next() {
-                            ::std::option::Option::Some(value) => {
-                                <#field_ty as ::yew_router_min::Switch>::from_route(value)
-                            },
-                            ::std::option::Option::None => ::std::option::Option::None,
-                        };
-                        match v {
-                            ::std::option::Option::Some(val) => {
-                                val
-                            },
-                            ::std::option::Option::None => return ::std::option::Option::None // Failed
-                        }
-                    }
-                }
-            });
+            let fields = unnamed_fields.unnamed.iter().zip(kinds).enumerate().map(
+                |(index, (field, kind))| field_from_capture(&index.to_string(), &field.ty, &kind),
+            );
 
             quote! {
-                if let Some(mut captures) = matcher.capture_route_into_vec(route).ok().map(|x| x.1) {
-                    let mut drain = captures.drain(..);
-                    return ::std::option::Option::Some(
-                        #ident(
-                            #(#fields),*
-                        )
-                    );
-                };
+                #ident(
+                    #(#fields),*
+                )
             }
         }
         Fields::Unit => {
-            return quote! {
-                if let ::std::option::Option::Some(_captures) = matcher.capture_route_into_map(route).ok().map(|x| x.1) {
-                    return ::std::option::Option::Some(#ident);
-                };
+            quote! {
+                #ident
             }
         }
     }
@@ -0,0 +1,334 @@
+//! Constraints that restrict what a capture will match, written as `{name:constraint}`.
+use crate::error::{ExpectedToken, ParseError, ParserErrorReason};
+
+/// A restriction on the sections that a capture will match.
+///
+/// A constraint that looks like an identifier names a type, and anything else is a pattern.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Constraint {
+    /// The section must parse as the type, eg. `{id:u32}`.
+    Type(TypeConstraint),
+    /// The section must match one of the alternatives, each of which is a sequence of items,
+    /// eg. `{name:[a-z0-9_-]+}` or `{state:draft|published}`.
+    Pattern(Vec<Vec<PatternItem>>),
+}
+
+/// Types that a section can be required to parse as.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TypeConstraint {
+    /// `u8`
+    U8,
+    /// `u16`
+    U16,
+    /// `u32`
+    U32,
+    /// `u64`
+    U64,
+    /// `u128`
+    U128,
+    /// `usize`
+    Usize,
+    /// `i8`
+    I8,
+    /// `i16`
+    I16,
+    /// `i32`
+    I32,
+    /// `i64`
+    I64,
+    /// `i128`
+    I128,
+    /// `isize`
+    Isize,
+    /// `bool` - either `true` or `false`.
+    Bool,
+    /// `date` - a calendar date formatted as `YYYY-MM-DD`.
+    Date,
+    /// `uuid` - a hyphenated UUID, like `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+    Uuid,
+}
+
+impl TypeConstraint {
+    const NAMES: [(&'static str, TypeConstraint); 15] = [
+        ("u8", TypeConstraint::U8),
+        ("u16", TypeConstraint::U16),
+        ("u32", TypeConstraint::U32),
+        ("u64", TypeConstraint::U64),
+        ("u128", TypeConstraint::U128),
+        ("usize", TypeConstraint::Usize),
+        ("i8", TypeConstraint::I8),
+        ("i16", TypeConstraint::I16),
+        ("i32", TypeConstraint::I32),
+        ("i64", TypeConstraint::I64),
+        ("i128", TypeConstraint::I128),
+        ("isize", TypeConstraint::Isize),
+        ("bool", TypeConstraint::Bool),
+        ("date", TypeConstraint::Date),
+        ("uuid", TypeConstraint::Uuid),
+    ];
+
+    /// Gets the type with the name used for it in a matcher string.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, constraint)| *constraint)
+    }
+
+    /// The name used for this type in a matcher string.
+    pub fn name(self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find(|(_, constraint)| *constraint == self)
+            .map(|(name, _)| *name)
+            .expect("every type has a name")
+    }
+}
+
+/// A class of characters that may be repeated, making up part of a pattern.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PatternItem {
+    /// Characters that match this item.
+    pub class: CharClass,
+    /// How many characters in a row can match this item.
+    pub repetition: Repetition,
+}
+
+/// A set of characters, written as either a single character or a bracketed class, like `[^a-z_]`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CharClass {
+    /// Whether the class matches the characters outside of the ranges instead.
+    pub negated: bool,
+    /// Inclusive ranges of characters, where a single character is a range that starts and ends with it.
+    pub ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    /// Checks if the character is a member of this class.
+    pub fn contains(&self, c: char) -> bool {
+        self.ranges
+            .iter()
+            .any(|(start, end)| *start <= c && c <= *end)
+            != self.negated
+    }
+}
+
+/// How many times in a row a pattern item can match.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Repetition {
+    /// Exactly once.
+    Once,
+    /// `?` - zero or one times.
+    Optional,
+    /// `*` - any number of times.
+    ZeroOrMore,
+    /// `+` - at least once.
+    OneOrMore,
+}
+
+impl Repetition {
+    /// The least and most number of times the item can match.
+    pub fn bounds(self) -> (usize, usize) {
+        match self {
+            Repetition::Once => (1, 1),
+            Repetition::Optional => (0, 1),
+            Repetition::ZeroOrMore => (0, usize::MAX),
+            Repetition::OneOrMore => (1, usize::MAX),
+        }
+    }
+}
+
+/// Characters that have a meaning in patterns, and must be escaped with `\` to be matched literally.
+const PATTERN_SPECIAL_CHARS: &str = r"[]()|?*+\";
+
+/// Parses the text of a constraint.
+///
+/// A pattern can be wrapped in parentheses, like `(en|de|fr)`.
+/// On failure, the offset of the error is relative to the start of the constraint.
+pub(crate) fn parse_constraint(i: &str) -> Result<Constraint, ParseError> {
+    let is_ident = i.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_ident {
+        return TypeConstraint::from_name(i)
+            .map(Constraint::Type)
+            .ok_or_else(|| ParseError {
+                reason: Some(ParserErrorReason::UnknownConstraintType),
+                expected: vec![ExpectedToken::Constraint],
+                offset: 0,
+            });
+    }
+
+    let (i, start) = match i.strip_prefix('(').and_then(|i| i.strip_suffix(')')) {
+        Some(inner) => (inner, 1),
+        None => (i, 0),
+    };
+    let mut chars = i.char_indices().peekable();
+    let mut alternatives = vec![];
+    let mut sequence = vec![];
+    let bad_pattern = |offset: usize| ParseError {
+        reason: Some(ParserErrorReason::BadPattern),
+        expected: vec![ExpectedToken::Constraint],
+        offset: start + offset,
+    };
+
+    while let Some((offset, c)) = chars.next() {
+        let class = match c {
+            '|' => {
+                if sequence.is_empty() {
+                    return Err(bad_pattern(offset));
+                }
+                alternatives.push(std::mem::take(&mut sequence));
+                continue;
+            }
+            '[' => {
+                let negated = chars.next_if(|(_, c)| *c == '^').is_some();
+                let mut ranges = vec![];
+                loop {
+                    let start = match chars.next() {
+                        Some((_, ']')) if !ranges.is_empty() => break,
+                        Some((_, '\\')) => chars.next(),
+                        next => next,
+                    };
+                    let start = match start {
+                        Some((_, start)) => start,
+                        None => return Err(bad_pattern(i.len())),
+                    };
+                    // A '-' at the end of the class is a literal '-'.
+                    let is_range = chars.next_if(|(_, c)| *c == '-').is_some();
+                    if is_range && chars.peek().map(|(_, c)| *c) != Some(']') {
+                        let end = match chars.next() {
+                            Some((_, '\\')) => chars.next(),
+                            next => next,
+                        };
+                        match end {
+                            Some((_, end)) if start <= end => ranges.push((start, end)),
+                            Some((offset, _)) => return Err(bad_pattern(offset)),
+                            None => return Err(bad_pattern(i.len())),
+                        }
+                    } else {
+                        ranges.push((start, start));
+                        if is_range {
+                            ranges.push(('-', '-'));
+                        }
+                    }
+                }
+                CharClass { negated, ranges }
+            }
+            '\\' => match chars.next() {
+                Some((_, escaped)) => CharClass {
+                    negated: false,
+                    ranges: vec![(escaped, escaped)],
+                },
+                None => return Err(bad_pattern(offset)),
+            },
+            c if PATTERN_SPECIAL_CHARS.contains(c) => return Err(bad_pattern(offset)),
+            c => CharClass {
+                negated: false,
+                ranges: vec![(c, c)],
+            },
+        };
+        let repetition = match chars.next_if(|(_, c)| "?*+".contains(*c)) {
+            Some((_, '?')) => Repetition::Optional,
+            Some((_, '*')) => Repetition::ZeroOrMore,
+            Some((_, '+')) => Repetition::OneOrMore,
+            _ => Repetition::Once,
+        };
+        sequence.push(PatternItem { class, repetition });
+    }
+
+    if sequence.is_empty() {
+        return Err(bad_pattern(i.len()));
+    }
+    alternatives.push(sequence);
+    Ok(Constraint::Pattern(alternatives))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn class(ranges: &[(char, char)]) -> CharClass {
+        CharClass {
+            negated: false,
+            ranges: ranges.to_vec(),
+        }
+    }
+
+    #[test]
+    fn type_constraint() {
+        assert_eq!(
+            parse_constraint("u32"),
+            Ok(Constraint::Type(TypeConstraint::U32))
+        );
+        assert_eq!(
+            parse_constraint("date"),
+            Ok(Constraint::Type(TypeConstraint::Date))
+        );
+    }
+
+    #[test]
+    fn unknown_type_fails() {
+        let error = parse_constraint("u33").expect_err("should not parse");
+        assert_eq!(error.reason, Some(ParserErrorReason::UnknownConstraintType));
+    }
+
+    #[test]
+    fn char_class_with_trailing_dash() {
+        let expected = Constraint::Pattern(vec![vec![PatternItem {
+            class: class(&[('a', 'z'), ('0', '9'), ('_', '_'), ('-', '-')]),
+            repetition: Repetition::OneOrMore,
+        }]]);
+        assert_eq!(parse_constraint("[a-z0-9_-]+"), Ok(expected));
+    }
+
+    #[test]
+    fn alternation() {
+        let word = |w: &str| {
+            w.chars()
+                .map(|c| PatternItem {
+                    class: class(&[(c, c)]),
+                    repetition: Repetition::Once,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            parse_constraint("en|fr"),
+            Ok(Constraint::Pattern(vec![word("en"), word("fr")]))
+        );
+    }
+
+    #[test]
+    fn parenthesized_alternation() {
+        assert_eq!(parse_constraint("(en|fr)"), parse_constraint("en|fr"));
+        assert_eq!(parse_constraint("(en|)").map_err(|e| e.offset), Err(4));
+        assert_eq!(parse_constraint("(en|fr").map_err(|e| e.offset), Err(0));
+    }
+
+    #[test]
+    fn negated_class() {
+        let expected = Constraint::Pattern(vec![vec![PatternItem {
+            class: CharClass {
+                negated: true,
+                ranges: vec![('.', '.')],
+            },
+            repetition: Repetition::ZeroOrMore,
+        }]]);
+        assert_eq!(parse_constraint("[^.]*"), Ok(expected));
+    }
+
+    #[test]
+    fn malformed_patterns_fail_at_offset() {
+        assert_eq!(parse_constraint("[a-z").map_err(|e| e.offset), Err(4));
+        assert_eq!(parse_constraint("a||b").map_err(|e| e.offset), Err(2));
+        assert_eq!(parse_constraint("+a").map_err(|e| e.offset), Err(0));
+        assert_eq!(parse_constraint("[z-a]").map_err(|e| e.offset), Err(3));
+        assert_eq!(parse_constraint("a|").map_err(|e| e.offset), Err(2));
+    }
+
+    #[test]
+    fn type_names_round_trip() {
+        for (name, constraint) in TypeConstraint::NAMES.iter() {
+            assert_eq!(TypeConstraint::from_name(name), Some(*constraint));
+            assert_eq!(constraint.name(), *name);
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{
+    constraint::parse_constraint,
     error::{ExpectedToken, ParserErrorReason},
     parser::{CaptureOrExact, RefCaptureVariant, RouteParserToken},
     ParseError,
@@ -27,35 +28,59 @@ pub enum FieldNamingScheme {
     Unit,
 }
 
-pub fn get_slash(i: &str) -> IResult<&str, RouteParserToken, ParseError> {
+pub fn get_slash(i: &str) -> IResult<&str, RouteParserToken<'_>, ParseError> {
     map(char('/'), |_: char| RouteParserToken::Separator)(i)
         .map_err(|_: nom::Err<()>| nom::Err::Error(ParseError::expected(ExpectedToken::Separator)))
 }
 
-pub fn get_question(i: &str) -> IResult<&str, RouteParserToken, ParseError> {
+pub fn get_question(i: &str) -> IResult<&str, RouteParserToken<'_>, ParseError> {
     map(char('?'), |_: char| RouteParserToken::QueryBegin)(i)
         .map_err(|_: nom::Err<()>| nom::Err::Error(ParseError::expected(ExpectedToken::QueryBegin)))
 }
 
-pub fn get_and(i: &str) -> IResult<&str, RouteParserToken, ParseError> {
+pub fn get_and(i: &str) -> IResult<&str, RouteParserToken<'_>, ParseError> {
     map(char('&'), |_: char| RouteParserToken::QuerySeparator)(i).map_err(|_: nom::Err<()>| {
         nom::Err::Error(ParseError::expected(ExpectedToken::QuerySeparator))
     })
 }
 
 /// Returns a FragmentBegin variant if the next character is '\#'.
-pub fn get_hash(i: &str) -> IResult<&str, RouteParserToken, ParseError> {
+pub fn get_hash(i: &str) -> IResult<&str, RouteParserToken<'_>, ParseError> {
     map(char('#'), |_: char| RouteParserToken::FragmentBegin)(i).map_err(|_: nom::Err<()>| {
         nom::Err::Error(ParseError::expected(ExpectedToken::FragmentBegin))
     })
 }
 
 /// Returns an End variant if the next character is a '!`.
-pub fn get_end(i: &str) -> IResult<&str, RouteParserToken, ParseError> {
+pub fn get_end(i: &str) -> IResult<&str, RouteParserToken<'_>, ParseError> {
     map(char('!'), |_: char| RouteParserToken::End)(i)
         .map_err(|_: nom::Err<()>| nom::Err::Error(ParseError::expected(ExpectedToken::End)))
 }
 
+/// Returns an OptionalBegin variant if the next character is a '['.
+pub fn get_open_optional(i: &str) -> IResult<&str, RouteParserToken<'_>, ParseError> {
+    map(char('['), |_: char| RouteParserToken::OptionalBegin)(i).map_err(|_: nom::Err<()>| {
+        nom::Err::Error(ParseError::expected(ExpectedToken::OpenOptional))
+    })
+}
+
+/// Returns an OptionalEnd variant if the next character is a ']'.
+pub fn get_close_optional(i: &str) -> IResult<&str, RouteParserToken<'_>, ParseError> {
+    map(char(']'), |_: char| RouteParserToken::OptionalEnd)(i).map_err(|_: nom::Err<()>| {
+        nom::Err::Error(ParseError::expected(ExpectedToken::CloseOptional))
+    })
+}
+
+fn get_open_paren(i: &str) -> IResult<&str, (), ParseError> {
+    map(char('('), |_: char| ())(i)
+        .map_err(|_: nom::Err<()>| nom::Err::Error(ParseError::expected(ExpectedToken::OpenParen)))
+}
+
+fn get_close_paren(i: &str) -> IResult<&str, (), ParseError> {
+    map(char(')'), |_: char| ())(i)
+        .map_err(|_: nom::Err<()>| nom::Err::Error(ParseError::expected(ExpectedToken::CloseParen)))
+}
+
 /// Returns an End variant if the next character is a '!`.
 fn get_open_bracket(i: &str) -> IResult<&str, (), ParseError> {
     map(char('{'), |_: char| ())(i).map_err(|_: nom::Err<()>| {
@@ -85,49 +110,76 @@ fn get_colon(i: &str) -> IResult<&str, (), ParseError> {
 }
 
 fn rust_ident(i: &str) -> IResult<&str, &str, ParseError> {
+    rust_ident_until("}")(i)
+}
+
+/// Reads an ident that ends at any of the terminating characters.
+fn rust_ident_until(terminators: &'static str) -> impl Fn(&str) -> IResult<&str, &str, ParseError> {
     let invalid_ident_chars = r##" \|/{[]()?+=-!@#$%^&*~`'";:"##;
-    // Detect an ident by first reading until a } is found,
+    // Detect an ident by first reading until a terminator is found,
     // then validating the captured section against invalid characters that can't be in rust idents.
-    map_parser(take_till1(move |c| c == '}'), move |i: &str| {
-        match take_till1::<_, _, ()>(|c| invalid_ident_chars.contains(c))(i) {
-            Ok((remain, got)) => {
-                // Detects if the first character is a digit.
-                if !got.is_empty() && got.starts_with(|c: char| is_digit(c as u8)) {
-                    Err(nom::Err::Failure(ParseError {
-                        reason: Some(ParserErrorReason::BadRustIdent(got.chars().next().unwrap())),
-                        expected: vec![ExpectedToken::Ident],
-                        offset: 1,
-                    }))
-                } else if !remain.is_empty() {
-                    Err(nom::Err::Failure(ParseError {
-                        reason: Some(ParserErrorReason::BadRustIdent(
-                            remain.chars().next().unwrap(),
-                        )),
-                        expected: vec![ExpectedToken::CloseBracket, ExpectedToken::Ident],
-                        offset: got.len() + 1,
-                    }))
-                } else {
-                    Ok((i, i))
+    move |i: &str| {
+        map_parser(
+            take_till1(move |c| terminators.contains(c)),
+            move |i: &str| {
+                match take_till1::<_, _, ()>(|c| invalid_ident_chars.contains(c))(i) {
+                    Ok((remain, got)) => {
+                        // Detects if the first character is a digit.
+                        if !got.is_empty() && got.starts_with(|c: char| is_digit(c as u8)) {
+                            Err(nom::Err::Failure(ParseError {
+                                reason: Some(ParserErrorReason::BadRustIdent(
+                                    got.chars().next().unwrap(),
+                                )),
+                                expected: vec![ExpectedToken::Ident],
+                                offset: 1,
+                            }))
+                        } else if !remain.is_empty() {
+                            Err(nom::Err::Failure(ParseError {
+                                reason: Some(ParserErrorReason::BadRustIdent(
+                                    remain.chars().next().unwrap(),
+                                )),
+                                expected: vec![ExpectedToken::CloseBracket, ExpectedToken::Ident],
+                                offset: got.len() + 1,
+                            }))
+                        } else {
+                            Ok((i, i))
+                        }
+                    }
+                    Err(_) => Ok((i, i)),
                 }
-            }
-            Err(_) => Ok((i, i)),
-        }
-    })(i)
+            },
+        )(i)
+    }
 }
 
 /// Matches escaped items
 fn escaped_item_impl(i: &str) -> IResult<&str, &str> {
-    map(alt((tag("!!"), tag("{{"), tag("}}"))), |s| match s {
-        "!!" => "!",
-        "}}" => "}",
-        "{{" => "{",
-        _ => unreachable!(),
-    })(i)
+    map(
+        alt((
+            tag("!!"),
+            tag("{{"),
+            tag("}}"),
+            tag("[["),
+            tag("]]"),
+            tag("(("),
+            tag("))"),
+        )),
+        |s| match s {
+            "!!" => "!",
+            "}}" => "}",
+            "{{" => "{",
+            "[[" => "[",
+            "]]" => "]",
+            "((" => "(",
+            "))" => ")",
+            _ => unreachable!(),
+        },
+    )(i)
 }
 
 /// Matches "".
-pub fn nothing(i: &str) -> IResult<&str, RouteParserToken, ParseError> {
-    if i.len() == 0 {
+pub fn nothing(i: &str) -> IResult<&str, RouteParserToken<'_>, ParseError> {
+    if i.is_empty() {
         Ok((i, RouteParserToken::Nothing))
     } else {
         Err(nom::Err::Error(ParseError {
@@ -166,18 +218,54 @@ fn exact_impl(special_chars: &'static str) -> impl Fn(&str) -> IResult<&str, &st
 }
 
 const SPECIAL_CHARS: &str = r##"/?&#={}!"##;
+/// Brackets delimit optional sections and parentheses delimit alternatives,
+/// so they are only special in the path.
+const PATH_SPECIAL_CHARS: &str = r##"/?&#={}![]()"##;
 const FRAGMENT_SPECIAL_CHARS: &str = r##"{}!"##;
 
-pub fn exact(i: &str) -> IResult<&str, RouteParserToken, ParseError> {
-    map(exact_impl(SPECIAL_CHARS), RouteParserToken::Exact)(i)
+pub fn exact(i: &str) -> IResult<&str, RouteParserToken<'_>, ParseError> {
+    map(exact_impl(PATH_SPECIAL_CHARS), RouteParserToken::Exact)(i)
+}
+
+/// Matches `(a|b|c)`, which matches any one of the literals within it.
+///
+/// The alternatives are kept separated by `|`, and can't be empty or contain special characters.
+pub fn one_of(i: &str) -> IResult<&str, RouteParserToken<'_>, ParseError> {
+    let (rest, _) = get_open_paren(i)?;
+    let alternatives = &rest[..rest.find(')').unwrap_or(rest.len())];
+    let mut offset = 1;
+    for alternative in alternatives.split('|') {
+        if alternative.is_empty() {
+            return Err(nom::Err::Failure(ParseError {
+                reason: Some(ParserErrorReason::BadAlternative),
+                expected: vec![ExpectedToken::Literal],
+                offset,
+            }));
+        }
+        if let Some(position) = alternative.find(|c| PATH_SPECIAL_CHARS.contains(c)) {
+            return Err(nom::Err::Failure(ParseError {
+                reason: Some(ParserErrorReason::BadAlternative),
+                expected: vec![ExpectedToken::Literal, ExpectedToken::CloseParen],
+                offset: offset + position,
+            }));
+        }
+        offset += alternative.len() + 1;
+    }
+    let (rest, _) = get_close_paren(&rest[alternatives.len()..]).map_err(|_| {
+        nom::Err::Failure(ParseError {
+            reason: None,
+            expected: vec![ExpectedToken::CloseParen],
+            offset: 1 + alternatives.len(),
+        })
+    })?;
+    Ok((rest, RouteParserToken::OneOf(alternatives)))
 }
 
 /// More permissive exact matchers
-pub fn fragment_exact(i: &str) -> IResult<&str, RouteParserToken, ParseError> {
+pub fn fragment_exact(i: &str) -> IResult<&str, RouteParserToken<'_>, ParseError> {
     map(exact_impl(FRAGMENT_SPECIAL_CHARS), RouteParserToken::Exact)(i)
 }
 
-
 pub fn capture<'a>(
     field_naming_scheme: FieldNamingScheme,
 ) -> impl Fn(&'a str) -> IResult<&'a str, RouteParserToken<'a>, ParseError> {
@@ -199,12 +287,16 @@ fn capture_single_impl<'a>(
     move |i: &str| match field_naming_scheme {
         FieldNamingScheme::Named => delimited(
             get_open_bracket,
-            named::single_capture_impl,
+            alt((named::constrained_capture_impl, named::single_capture_impl)),
             get_close_bracket,
         )(i),
         FieldNamingScheme::Unnamed => delimited(
             get_open_bracket,
-            alt((named::single_capture_impl, unnamed::single_capture_impl)),
+            alt((
+                named::constrained_capture_impl,
+                named::single_capture_impl,
+                unnamed::single_capture_impl,
+            )),
             get_close_bracket,
         )(i),
         FieldNamingScheme::Unit => {
@@ -218,7 +310,7 @@ fn capture_single_impl<'a>(
     }
 }
 
-/// Captures {ident}, {*:ident}, {<number>:ident}
+/// Captures {ident}, {*:ident}, {<number>:ident}, {ident:constraint}
 ///
 /// Depending on the provided field naming, it may also match {}, {*}, and {<number>} for unnamed fields, or none at all for units.
 fn capture_impl<'a>(
@@ -229,6 +321,7 @@ fn capture_impl<'a>(
             let inner = alt((
                 named::many_capture_impl,
                 named::numbered_capture_impl,
+                named::constrained_capture_impl,
                 named::single_capture_impl,
             ));
             delimited(get_open_bracket, inner, get_close_bracket)(i)
@@ -239,6 +332,7 @@ fn capture_impl<'a>(
                 unnamed::many_capture_impl,
                 named::numbered_capture_impl,
                 unnamed::numbered_capture_impl,
+                named::constrained_capture_impl,
                 named::single_capture_impl,
                 unnamed::single_capture_impl,
             ));
@@ -252,20 +346,50 @@ fn capture_impl<'a>(
     }
 }
 
+/// Captures {ident}, {*:ident} and {ident:constraint} as the value of a query parameter,
+/// where {*:ident} captures the values of every parameter with the key.
+///
+/// Depending on the provided field naming, it may also match {} and {*} for unnamed fields, or none at all for units.
+fn query_capture_impl<'a>(
+    field_naming_scheme: FieldNamingScheme,
+) -> impl Fn(&'a str) -> IResult<&'a str, RefCaptureVariant<'a>, ParseError> {
+    move |i: &str| match field_naming_scheme {
+        FieldNamingScheme::Named => {
+            let inner = alt((
+                named::many_capture_impl,
+                named::constrained_capture_impl,
+                named::single_capture_impl,
+            ));
+            delimited(get_open_bracket, inner, get_close_bracket)(i)
+        }
+        FieldNamingScheme::Unnamed => {
+            let inner = alt((
+                named::many_capture_impl,
+                unnamed::many_capture_impl,
+                named::constrained_capture_impl,
+                named::single_capture_impl,
+                unnamed::single_capture_impl,
+            ));
+            delimited(get_open_bracket, inner, get_close_bracket)(i)
+        }
+        FieldNamingScheme::Unit => capture_single_impl(field_naming_scheme)(i),
+    }
+}
+
 mod named {
     use super::*;
-    pub fn single_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant, ParseError> {
-        map(rust_ident, |key| RefCaptureVariant::Named(key))(i)
+    pub fn single_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant<'_>, ParseError> {
+        map(rust_ident, RefCaptureVariant::Named)(i)
     }
 
-    pub fn many_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant, ParseError> {
+    pub fn many_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant<'_>, ParseError> {
         map(
             separated_pair(get_star, get_colon, rust_ident),
             |(_, key)| RefCaptureVariant::ManyNamed(key),
         )(i)
     }
 
-    pub fn numbered_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant, ParseError> {
+    pub fn numbered_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant<'_>, ParseError> {
         map(
             separated_pair(digit1, get_colon, rust_ident),
             |(number, key)| RefCaptureVariant::NumberedNamed {
@@ -274,6 +398,28 @@ mod named {
             },
         )(i)
     }
+
+    /// Matches `ident:constraint`, failing if the constraint is malformed.
+    pub fn constrained_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant<'_>, ParseError> {
+        let (i, name) = rust_ident_until(":}")(i)?;
+        let (i, _) = get_colon(i)?;
+        // Offset of the constraint, relative to the opening bracket.
+        let offset = 1 + name.len() + 1;
+        let (i, constraint) = take_till1::<_, _, ()>(|c| c == '}')(i).map_err(|_| {
+            nom::Err::Failure(ParseError {
+                reason: None,
+                expected: vec![ExpectedToken::Constraint],
+                offset,
+            })
+        })?;
+        parse_constraint(constraint).map_err(|error| {
+            nom::Err::Failure(ParseError {
+                offset: offset + error.offset,
+                ..error
+            })
+        })?;
+        Ok((i, RefCaptureVariant::Constrained { name, constraint }))
+    }
 }
 
 mod unnamed {
@@ -281,15 +427,15 @@ mod unnamed {
 
     /// #Note
     /// because this always succeeds, try this last
-    pub fn single_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant, ParseError> {
+    pub fn single_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant<'_>, ParseError> {
         Ok((i, RefCaptureVariant::Unnamed))
     }
 
-    pub fn many_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant, ParseError> {
+    pub fn many_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant<'_>, ParseError> {
         map(get_star, |_| RefCaptureVariant::ManyUnnamed)(i)
     }
 
-    pub fn numbered_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant, ParseError> {
+    pub fn numbered_capture_impl(i: &str) -> IResult<&str, RefCaptureVariant<'_>, ParseError> {
         map(digit1, |number: &str| RefCaptureVariant::NumberedUnnamed {
             sections: number.parse().unwrap(),
         })(i)
@@ -301,12 +447,13 @@ fn cap_or_exact<'a>(
     field_naming_scheme: FieldNamingScheme,
 ) -> impl Fn(&'a str) -> IResult<&'a str, CaptureOrExact<'a>, ParseError> {
     move |i: &str| {
+        // Literals are tried first, as captures always fail for units.
         alt((
+            map(exact_impl(SPECIAL_CHARS), CaptureOrExact::Exact),
             map(
-                capture_single_impl(field_naming_scheme),
+                query_capture_impl(field_naming_scheme),
                 CaptureOrExact::Capture,
             ),
-            map(exact_impl(SPECIAL_CHARS), CaptureOrExact::Exact),
         ))(i)
     }
 }
@@ -354,6 +501,26 @@ mod test {
         query(FieldNamingScheme::Named)("lorem=ipsum").expect("should parse");
     }
 
+    #[test]
+    fn query_section_capture_repeated() {
+        let (_, token) = query(FieldNamingScheme::Named)("tag={*:tags}").expect("should parse");
+        assert_eq!(
+            token,
+            RouteParserToken::Query {
+                ident: "tag",
+                capture_or_exact: CaptureOrExact::Capture(RefCaptureVariant::ManyNamed("tags"))
+            }
+        );
+        query(FieldNamingScheme::Unnamed)("tag={*}").expect("should parse");
+        query(FieldNamingScheme::Named)("tag={2:tags}").expect_err("should not parse");
+    }
+
+    #[test]
+    fn query_section_exact_in_unit() {
+        query(FieldNamingScheme::Unit)("lorem=ipsum").expect("should parse");
+        query(FieldNamingScheme::Unit)("lorem={ipsum}").expect_err("should not parse");
+    }
+
     #[test]
     fn query_section_capture_named() {
         query(FieldNamingScheme::Named)("lorem={ipsum}").expect("should parse");
@@ -371,6 +538,52 @@ mod test {
     fn non_leading_numbers_in_ident() {
         rust_ident("hello5").expect("sholud parse");
     }
+    #[test]
+    fn constrained_capture() {
+        let (_, cap) = capture(FieldNamingScheme::Named)("{id:u32}").expect("should parse");
+        assert_eq!(
+            cap,
+            RouteParserToken::Capture(RefCaptureVariant::Constrained {
+                name: "id",
+                constraint: "u32"
+            })
+        );
+        capture(FieldNamingScheme::Named)("{name:[a-z0-9_-]+}").expect("should parse");
+        capture_single(FieldNamingScheme::Named)("{id:u32}").expect("should parse");
+    }
+
+    #[test]
+    fn malformed_constraint_fails() {
+        let error = capture(FieldNamingScheme::Named)("{name:[a-z}").expect_err("should not parse");
+        match error {
+            nom::Err::Failure(error) => {
+                assert_eq!(error.reason, Some(ParserErrorReason::BadPattern));
+                assert_eq!(error.offset, 10);
+            }
+            _ => panic!("should fail"),
+        }
+        capture(FieldNamingScheme::Named)("{name:}").expect_err("should not parse");
+        capture(FieldNamingScheme::Named)("{id:u33}").expect_err("should not parse");
+    }
+
+    #[test]
+    fn alternatives() {
+        let (rest, token) = one_of("(posts|articles)/").expect("should parse");
+        assert_eq!(rest, "/");
+        assert_eq!(token, RouteParserToken::OneOf("posts|articles"));
+    }
+
+    #[test]
+    fn malformed_alternatives_fail() {
+        let offset = |i| match one_of(i) {
+            Err(nom::Err::Failure(error)) => error.offset,
+            _ => panic!("should fail"),
+        };
+        assert_eq!(offset("(posts||articles)"), 7);
+        assert_eq!(offset("(posts|art/icles)"), 10);
+        assert_eq!(offset("(posts|articles"), 15);
+    }
+
     #[test]
     fn leading_numbers_in_ident_fails() {
         rust_ident("5hello").expect_err("sholud not parse");
@@ -39,11 +39,10 @@ impl<'a> fmt::Debug for PrettyParseError<'a> {
             f.write_str("Expected: ")?;
             self.error.expected[..self.error.expected.len() - 1]
                 .iter()
-                .map(|expected| {
+                .try_for_each(|expected| {
                     <ExpectedToken as fmt::Display>::fmt(expected, f)
                         .and_then(|_| f.write_str(", "))
-                })
-                .collect::<Result<(), fmt::Error>>()?;
+                })?;
             self.error
                 .expected
                 .last()
@@ -134,6 +133,16 @@ pub enum ExpectedToken {
     Star,
     /// :
     Colon,
+    /// type or pattern after the : in a capture
+    Constraint,
+    /// [
+    OpenOptional,
+    /// ]
+    CloseOptional,
+    /// (
+    OpenParen,
+    /// )
+    CloseParen,
 }
 
 impl fmt::Display for ExpectedToken {
@@ -151,6 +160,11 @@ impl fmt::Display for ExpectedToken {
             ExpectedToken::Equals => f.write_str("="),
             ExpectedToken::Star => f.write_str("*"),
             ExpectedToken::Colon => f.write_str(":"),
+            ExpectedToken::Constraint => f.write_str("<constraint>"),
+            ExpectedToken::OpenOptional => f.write_str("["),
+            ExpectedToken::CloseOptional => f.write_str("]"),
+            ExpectedToken::OpenParen => f.write_str("("),
+            ExpectedToken::CloseParen => f.write_str(")"),
         }
     }
 }
@@ -183,6 +197,19 @@ pub enum ParserErrorReason {
     NotAllowedStateTransition,
     /// Expected a specific token
     Expected(ExpectedToken),
+    /// A constraint looks like a type, but isn't one of the supported types.
+    UnknownConstraintType,
+    /// A constraint pattern is malformed.
+    BadPattern,
+    /// A [ without a matching ], or the other way around.
+    UnbalancedOptional,
+    /// An optional section that is empty, nested, starts with a capture, is followed by a capture,
+    /// or is outside of the path.
+    BadOptional,
+    /// More optional sections than `MAX_OPTIONAL_SECTIONS`.
+    TooManyOptionals,
+    /// An alternative within (a|b) is empty or contains a special character.
+    BadAlternative,
 }
 
 impl fmt::Display for ParserErrorReason {
@@ -227,6 +254,29 @@ impl fmt::Display for ParserErrorReason {
             ParserErrorReason::CapturesInUnit => {
                 f.write_str("Cannot have a capture section for a unit struct or variant.")?;
             }
+            ParserErrorReason::UnknownConstraintType => {
+                f.write_str("Unknown constraint type. Supported types are integers (eg. `u32`), `bool`, `date` and `uuid`.")?;
+            }
+            ParserErrorReason::BadPattern => {
+                f.write_str("Malformed constraint pattern. Patterns are made of characters and character classes (eg. `[a-z_]`), optionally followed by `?`, `*` or `+`, and separated into alternatives by `|`.")?;
+            }
+            ParserErrorReason::UnbalancedOptional => {
+                f.write_str(
+                    "Every optional section must be opened with a '[' and closed with a ']'.",
+                )?;
+            }
+            ParserErrorReason::BadOptional => {
+                f.write_str("Optional sections ([...]) must start with a '/' or literal, can't be empty or nested, can't be followed directly by a capture, and can only be in the path.")?;
+            }
+            ParserErrorReason::TooManyOptionals => {
+                f.write_str(&format!(
+                    "A matcher can't have more than {} optional sections ([...]).",
+                    crate::parser::MAX_OPTIONAL_SECTIONS
+                ))?;
+            }
+            ParserErrorReason::BadAlternative => {
+                f.write_str("Alternatives ((a|b)) must be literals without special characters, separated by '|'.")?;
+            }
         }
         Ok(())
     }
@@ -13,32 +13,75 @@
     unused_qualifications
 )]
 
+mod constraint;
 mod core;
 mod error;
 pub mod parser;
 pub use crate::core::FieldNamingScheme;
+pub use constraint::{CharClass, Constraint, PatternItem, Repetition, TypeConstraint};
 pub use error::{ParseError, PrettyParseError};
 mod optimizer;
 pub use optimizer::{convert_tokens, parse_str_and_optimize_tokens};
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
-/// Alias of `HashMap<&'a str, String>` that represent strings captured from a route.
+/// Alias of `HashMap<&'a str, Cow<'a, str>>` that represent strings captured from a route.
 ///
 /// Captures contain keys corresponding to named match sections,
 /// and values containing the content captured by those sections.
-pub type Captures<'a> = HashMap<&'a str, String>;
+/// The values borrow from the route that was matched, unless they had to be decoded.
+pub type Captures<'a> = HashMap<&'a str, Cow<'a, str>>;
 
 /// Tokens used to determine how to match and capture sections from a URL.
 #[derive(Debug, PartialEq, Clone)]
 pub enum MatcherToken {
     /// Section-related tokens can be condensed into a match.
     Exact(String),
+    /// Matches any one of the literals, written as `(a|b)`.
+    OneOf(Vec<String>),
     /// Capture section.
     Capture(CaptureVariant),
     /// End token - if the string hasn't been consumed entirely, then the parse will fail.
     /// This is useful for being able to specify more general matchers for variants that would
     /// otherwise match above more specific variants.
     End,
+    /// A section of the path, written as `[...]`, that is matched if possible and skipped otherwise.
+    ///
+    /// The tokens within it never contain another optional section.
+    Optional(Vec<MatcherToken>),
+    /// The query, written as `?key=value&key={capture}`.
+    ///
+    /// The parameters are matched regardless of the order they appear in,
+    /// and parameters that aren't listed are ignored, unless the query is strict.
+    Query {
+        /// The parameters that the query must contain.
+        params: Vec<QueryParam>,
+        /// Whether parameters that aren't listed prevent the query from matching.
+        /// A query is strict when it is followed by the end token (`!`).
+        strict: bool,
+    },
+}
+
+/// A parameter that must appear in the query.
+#[derive(Debug, PartialEq, Clone)]
+pub struct QueryParam {
+    /// The key of the parameter.
+    pub key: String,
+    /// What the value of the parameter must be.
+    pub value: QueryValue,
+    /// Whether the query can match without the parameter, in which case nothing is captured.
+    ///
+    /// Matcher strings always produce required parameters,
+    /// but the derived `Switch` makes the parameters that populate `Option` fields optional.
+    pub optional: bool,
+}
+
+/// The value of a query parameter.
+#[derive(Debug, PartialEq, Clone)]
+pub enum QueryValue {
+    /// The value must be this literal.
+    Exact(String),
+    /// The value is captured.
+    Capture(CaptureVariant),
 }
 
 /// Variants that indicate how part of a string should be captured.
@@ -64,4 +107,12 @@ pub enum CaptureVariant {
         /// The key to be entered in the `Matches` map.
         name: String,
     },
+    /// {name:constraint} - captures a section with a given name,
+    /// but only if it satisfies the constraint.
+    Constrained {
+        /// The key to be entered in the `Matches` map.
+        name: String,
+        /// The constraint the section must satisfy.
+        constraint: Constraint,
+    },
 }
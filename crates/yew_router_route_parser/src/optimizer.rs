@@ -3,7 +3,10 @@ use crate::{
     parser::{parse, CaptureOrExact, RefCaptureVariant, RouteParserToken},
 };
 
-use crate::{core::FieldNamingScheme, CaptureVariant, MatcherToken};
+use crate::{
+    constraint::parse_constraint, core::FieldNamingScheme, CaptureVariant, MatcherToken,
+    QueryParam, QueryValue,
+};
 
 impl<'a> From<RefCaptureVariant<'a>> for CaptureVariant {
     fn from(v: RefCaptureVariant<'a>) -> Self {
@@ -14,6 +17,11 @@ impl<'a> From<RefCaptureVariant<'a>> for CaptureVariant {
                 sections,
                 name: name.to_string(),
             },
+            RefCaptureVariant::Constrained { name, constraint } => CaptureVariant::Constrained {
+                name: name.to_string(),
+                constraint: parse_constraint(constraint)
+                    .expect("constraints are checked when they are parsed"),
+            },
             RefCaptureVariant::Unnamed => CaptureVariant::Unnamed,
             RefCaptureVariant::ManyUnnamed => CaptureVariant::ManyUnnamed,
             RefCaptureVariant::NumberedUnnamed { sections } => {
@@ -23,11 +31,11 @@ impl<'a> From<RefCaptureVariant<'a>> for CaptureVariant {
     }
 }
 
-impl<'a> From<CaptureOrExact<'a>> for MatcherToken {
+impl<'a> From<CaptureOrExact<'a>> for QueryValue {
     fn from(value: CaptureOrExact<'a>) -> Self {
         match value {
-            CaptureOrExact::Exact(m) => MatcherToken::Exact(m.to_string()),
-            CaptureOrExact::Capture(v) => MatcherToken::Capture(v.into()),
+            CaptureOrExact::Exact(m) => QueryValue::Exact(m.to_string()),
+            CaptureOrExact::Capture(v) => QueryValue::Capture(v.into()),
         }
     }
 }
@@ -36,14 +44,17 @@ impl<'a> RouteParserToken<'a> {
     fn as_str(&self) -> &str {
         match self {
             RouteParserToken::Separator => "/",
-            RouteParserToken::Exact(literal) => &literal,
-            RouteParserToken::QueryBegin => "?",
-            RouteParserToken::QuerySeparator => "&",
+            RouteParserToken::Exact(literal) => literal,
             RouteParserToken::FragmentBegin => "#",
             RouteParserToken::Nothing
+            | RouteParserToken::QueryBegin
+            | RouteParserToken::QuerySeparator
+            | RouteParserToken::OneOf(_)
             | RouteParserToken::Capture { .. }
             | RouteParserToken::Query { .. }
-            | RouteParserToken::End => unreachable!(),
+            | RouteParserToken::End
+            | RouteParserToken::OptionalBegin
+            | RouteParserToken::OptionalEnd => unreachable!(),
         }
     }
 }
@@ -52,7 +63,7 @@ impl<'a> RouteParserToken<'a> {
 pub fn parse_str_and_optimize_tokens(
     i: &str,
     field_naming_scheme: FieldNamingScheme,
-) -> Result<Vec<MatcherToken>, PrettyParseError> {
+) -> Result<Vec<MatcherToken>, PrettyParseError<'_>> {
     let tokens = parse(i, field_naming_scheme)?;
     Ok(convert_tokens(&tokens))
 }
@@ -61,9 +72,15 @@ pub fn parse_str_and_optimize_tokens(
 ///
 /// In the process of converting the tokens, this function will condense multiple RouteParserTokens
 /// that represent literals into one Exact variant if multiple reducible tokens happen to occur in a row.
+/// The tokens between an OptionalBegin and OptionalEnd are gathered into one Optional variant,
+/// and the parameters of the query are gathered into one Query variant.
 pub fn convert_tokens(tokens: &[RouteParserToken]) -> Vec<MatcherToken> {
     let mut new_tokens = vec![];
     let mut run: Vec<RouteParserToken> = vec![];
+    // The tokens from before the optional section that is being converted.
+    let mut outer_tokens: Option<Vec<MatcherToken>> = None;
+    // The parameters of the query that is being converted.
+    let mut query: Option<Vec<QueryParam>> = None;
 
     fn empty_run(run: &mut Vec<RouteParserToken>) -> MatcherToken {
         let segment = run.iter().map(RouteParserToken::as_str).collect::<String>();
@@ -72,50 +89,76 @@ pub fn convert_tokens(tokens: &[RouteParserToken]) -> Vec<MatcherToken> {
         MatcherToken::Exact(segment)
     }
 
-    fn empty_run_with_query_cap_at_end(
-        run: &mut Vec<RouteParserToken>,
-        query_lhs: &str,
-    ) -> MatcherToken {
-        let segment = run
-            .iter()
-            .map(RouteParserToken::as_str)
-            .chain(Some(query_lhs))
-            .chain(Some("="))
-            .collect::<String>();
-        run.clear();
-
-        MatcherToken::Exact(segment)
-    }
-
     for token in tokens.iter() {
         match token {
-            RouteParserToken::QueryBegin
-            | RouteParserToken::FragmentBegin
-            | RouteParserToken::Separator
-            | RouteParserToken::QuerySeparator
-            | RouteParserToken::Exact(_) => run.push(*token),
+            RouteParserToken::Separator | RouteParserToken::Exact(_) => run.push(*token),
+            RouteParserToken::OneOf(alternatives) => {
+                if !run.is_empty() {
+                    new_tokens.push(empty_run(&mut run));
+                }
+                let alternatives = alternatives.split('|').map(String::from).collect();
+                new_tokens.push(MatcherToken::OneOf(alternatives));
+            }
             RouteParserToken::Capture(cap) => {
                 new_tokens.push(empty_run(&mut run));
                 new_tokens.push(MatcherToken::Capture(CaptureVariant::from(*cap)))
             }
+            RouteParserToken::QueryBegin => {
+                if !run.is_empty() {
+                    new_tokens.push(empty_run(&mut run));
+                }
+                query = Some(vec![]);
+            }
             RouteParserToken::Query {
                 ident,
                 capture_or_exact,
-            } => match capture_or_exact {
-                CaptureOrExact::Exact(s) => {
-                    run.push(RouteParserToken::Exact(ident));
-                    run.push(RouteParserToken::Exact("="));
-                    run.push(RouteParserToken::Exact(s));
+            } => query
+                .as_mut()
+                .expect("query parameters follow the start of the query when they are parsed")
+                .push(QueryParam {
+                    key: ident.to_string(),
+                    value: QueryValue::from(*capture_or_exact),
+                    optional: false,
+                }),
+            RouteParserToken::QuerySeparator => {}
+            RouteParserToken::FragmentBegin => {
+                if let Some(params) = query.take() {
+                    new_tokens.push(MatcherToken::Query {
+                        params,
+                        strict: false,
+                    });
                 }
-                CaptureOrExact::Capture(cap) => {
-                    new_tokens.push(empty_run_with_query_cap_at_end(&mut run, *ident));
-                    new_tokens.push(MatcherToken::Capture(CaptureVariant::from(*cap)))
-                }
-            },
+                run.push(*token)
+            }
             RouteParserToken::End => {
-                new_tokens.push(empty_run(&mut run));
+                // An empty literal would be used as the delimiter of a capture before the end.
+                if !run.is_empty() {
+                    new_tokens.push(empty_run(&mut run));
+                }
+                if let Some(params) = query.take() {
+                    new_tokens.push(MatcherToken::Query {
+                        params,
+                        strict: true,
+                    });
+                }
                 new_tokens.push(MatcherToken::End);
             }
+            RouteParserToken::OptionalBegin => {
+                if !run.is_empty() {
+                    new_tokens.push(empty_run(&mut run));
+                }
+                outer_tokens = Some(std::mem::take(&mut new_tokens));
+            }
+            RouteParserToken::OptionalEnd => {
+                if !run.is_empty() {
+                    new_tokens.push(empty_run(&mut run));
+                }
+                let outer_tokens = outer_tokens
+                    .take()
+                    .expect("optional sections are balanced when they are parsed");
+                let section = std::mem::replace(&mut new_tokens, outer_tokens);
+                new_tokens.push(MatcherToken::Optional(section));
+            }
             RouteParserToken::Nothing => {}
         }
     }
@@ -124,6 +167,12 @@ pub fn convert_tokens(tokens: &[RouteParserToken]) -> Vec<MatcherToken> {
     if !run.is_empty() {
         new_tokens.push(empty_run(&mut run));
     }
+    if let Some(params) = query.take() {
+        new_tokens.push(MatcherToken::Query {
+            params,
+            strict: false,
+        });
+    }
 
     new_tokens
 }
@@ -138,4 +187,78 @@ mod tests {
         let tokens = parse_str_and_optimize_tokens("", FieldNamingScheme::Unit).unwrap();
         assert_eq!(tokens, vec![])
     }
+
+    #[test]
+    fn alternatives_are_split() {
+        let tokens =
+            parse_str_and_optimize_tokens("/(posts|articles)/{slug}", FieldNamingScheme::Named)
+                .unwrap();
+        let expected = vec![
+            MatcherToken::Exact("/".to_string()),
+            MatcherToken::OneOf(vec!["posts".to_string(), "articles".to_string()]),
+            MatcherToken::Exact("/".to_string()),
+            MatcherToken::Capture(CaptureVariant::Named("slug".to_string())),
+        ];
+        assert_eq!(tokens, expected)
+    }
+
+    #[test]
+    fn query_parameters_are_grouped() {
+        let tokens =
+            parse_str_and_optimize_tokens("/search?q={q}&sort=new", FieldNamingScheme::Named)
+                .unwrap();
+        let expected = vec![
+            MatcherToken::Exact("/search".to_string()),
+            MatcherToken::Query {
+                params: vec![
+                    QueryParam {
+                        key: "q".to_string(),
+                        value: QueryValue::Capture(CaptureVariant::Named("q".to_string())),
+                        optional: false,
+                    },
+                    QueryParam {
+                        key: "sort".to_string(),
+                        value: QueryValue::Exact("new".to_string()),
+                        optional: false,
+                    },
+                ],
+                strict: false,
+            },
+        ];
+        assert_eq!(tokens, expected)
+    }
+
+    #[test]
+    fn query_before_end_is_strict() {
+        let tokens =
+            parse_str_and_optimize_tokens("?page={page}!", FieldNamingScheme::Named).unwrap();
+        assert_eq!(
+            tokens[0],
+            MatcherToken::Query {
+                params: vec![QueryParam {
+                    key: "page".to_string(),
+                    value: QueryValue::Capture(CaptureVariant::Named("page".to_string())),
+                    optional: false,
+                }],
+                strict: true,
+            }
+        );
+        assert_eq!(tokens[1], MatcherToken::End);
+    }
+
+    #[test]
+    fn optional_sections_are_grouped() {
+        let tokens =
+            parse_str_and_optimize_tokens("/users[/{id}][/edit]", FieldNamingScheme::Named)
+                .unwrap();
+        let expected = vec![
+            MatcherToken::Exact("/users".to_string()),
+            MatcherToken::Optional(vec![
+                MatcherToken::Exact("/".to_string()),
+                MatcherToken::Capture(CaptureVariant::Named("id".to_string())),
+            ]),
+            MatcherToken::Optional(vec![MatcherToken::Exact("/edit".to_string())]),
+        ];
+        assert_eq!(tokens, expected)
+    }
 }
@@ -1,10 +1,10 @@
 //! Parser that consumes a string and produces the first representation of the matcher.
 use crate::{
     core::{
-        capture, capture_single, exact, fragment_exact, get_and, get_end, get_hash, get_question,
-        get_slash, nothing, query,
+        capture, capture_single, exact, fragment_exact, get_and, get_close_optional, get_end,
+        get_hash, get_open_optional, get_question, get_slash, nothing, one_of, query,
     },
-    error::{get_reason, ParseError, ParserErrorReason, PrettyParseError},
+    error::{get_reason, ExpectedToken, ParseError, ParserErrorReason, PrettyParseError},
     FieldNamingScheme,
 };
 use nom::{branch::alt, IResult};
@@ -20,6 +20,8 @@ pub enum RouteParserToken<'a> {
     Separator,
     /// Match a specific string.
     Exact(&'a str),
+    /// Match (a|b) - any one of the alternatives, which are still separated by |.
+    OneOf(&'a str),
     /// Match {_}. See `RefCaptureVariant` for more.
     Capture(RefCaptureVariant<'a>),
     /// Match ?
@@ -37,6 +39,10 @@ pub enum RouteParserToken<'a> {
     FragmentBegin,
     /// Match !
     End,
+    /// Match [ - starts a section that may be skipped.
+    OptionalBegin,
+    /// Match ] - ends a section that may be skipped.
+    OptionalEnd,
 }
 
 /// Token representing various types of captures.
@@ -68,6 +74,13 @@ pub enum RefCaptureVariant<'a> {
         /// The key to be entered in the `Matches` map.
         name: &'a str,
     },
+    /// {name:constraint} - captures a section that satisfies a constraint with a given name.
+    Constrained {
+        /// The key to be entered in the `Matches` map.
+        name: &'a str,
+        /// The text of the constraint, which has been checked to be well formed.
+        constraint: &'a str,
+    },
 }
 
 /// Either a Capture, or an Exact match
@@ -84,6 +97,7 @@ pub enum CaptureOrExact<'a> {
 enum ParserState<'a> {
     None,
     Path { prev_token: RouteParserToken<'a> },
+    Optional { prev_token: RouteParserToken<'a> },
     FirstQuery { prev_token: RouteParserToken<'a> },
     NthQuery { prev_token: RouteParserToken<'a> },
     Fragment { prev_token: RouteParserToken<'a> },
@@ -101,17 +115,22 @@ impl<'a> ParserState<'a> {
             ParserState::None => match token {
                 RouteParserToken::Separator
                 | RouteParserToken::Exact(_)
+                | RouteParserToken::OneOf(_)
                 | RouteParserToken::Capture(_) => Ok(ParserState::Path { prev_token: token }),
                 RouteParserToken::QueryBegin => Ok(ParserState::FirstQuery { prev_token: token }),
                 RouteParserToken::QuerySeparator => Ok(ParserState::NthQuery { prev_token: token }),
                 RouteParserToken::Query { .. } => Err(ParserErrorReason::NotAllowedStateTransition),
                 RouteParserToken::FragmentBegin => Ok(ParserState::Fragment { prev_token: token }),
                 RouteParserToken::Nothing | RouteParserToken::End => Ok(ParserState::End),
+                RouteParserToken::OptionalBegin => Ok(ParserState::Optional { prev_token: token }),
+                RouteParserToken::OptionalEnd => Err(ParserErrorReason::NotAllowedStateTransition),
             },
             ParserState::Path { prev_token } => {
                 match prev_token {
                     RouteParserToken::Separator => match token {
-                        RouteParserToken::Exact(_) | RouteParserToken::Capture(_) => {
+                        RouteParserToken::Exact(_)
+                        | RouteParserToken::OneOf(_)
+                        | RouteParserToken::Capture(_) => {
                             Ok(ParserState::Path { prev_token: token })
                         }
                         RouteParserToken::QueryBegin => {
@@ -121,10 +140,14 @@ impl<'a> ParserState<'a> {
                             Ok(ParserState::Fragment { prev_token: token })
                         }
                         RouteParserToken::End => Ok(ParserState::End),
+                        RouteParserToken::OptionalBegin => {
+                            Ok(ParserState::Optional { prev_token: token })
+                        }
                         _ => Err(ParserErrorReason::NotAllowedStateTransition),
                     },
-                    RouteParserToken::Exact(_) => match token {
+                    RouteParserToken::Exact(_) | RouteParserToken::OneOf(_) => match token {
                         RouteParserToken::Exact(_)
+                        | RouteParserToken::OneOf(_)
                         | RouteParserToken::Separator
                         | RouteParserToken::Capture(_) => {
                             Ok(ParserState::Path { prev_token: token })
@@ -136,11 +159,33 @@ impl<'a> ParserState<'a> {
                             Ok(ParserState::Fragment { prev_token: token })
                         }
                         RouteParserToken::End => Ok(ParserState::End),
+                        RouteParserToken::OptionalBegin => {
+                            Ok(ParserState::Optional { prev_token: token })
+                        }
                         _ => Err(ParserErrorReason::NotAllowedStateTransition),
                     },
                     RouteParserToken::Capture(_) => match token {
-                        RouteParserToken::Separator | RouteParserToken::Exact(_) => {
-                            Ok(ParserState::Path { prev_token: token })
+                        RouteParserToken::Separator
+                        | RouteParserToken::Exact(_)
+                        | RouteParserToken::OneOf(_) => Ok(ParserState::Path { prev_token: token }),
+                        RouteParserToken::QueryBegin => {
+                            Ok(ParserState::FirstQuery { prev_token: token })
+                        }
+                        RouteParserToken::FragmentBegin => {
+                            Ok(ParserState::Fragment { prev_token: token })
+                        }
+                        RouteParserToken::End => Ok(ParserState::End),
+                        RouteParserToken::OptionalBegin => {
+                            Ok(ParserState::Optional { prev_token: token })
+                        }
+                        _ => Err(ParserErrorReason::NotAllowedStateTransition),
+                    },
+                    RouteParserToken::OptionalEnd => match token {
+                        RouteParserToken::Separator
+                        | RouteParserToken::Exact(_)
+                        | RouteParserToken::OneOf(_) => Ok(ParserState::Path { prev_token: token }),
+                        RouteParserToken::OptionalBegin => {
+                            Ok(ParserState::Optional { prev_token: token })
                         }
                         RouteParserToken::QueryBegin => {
                             Ok(ParserState::FirstQuery { prev_token: token })
@@ -155,6 +200,43 @@ impl<'a> ParserState<'a> {
                                                                 * invalid within a Path state. */
                 }
             }
+            // Optional sections can't contain anything other than path tokens,
+            // and go back to the path once they are closed.
+            ParserState::Optional { prev_token } => match prev_token {
+                RouteParserToken::OptionalBegin => match token {
+                    RouteParserToken::Separator
+                    | RouteParserToken::Exact(_)
+                    | RouteParserToken::OneOf(_) => Ok(ParserState::Optional { prev_token: token }),
+                    _ => Err(ParserErrorReason::NotAllowedStateTransition),
+                },
+                RouteParserToken::Separator => match token {
+                    RouteParserToken::Exact(_)
+                    | RouteParserToken::OneOf(_)
+                    | RouteParserToken::Capture(_) => {
+                        Ok(ParserState::Optional { prev_token: token })
+                    }
+                    RouteParserToken::OptionalEnd => Ok(ParserState::Path { prev_token: token }),
+                    _ => Err(ParserErrorReason::NotAllowedStateTransition),
+                },
+                RouteParserToken::Exact(_) | RouteParserToken::OneOf(_) => match token {
+                    RouteParserToken::Exact(_)
+                    | RouteParserToken::OneOf(_)
+                    | RouteParserToken::Separator
+                    | RouteParserToken::Capture(_) => {
+                        Ok(ParserState::Optional { prev_token: token })
+                    }
+                    RouteParserToken::OptionalEnd => Ok(ParserState::Path { prev_token: token }),
+                    _ => Err(ParserErrorReason::NotAllowedStateTransition),
+                },
+                RouteParserToken::Capture(_) => match token {
+                    RouteParserToken::Separator
+                    | RouteParserToken::Exact(_)
+                    | RouteParserToken::OneOf(_) => Ok(ParserState::Optional { prev_token: token }),
+                    RouteParserToken::OptionalEnd => Ok(ParserState::Path { prev_token: token }),
+                    _ => Err(ParserErrorReason::NotAllowedStateTransition),
+                },
+                _ => Err(ParserErrorReason::InvalidState),
+            },
             ParserState::FirstQuery { prev_token } => match prev_token {
                 RouteParserToken::QueryBegin => match token {
                     RouteParserToken::Query { .. } => {
@@ -205,6 +287,12 @@ impl<'a> ParserState<'a> {
     }
 }
 
+/// The most optional sections that a matcher string can have.
+///
+/// Routes are matched against every way of including or skipping the optional sections that
+/// the route allows, so this keeps the number of those ways small.
+pub const MAX_OPTIONAL_SECTIONS: usize = 8;
+
 /// Parse a matching string into a vector of RouteParserTokens.
 ///
 /// The parsing logic involves using a state machine.
@@ -216,10 +304,11 @@ impl<'a> ParserState<'a> {
 pub fn parse(
     mut i: &str,
     field_naming_scheme: FieldNamingScheme,
-) -> Result<Vec<RouteParserToken>, PrettyParseError> {
+) -> Result<Vec<RouteParserToken<'_>>, PrettyParseError<'_>> {
     let input = i;
     let mut tokens: Vec<RouteParserToken> = vec![];
     let mut state = ParserState::None;
+    let mut optional_sections = 0;
 
     loop {
         let (ii, token) = parse_impl(i, &state, field_naming_scheme).map_err(|e| match e {
@@ -231,7 +320,7 @@ pub fn parse(
             _ => panic!("parser should not be incomplete"),
         })?;
         i = ii;
-        state = state.transition(token.clone()).map_err(|reason| {
+        state = state.transition(token).map_err(|reason| {
             let error = ParseError {
                 reason: Some(reason),
                 expected: vec![],
@@ -243,6 +332,20 @@ pub fn parse(
                 remaining: i,
             }
         })?;
+        if let RouteParserToken::OptionalBegin = token {
+            optional_sections += 1;
+            if optional_sections > MAX_OPTIONAL_SECTIONS {
+                return Err(PrettyParseError {
+                    error: ParseError {
+                        reason: Some(ParserErrorReason::TooManyOptionals),
+                        expected: vec![],
+                        offset: 0,
+                    },
+                    input,
+                    remaining: i,
+                });
+            }
+        }
         tokens.push(token);
 
         // If there is no more input, break out of the loop
@@ -250,6 +353,18 @@ pub fn parse(
             break;
         }
     }
+
+    if let ParserState::Optional { .. } = state {
+        return Err(PrettyParseError {
+            error: ParseError {
+                reason: Some(ParserErrorReason::UnbalancedOptional),
+                expected: vec![ExpectedToken::CloseOptional],
+                offset: 0,
+            },
+            input,
+            remaining: i,
+        });
+    }
     Ok(tokens)
 }
 
@@ -266,6 +381,8 @@ fn parse_impl<'a>(
             get_hash,
             capture(field_naming_scheme),
             exact,
+            one_of,
+            get_open_optional,
             get_end,
             nothing,
         ))(i),
@@ -273,7 +390,9 @@ fn parse_impl<'a>(
             RouteParserToken::Separator => {
                 alt((
                     exact,
+                    one_of,
                     capture(field_naming_scheme),
+                    get_open_optional,
                     get_question,
                     get_hash,
                     get_end,
@@ -284,16 +403,21 @@ fn parse_impl<'a>(
                     *reason = get_slash(i)
                         .map(|_| ParserErrorReason::DoubleSlash)
                         .or_else(|_| get_and(i).map(|_| ParserErrorReason::AndBeforeQuestion))
+                        .or_else(|_| {
+                            get_close_optional(i).map(|_| ParserErrorReason::UnbalancedOptional)
+                        })
                         .ok()
                         .or(*reason);
                     e
                 })
             }
-            RouteParserToken::Exact(_) => {
+            RouteParserToken::Exact(_) | RouteParserToken::OneOf(_) => {
                 alt((
                     get_slash,
                     exact, // This will handle escaped items
+                    one_of,
                     capture(field_naming_scheme),
+                    get_open_optional,
                     get_question,
                     get_hash,
                     get_end,
@@ -303,31 +427,117 @@ fn parse_impl<'a>(
                     let reason: &mut Option<ParserErrorReason> = get_reason(&mut e);
                     *reason = get_and(i)
                         .map(|_| ParserErrorReason::AndBeforeQuestion)
+                        .or_else(|_| {
+                            get_close_optional(i).map(|_| ParserErrorReason::UnbalancedOptional)
+                        })
                         .ok()
                         .or(*reason);
                     e
                 })
             }
-            RouteParserToken::Capture(_) => {
-                alt((get_slash, exact, get_question, get_hash, get_end))(i).map_err(
-                    |mut e: nom::Err<ParseError>| {
-                        // Detect likely failures if the above failed to match.
-                        let reason: &mut Option<ParserErrorReason> = get_reason(&mut e);
-                        *reason = capture(field_naming_scheme)(i)
-                            .map(|_| ParserErrorReason::AdjacentCaptures)
-                            .or_else(|_| get_and(i).map(|_| ParserErrorReason::AndBeforeQuestion))
-                            .ok()
-                            .or(*reason);
-                        e
-                    },
-                )
-            }
+            RouteParserToken::Capture(_) => alt((
+                get_slash,
+                exact,
+                one_of,
+                get_open_optional,
+                get_question,
+                get_hash,
+                get_end,
+            ))(i)
+            .map_err(|mut e: nom::Err<ParseError>| {
+                // Detect likely failures if the above failed to match.
+                let reason: &mut Option<ParserErrorReason> = get_reason(&mut e);
+                *reason = capture(field_naming_scheme)(i)
+                    .map(|_| ParserErrorReason::AdjacentCaptures)
+                    .or_else(|_| get_and(i).map(|_| ParserErrorReason::AndBeforeQuestion))
+                    .or_else(|_| {
+                        get_close_optional(i).map(|_| ParserErrorReason::UnbalancedOptional)
+                    })
+                    .ok()
+                    .or(*reason);
+                e
+            }),
+            RouteParserToken::OptionalEnd => alt((
+                get_slash,
+                exact,
+                one_of,
+                get_open_optional,
+                get_question,
+                get_hash,
+                get_end,
+            ))(i)
+            .map_err(|mut e: nom::Err<ParseError>| {
+                // Detect likely failures if the above failed to match.
+                let reason: &mut Option<ParserErrorReason> = get_reason(&mut e);
+                *reason = capture(field_naming_scheme)(i)
+                    .map(|_| ParserErrorReason::BadOptional)
+                    .or_else(|_| get_and(i).map(|_| ParserErrorReason::AndBeforeQuestion))
+                    .or_else(|_| {
+                        get_close_optional(i).map(|_| ParserErrorReason::UnbalancedOptional)
+                    })
+                    .ok()
+                    .or(*reason);
+                e
+            }),
             _ => Err(nom::Err::Failure(ParseError {
                 reason: Some(ParserErrorReason::InvalidState),
                 expected: vec![],
                 offset: 0,
             })),
         },
+        ParserState::Optional { prev_token } => {
+            // Closing the section is tried before literals, so `]]` closes it instead of being an
+            // escaped `]`.
+            let parsed = match prev_token {
+                RouteParserToken::OptionalBegin => alt((get_slash, exact, one_of))(i),
+                RouteParserToken::Separator => alt((
+                    get_close_optional,
+                    exact,
+                    one_of,
+                    capture(field_naming_scheme),
+                ))(i),
+                RouteParserToken::Exact(_) | RouteParserToken::OneOf(_) => alt((
+                    get_slash,
+                    get_close_optional,
+                    exact,
+                    one_of,
+                    capture(field_naming_scheme),
+                ))(i),
+                RouteParserToken::Capture(_) => {
+                    alt((get_slash, get_close_optional, exact, one_of))(i)
+                }
+                _ => Err(nom::Err::Failure(ParseError {
+                    reason: Some(ParserErrorReason::InvalidState),
+                    expected: vec![],
+                    offset: 0,
+                })),
+            };
+            parsed.map_err(|mut e: nom::Err<ParseError>| {
+                // Detect likely failures if the above failed to match.
+                let reason: &mut Option<ParserErrorReason> = get_reason(&mut e);
+                let adjacent_capture = match prev_token {
+                    RouteParserToken::OptionalBegin => ParserErrorReason::BadOptional,
+                    _ => ParserErrorReason::AdjacentCaptures,
+                };
+                *reason = capture(field_naming_scheme)(i)
+                    .map(|_| adjacent_capture)
+                    .or_else(|_| get_slash(i).map(|_| ParserErrorReason::DoubleSlash))
+                    .or_else(|_| {
+                        alt((
+                            get_open_optional,
+                            get_close_optional,
+                            get_question,
+                            get_and,
+                            get_hash,
+                            get_end,
+                        ))(i)
+                        .map(|_| ParserErrorReason::BadOptional)
+                    })
+                    .ok()
+                    .or(*reason);
+                e
+            })
+        }
         ParserState::FirstQuery { prev_token } => match prev_token {
             RouteParserToken::QueryBegin => {
                 query(field_naming_scheme)(i).map_err(|mut e: nom::Err<ParseError>| {
@@ -413,14 +623,13 @@ mod test {
     use crate::{parser::RouteParserToken, FieldNamingScheme, PrettyParseError};
 
     // Call all tests to parse with the Unnamed variant
-    fn parse(i: &str) -> Result<Vec<RouteParserToken>, PrettyParseError> {
+    fn parse(i: &str) -> Result<Vec<RouteParserToken<'_>>, PrettyParseError<'_>> {
         actual_parse(i, FieldNamingScheme::Unnamed)
     }
 
     mod does_parse {
         use super::*;
 
-
         #[test]
         fn empty() {
             let x = parse("").expect("Should parse");
@@ -531,6 +740,48 @@ mod test {
             assert_eq!(tokens, expected);
         }
 
+        #[test]
+        fn optional_sections() {
+            parse("/users[/{id}][/edit]").expect("should parse");
+            parse("[/en]/home").expect("should parse");
+            parse("/users[/]?page={page}").expect("should parse");
+        }
+
+        #[test]
+        fn alternatives() {
+            parse("/(posts|articles)/{slug}").expect("should parse");
+            parse("/{lang:(en|de|fr)}/home").expect("should parse");
+            parse("/users[/(edit|delete)]").expect("should parse");
+        }
+
+        #[test]
+        fn escaped_parentheses() {
+            let tokens = parse(r#"/wiki/Rust_((language))"#).expect("should parse");
+            let expected = vec![
+                RouteParserToken::Separator,
+                RouteParserToken::Exact(r#"wiki"#),
+                RouteParserToken::Separator,
+                RouteParserToken::Exact(r#"Rust_"#),
+                RouteParserToken::Exact(r#"("#),
+                RouteParserToken::Exact(r#"language"#),
+                RouteParserToken::Exact(r#")"#),
+            ];
+            assert_eq!(tokens, expected);
+        }
+
+        #[test]
+        fn escaped_square_brackets() {
+            let tokens = parse(r#"/escaped[[brackets]]"#).expect("should parse");
+            let expected = vec![
+                RouteParserToken::Separator,
+                RouteParserToken::Exact(r#"escaped"#),
+                RouteParserToken::Exact(r#"["#),
+                RouteParserToken::Exact(r#"brackets"#),
+                RouteParserToken::Exact(r#"]"#),
+            ];
+            assert_eq!(tokens, expected);
+        }
+
         #[test]
         fn escaped_close_bracket() {
             let tokens = parse(r#"/escaped}}bracket"#).expect("should parse");
@@ -548,7 +799,6 @@ mod test {
         use super::*;
         use crate::error::{ExpectedToken, ParserErrorReason};
 
-
         #[test]
         fn double_slash() {
             let x = parse("//").expect_err("Should not parse");
@@ -571,6 +821,74 @@ mod test {
             )
         }
 
+        #[test]
+        fn malformed_constraint() {
+            let x = parse("/file/{name:[a-z}").expect_err("Should not parse");
+            assert_eq!(x.error.reason, Some(ParserErrorReason::BadPattern));
+            assert_eq!(x.error.expected, vec![ExpectedToken::Constraint]);
+            let pretty = format!("{:?}", x);
+            assert!(pretty.contains("Route: /file/{name:[a-z}\n-----------------------^"));
+        }
+
+        #[test]
+        fn unknown_constraint_type() {
+            let x = parse("/post/{id:u33}").expect_err("Should not parse");
+            assert_eq!(
+                x.error.reason,
+                Some(ParserErrorReason::UnknownConstraintType)
+            );
+        }
+
+        #[test]
+        fn empty_alternative() {
+            let x = parse("/(posts|)/{slug}").expect_err("Should not parse");
+            assert_eq!(x.error.reason, Some(ParserErrorReason::BadAlternative));
+            let pretty = format!("{:?}", x);
+            assert!(pretty.contains("Route: /(posts|)/{slug}\n---------------^"));
+        }
+
+        #[test]
+        fn unclosed_optional() {
+            let x = parse("/users[/{id}").expect_err("Should not parse");
+            assert_eq!(x.error.reason, Some(ParserErrorReason::UnbalancedOptional));
+            assert_eq!(x.error.expected, vec![ExpectedToken::CloseOptional]);
+        }
+
+        #[test]
+        fn unopened_optional() {
+            let x = parse("/users/{id}]").expect_err("Should not parse");
+            assert_eq!(x.error.reason, Some(ParserErrorReason::UnbalancedOptional));
+        }
+
+        #[test]
+        fn optional_starting_with_capture() {
+            let x = parse("/users/{name}[{id}]").expect_err("Should not parse");
+            assert_eq!(x.error.reason, Some(ParserErrorReason::BadOptional));
+        }
+
+        #[test]
+        fn capture_after_optional() {
+            let x = parse("/users[/{id}]{name}").expect_err("Should not parse");
+            assert_eq!(x.error.reason, Some(ParserErrorReason::BadOptional));
+        }
+
+        #[test]
+        fn nested_optional() {
+            let x = parse("/users[/{id}[/edit]]").expect_err("Should not parse");
+            assert_eq!(x.error.reason, Some(ParserErrorReason::BadOptional));
+        }
+
+        #[test]
+        fn too_many_optionals() {
+            let x = parse("[/a][/b][/c][/d][/e][/f][/g][/h][/i]").expect_err("Should not parse");
+            assert_eq!(x.error.reason, Some(ParserErrorReason::TooManyOptionals));
+        }
+
+        #[test]
+        fn query_in_optional() {
+            let x = parse("/users[?page={page}]").expect_err("Should not parse");
+            assert_eq!(x.error.reason, Some(ParserErrorReason::BadOptional));
+        }
 
         #[test]
         fn after_end() {
@@ -775,6 +1093,32 @@ mod test {
             assert_eq!(parsed, expected);
         }
 
+        #[test]
+        fn optional_path() {
+            let parsed = parse("/lorem[/{ipsum}]!").unwrap();
+            let expected = vec![
+                RouteParserToken::Separator,
+                RouteParserToken::Exact("lorem"),
+                RouteParserToken::OptionalBegin,
+                RouteParserToken::Separator,
+                RouteParserToken::Capture(RefCaptureVariant::Named("ipsum")),
+                RouteParserToken::OptionalEnd,
+                RouteParserToken::End,
+            ];
+            assert_eq!(parsed, expected);
+        }
+
+        #[test]
+        fn alternatives_between_literals() {
+            let parsed = parse("/(lorem|ipsum)dolor").unwrap();
+            let expected = vec![
+                RouteParserToken::Separator,
+                RouteParserToken::OneOf("lorem|ipsum"),
+                RouteParserToken::Exact("dolor"),
+            ];
+            assert_eq!(parsed, expected);
+        }
+
         #[test]
         fn just_end() {
             let parsed = parse("!").unwrap();
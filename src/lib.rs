@@ -6,7 +6,7 @@
 //! that are used together to facilitate routing within the Yew framework.
 //! Among them are:
 //! * Switch - A trait/derive macro that allows specification of how enums or structs can be constructed
-//! from Routes.
+//!   from Routes.
 
 #![deny(
     missing_docs,
@@ -34,8 +34,9 @@ pub mod prelude {
 }
 
 pub mod matcher;
+pub mod openapi;
 
 pub use matcher::Captures;
 
-pub use route::Switch;
+pub use route::{BasePath, Switch, SwitchError};
 pub use yew_router_macro::Switch;
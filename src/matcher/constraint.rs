@@ -0,0 +1,193 @@
+//! Checks captured sections against the constraints given to them in the matcher string.
+
+use yew_router_route_parser::{Constraint, PatternItem, TypeConstraint};
+
+/// Checks if a captured section satisfies the constraint.
+///
+/// The section is checked as it appears in the route, before any decoding.
+pub(super) fn satisfies(constraint: &Constraint, section: &str) -> bool {
+    match constraint {
+        Constraint::Type(ty) => satisfies_type(*ty, section),
+        Constraint::Pattern(alternatives) => alternatives
+            .iter()
+            .any(|sequence| matches_sequence(sequence, section)),
+    }
+}
+
+fn satisfies_type(ty: TypeConstraint, section: &str) -> bool {
+    match ty {
+        TypeConstraint::U8 => section.parse::<u8>().is_ok(),
+        TypeConstraint::U16 => section.parse::<u16>().is_ok(),
+        TypeConstraint::U32 => section.parse::<u32>().is_ok(),
+        TypeConstraint::U64 => section.parse::<u64>().is_ok(),
+        TypeConstraint::U128 => section.parse::<u128>().is_ok(),
+        TypeConstraint::Usize => section.parse::<usize>().is_ok(),
+        TypeConstraint::I8 => section.parse::<i8>().is_ok(),
+        TypeConstraint::I16 => section.parse::<i16>().is_ok(),
+        TypeConstraint::I32 => section.parse::<i32>().is_ok(),
+        TypeConstraint::I64 => section.parse::<i64>().is_ok(),
+        TypeConstraint::I128 => section.parse::<i128>().is_ok(),
+        TypeConstraint::Isize => section.parse::<isize>().is_ok(),
+        TypeConstraint::Bool => section.parse::<bool>().is_ok(),
+        TypeConstraint::Date => is_date(section),
+        TypeConstraint::Uuid => is_uuid(section),
+    }
+}
+
+/// Checks for a `YYYY-MM-DD` date that exists in the proleptic Gregorian calendar.
+fn is_date(section: &str) -> bool {
+    let digits = |s: &str| -> Option<u32> {
+        if s.bytes().all(|b| b.is_ascii_digit()) {
+            s.parse().ok()
+        } else {
+            None
+        }
+    };
+    let parts = section.split('-').collect::<Vec<_>>();
+    let (year, month, day) = match parts.as_slice() {
+        [year, month, day] if year.len() == 4 && month.len() == 2 && day.len() == 2 => {
+            match (digits(year), digits(month), digits(day)) {
+                (Some(year), Some(month), Some(day)) => (year, month, day),
+                _ => return false,
+            }
+        }
+        _ => return false,
+    };
+    let is_leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days_in_month).contains(&day)
+}
+
+/// Checks for a hyphenated UUID, which has groups of 8, 4, 4, 4 and 12 hex digits.
+fn is_uuid(section: &str) -> bool {
+    let groups = section.split('-').map(str::len).collect::<Vec<_>>();
+    groups == [8, 4, 4, 4, 12] && section.bytes().all(|b| b == b'-' || b.is_ascii_hexdigit())
+}
+
+/// Checks if the whole section matches the sequence of pattern items.
+///
+/// Rather than backtracking, this tracks every offset that the items so far can end at,
+/// so each item only looks at each character of the section a bounded number of times,
+/// however many of the items can match the same characters.
+fn matches_sequence(items: &[PatternItem], section: &str) -> bool {
+    let mut reachable = vec![false; section.len() + 1];
+    reachable[0] = true;
+    for item in items {
+        // The offsets that can be reached by matching one more character of the class.
+        let step = |from: &[bool]| {
+            let mut to = vec![false; from.len()];
+            for (offset, c) in section.char_indices() {
+                if from[offset] && item.class.contains(c) {
+                    to[offset + c.len_utf8()] = true;
+                }
+            }
+            to
+        };
+        let (min, max) = item.repetition.bounds();
+        for _ in 0..min {
+            reachable = step(&reachable);
+        }
+        if max == usize::MAX {
+            // Offsets are visited in order, so an offset that was just reached is stepped from too.
+            for (offset, c) in section.char_indices() {
+                if reachable[offset] && item.class.contains(c) {
+                    reachable[offset + c.len_utf8()] = true;
+                }
+            }
+        } else {
+            let mut repeated = reachable.clone();
+            for _ in min..max {
+                repeated = step(&repeated);
+                for (reached, repeated) in reachable.iter_mut().zip(&repeated) {
+                    *reached |= *repeated;
+                }
+            }
+        }
+    }
+    reachable[section.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::RouteMatcher;
+
+    fn constraint(matcher: &str) -> Constraint {
+        match RouteMatcher::try_from(matcher)
+            .expect("should parse")
+            .tokens[1]
+            .clone()
+        {
+            yew_router_route_parser::MatcherToken::Capture(
+                yew_router_route_parser::CaptureVariant::Constrained { constraint, .. },
+            ) => constraint,
+            _ => panic!("should be a constrained capture"),
+        }
+    }
+
+    #[test]
+    fn integer_types() {
+        let u8_constraint = constraint("/{id:u8}");
+        assert!(satisfies(&u8_constraint, "255"));
+        assert!(!satisfies(&u8_constraint, "256"));
+        assert!(!satisfies(&u8_constraint, "new"));
+        assert!(satisfies(&constraint("/{id:i32}"), "-12"));
+    }
+
+    #[test]
+    fn dates() {
+        let date = constraint("/{d:date}");
+        assert!(satisfies(&date, "2020-02-29"));
+        assert!(!satisfies(&date, "2019-02-29"));
+        assert!(!satisfies(&date, "2020-13-01"));
+        assert!(!satisfies(&date, "2020-1-01"));
+        assert!(!satisfies(&date, "+020-01-01"));
+    }
+
+    #[test]
+    fn uuids() {
+        let uuid = constraint("/{id:uuid}");
+        assert!(satisfies(&uuid, "67e55044-10b1-426f-9247-bb680e5fe0c8"));
+        assert!(!satisfies(&uuid, "67e55044-10b1-426f-9247-bb680e5fe0cg"));
+        assert!(!satisfies(&uuid, "67e5504410b1426f9247bb680e5fe0c8"));
+    }
+
+    #[test]
+    fn patterns() {
+        let slug = constraint("/{name:[a-z0-9_-]+}");
+        assert!(satisfies(&slug, "hello_world-2"));
+        assert!(!satisfies(&slug, "Hello"));
+        assert!(!satisfies(&slug, ""));
+
+        let state = constraint("/{state:draft|published}");
+        assert!(satisfies(&state, "draft"));
+        assert!(!satisfies(&state, "drafts"));
+    }
+
+    #[test]
+    fn repetitions_backtrack() {
+        let file = constraint("/{file:[a-z.]+.rs}");
+        assert!(satisfies(&file, "lib.rs"));
+        assert!(satisfies(&file, "a.b.rs"));
+        assert!(!satisfies(&file, "lib.rs.bak"));
+
+        let optional = constraint("/{v:v?[0-9]+}");
+        assert!(satisfies(&optional, "v2"));
+        assert!(satisfies(&optional, "2"));
+        assert!(!satisfies(&optional, "vv2"));
+    }
+
+    #[test]
+    fn overlapping_repetitions_dont_backtrack() {
+        let overlapping = constraint("/{x:a*a*a*a*b}");
+        let long = "a".repeat(100_000);
+        assert!(!satisfies(&overlapping, &long));
+        assert!(satisfies(&overlapping, &format!("{}b", long)));
+    }
+}
@@ -0,0 +1,350 @@
+//! Deserializes the captures of a route into any type that implements `Deserialize`.
+
+use crate::matcher::{CaptureKind, Captures, MatchError};
+use serde::de::{
+    self, value::SeqDeserializer, DeserializeSeed, IntoDeserializer, MapAccess, Visitor,
+};
+use std::{
+    borrow::Cow,
+    collections::{hash_map, HashSet},
+    fmt,
+};
+
+/// Describes why a route couldn't be captured into a type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureError {
+    /// The route didn't match the matcher.
+    Match(MatchError),
+    /// The captures couldn't be deserialized into the type.
+    Deserialize(String),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Match(error) => error.fmt(f),
+            CaptureError::Deserialize(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl de::Error for CaptureError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        CaptureError::Deserialize(msg.to_string())
+    }
+}
+
+/// Deserializes `Captures` as a map from the names of the captures to their values.
+///
+/// Each value is parsed into whatever type is asked of it, so a capture of `"5"` can populate
+/// a `String` as well as a `u32`.
+/// Captures that are missing are left out of the map, which makes them `None` for `Option`s,
+/// and values that are joined by `&`, like repeated query parameters, populate sequences.
+/// Unnamed captures are skipped.
+#[derive(Debug)]
+pub struct CapturesDeserializer<'a> {
+    captures: Captures<'a>,
+    /// The names of repeated query captures whose values are decoded once they're split apart.
+    decoded_repeated: HashSet<&'a str>,
+}
+
+impl<'a> CapturesDeserializer<'a> {
+    /// Creates a deserializer over the captures.
+    pub fn new(captures: Captures<'a>) -> Self {
+        CapturesDeserializer {
+            captures,
+            decoded_repeated: HashSet::new(),
+        }
+    }
+
+    /// Decodes the values of the repeated query captures with these names as query values,
+    /// after splitting them apart.
+    ///
+    /// A `RouteMatcher` that decodes its captures leaves repeated query captures as they appear in the route,
+    /// since decoding them before they're split would turn an encoded `&` into a separator.
+    /// If the whole capture is asked for, rather than a sequence, it's decoded as one value.
+    pub fn decode_repeated(mut self, names: impl IntoIterator<Item = &'a str>) -> Self {
+        self.decoded_repeated.extend(names);
+        self
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for CapturesDeserializer<'a> {
+    type Error = CaptureError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_map(CapturesAccess {
+            captures: self.captures.into_iter(),
+            decoded_repeated: self.decoded_repeated,
+            value: None,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Yields the named captures as the entries of a map.
+struct CapturesAccess<'a> {
+    captures: hash_map::IntoIter<&'a str, Cow<'a, str>>,
+    decoded_repeated: HashSet<&'a str>,
+    /// The value of the last key that was yielded.
+    value: Option<CaptureDeserializer<'a>>,
+}
+
+impl<'de, 'a> MapAccess<'de> for CapturesAccess<'a> {
+    type Error = CaptureError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error> {
+        match self.captures.by_ref().find(|(key, _)| !key.is_empty()) {
+            Some((key, value)) => {
+                self.value = Some(if self.decoded_repeated.contains(key) {
+                    CaptureDeserializer::repeated(value)
+                } else {
+                    CaptureDeserializer::new(value)
+                });
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, Self::Error> {
+        let value = self
+            .value
+            .take()
+            .expect("a value is only asked for after its key");
+        seed.deserialize(value)
+    }
+}
+
+/// Deserializes a single captured section by parsing it into the type that is asked of it.
+struct CaptureDeserializer<'a> {
+    value: Cow<'a, str>,
+    /// The values of a repeated capture as they appear in the route, if they're decoded once
+    /// they're split apart, in which case `value` is the decoded capture.
+    encoded_values: Option<Cow<'a, str>>,
+}
+
+impl<'a> CaptureDeserializer<'a> {
+    fn new(value: Cow<'a, str>) -> Self {
+        CaptureDeserializer {
+            value,
+            encoded_values: None,
+        }
+    }
+
+    fn repeated(encoded_values: Cow<'a, str>) -> Self {
+        CaptureDeserializer {
+            value: Cow::Owned(CaptureKind::Query.decode(&encoded_values).into_owned()),
+            encoded_values: Some(encoded_values),
+        }
+    }
+
+    fn parse<T: std::str::FromStr>(&self, expected: &str) -> Result<T, CaptureError>
+    where
+        T::Err: fmt::Display,
+    {
+        self.value.parse().map_err(|error| {
+            CaptureError::Deserialize(format!(
+                "'{}' couldn't be parsed as {}: {}",
+                self.value, expected, error
+            ))
+        })
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $ty:ty,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                visitor.$visit(self.parse::<$ty>(stringify!($ty))?)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> de::Deserializer<'de> for CaptureDeserializer<'a> {
+    type Error = CaptureError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            Cow::Borrowed(value) => visitor.visit_str(value),
+            Cow::Owned(value) => visitor.visit_string(value),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool: bool,
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_i128 => visit_i128: i128,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_u128 => visit_u128: u128,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64,
+        deserialize_char => visit_char: char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        // A capture that is missing is never deserialized, so this one is present.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let values = match &self.encoded_values {
+            Some(encoded_values) => encoded_values
+                .split('&')
+                .map(|value| CaptureKind::Query.decode(value).into_owned())
+                .collect::<Vec<_>>(),
+            None => self.value.split('&').map(str::to_string).collect(),
+        };
+        let values = values
+            .into_iter()
+            .map(|value| CaptureDeserializer::new(Cow::Owned(value)));
+        SeqDeserializer::new(values).deserialize_seq(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        // Only unit variants can be named by a capture.
+        let variant: de::value::StringDeserializer<CaptureError> =
+            self.value.into_owned().into_deserializer();
+        visitor.visit_enum(variant)
+    }
+
+    serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit_struct tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de, 'a> IntoDeserializer<'de, CaptureError> for CaptureDeserializer<'a> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::{MatcherSettings, RouteMatcher};
+    use serde_derive::Deserialize;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Sort {
+        #[serde(rename = "new")]
+        New,
+        #[serde(rename = "top")]
+        Top,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Search {
+        #[serde(rename = "q")]
+        query: String,
+        page: u32,
+        sort: Option<Sort>,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn captures_into_struct() {
+        let matcher =
+            RouteMatcher::try_from("/search/{q}?page={page}&tag={*:tags}").expect("should parse");
+        let search: Search = matcher
+            .capture_into("/search/rust?tag=web&page=2&tag=wasm")
+            .expect("should deserialize");
+        assert_eq!(
+            search,
+            Search {
+                query: "rust".to_string(),
+                page: 2,
+                sort: None,
+                tags: vec!["web".to_string(), "wasm".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn captures_unit_variant() {
+        let matcher =
+            RouteMatcher::try_from("/search/{q}/{sort}?page={page}").expect("should parse");
+        let search: Search = matcher
+            .capture_into("/search/rust/top?page=1")
+            .expect("should deserialize");
+        assert_eq!(search.sort, Some(Sort::Top));
+        assert!(search.tags.is_empty());
+    }
+
+    #[test]
+    fn repeated_values_are_decoded_after_splitting() {
+        let matcher = RouteMatcher::new(
+            "/search/{q}?page={page}&tag={*:tags}",
+            MatcherSettings {
+                decode_captures: true,
+                ..MatcherSettings::default()
+            },
+        )
+        .expect("should parse");
+        let search: Search = matcher
+            .capture_into("/search/c%2B%2B?page=1&tag=a%26b&tag=web+dev")
+            .expect("should deserialize");
+        assert_eq!(search.query, "c++");
+        assert_eq!(search.tags, vec!["a&b".to_string(), "web dev".to_string()]);
+
+        let matcher =
+            RouteMatcher::try_from("/search/{q}?page={page}&tag={*:tags}").expect("should parse");
+        let search: Search = matcher
+            .capture_into("/search/rust?page=1&tag=a%26b")
+            .expect("should deserialize");
+        assert_eq!(search.tags, vec!["a%26b".to_string()]);
+    }
+
+    #[test]
+    fn fails_to_parse_field() {
+        let matcher = RouteMatcher::try_from("/search/{q}?page={page}").expect("should parse");
+        let error = matcher
+            .capture_into::<Search>("/search/rust?page=last")
+            .expect_err("should not deserialize");
+        assert!(matches!(error, CaptureError::Deserialize(_)));
+        let error = matcher
+            .capture_into::<Search>("/find/rust?page=1")
+            .expect_err("should not match");
+        assert!(matches!(error, CaptureError::Match(_)));
+    }
+}
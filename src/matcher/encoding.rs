@@ -0,0 +1,158 @@
+//! Percent-encoding and decoding of captured sections of a route.
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use std::borrow::Cow;
+
+/// Characters that are encoded in a single path segment or fragment capture.
+///
+/// This covers everything that would end the capture early, so the encoded value is captured whole.
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'&')
+    .add(b'*')
+    .add(b'/')
+    .add(b'<')
+    .add(b'=')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Characters that are encoded in a capture that spans multiple path segments.
+const SEGMENTS: &AsciiSet = &SEGMENT.remove(b'/');
+
+/// Characters that are encoded in a query value, following `application/x-www-form-urlencoded`.
+///
+/// Spaces are encoded separately, as `+`.
+const QUERY: &AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+    .remove(b'*')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_');
+
+/// The kind of section a capture was taken from, which determines how it is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaptureKind {
+    /// A single path segment or a section of the fragment, captured by `{}` or `{name}`.
+    Segment,
+    /// Several path segments, captured by `{*}` or `{3}`. The slashes between segments are kept.
+    Segments,
+    /// A query value, which is form-urlencoded, so `+` stands for a space.
+    Query,
+}
+
+impl CaptureKind {
+    /// Decodes a captured section.
+    ///
+    /// Malformed UTF-8 is replaced rather than rejected, and the capture is only copied if it
+    /// actually contains an escape.
+    pub fn decode(self, capture: &str) -> Cow<'_, str> {
+        match self {
+            CaptureKind::Segment | CaptureKind::Segments => {
+                percent_decode_str(capture).decode_utf8_lossy()
+            }
+            CaptureKind::Query if capture.contains('+') => {
+                let capture = capture.replace('+', " ");
+                Cow::Owned(
+                    percent_decode_str(&capture)
+                        .decode_utf8_lossy()
+                        .into_owned(),
+                )
+            }
+            CaptureKind::Query => percent_decode_str(capture).decode_utf8_lossy(),
+        }
+    }
+
+    /// Encodes a value so that it can be captured as this kind of section, and decoded back.
+    pub fn encode(self, value: &str) -> String {
+        match self {
+            CaptureKind::Segment => utf8_percent_encode(value, SEGMENT).to_string(),
+            CaptureKind::Segments => utf8_percent_encode(value, SEGMENTS).to_string(),
+            CaptureKind::Query => value
+                .split(' ')
+                .map(|part| utf8_percent_encode(part, QUERY).to_string())
+                .collect::<Vec<_>>()
+                .join("+"),
+        }
+    }
+}
+
+/// The section of the route that a matcher is currently matching against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum Section {
+    Path,
+    Query,
+    Fragment,
+}
+
+impl Section {
+    /// The section that follows a literal, which may contain the `?` or `#` that starts a new one.
+    pub(super) fn after(self, literal: &str) -> Self {
+        literal.chars().fold(self, |section, c| match (section, c) {
+            (Section::Fragment, _) | (_, '#') => Section::Fragment,
+            (_, '?') | (_, '&') => Section::Query,
+            (section, _) => section,
+        })
+    }
+
+    /// The kind of a capture in this section, given whether it can span multiple path segments.
+    pub(super) fn capture_kind(self, many: bool) -> CaptureKind {
+        match self {
+            Section::Path if many => CaptureKind::Segments,
+            Section::Path | Section::Fragment => CaptureKind::Segment,
+            Section::Query => CaptureKind::Query,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segment_round_trip() {
+        let value = "Jürgen / 100% {x}?";
+        let encoded = CaptureKind::Segment.encode(value);
+        assert_eq!(encoded, "J%C3%BCrgen%20%2F%20100%25%20%7Bx%7D%3F");
+        assert_eq!(CaptureKind::Segment.decode(&encoded), value);
+    }
+
+    #[test]
+    fn segments_keep_slashes() {
+        let encoded = CaptureKind::Segments.encode("docs/a b");
+        assert_eq!(encoded, "docs/a%20b");
+        assert_eq!(CaptureKind::Segments.decode(&encoded), "docs/a b");
+    }
+
+    #[test]
+    fn query_is_form_urlencoded() {
+        let encoded = CaptureKind::Query.encode("rust & c++");
+        assert_eq!(encoded, "rust+%26+c%2B%2B");
+        assert_eq!(CaptureKind::Query.decode(&encoded), "rust & c++");
+        assert_eq!(CaptureKind::Segment.decode("a+b"), "a+b");
+    }
+
+    #[test]
+    fn unescaped_capture_is_borrowed() {
+        assert!(matches!(
+            CaptureKind::Segment.decode("plain"),
+            Cow::Borrowed(_)
+        ));
+        assert!(matches!(
+            CaptureKind::Query.decode("plain"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn sections_follow_literals() {
+        assert_eq!(Section::Path.after("/a/path"), Section::Path);
+        assert_eq!(Section::Path.after("/a?lorem="), Section::Query);
+        assert_eq!(Section::Path.after("&lorem="), Section::Query);
+        assert_eq!(Section::Query.after("#frag?x="), Section::Fragment);
+    }
+}
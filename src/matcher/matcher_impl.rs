@@ -1,23 +1,23 @@
 use crate::matcher::{
-    util::{consume_until, next_delimiter, tag_possibly_case_sensitive},
-    Captures, MatcherSettings,
+    constraint::satisfies,
+    encoding::{CaptureKind, Section},
+    util::{alternative, consume_until, next_delimiter, tag_possibly_case_sensitive},
+    Captures, MatchError, MatcherSettings,
 };
 use log::trace;
 use nom::{
     bytes::complete::{is_not, tag},
-    combinator::map,
     error::ErrorKind,
     sequence::terminated,
     IResult,
 };
-use std::{iter::Peekable, slice::Iter};
-use yew_router_route_parser::{CaptureVariant, MatcherToken};
+use std::{borrow::Cow, collections::HashSet};
+use yew_router_route_parser::{CaptureVariant, MatcherToken, QueryParam, QueryValue};
 
 /// Allows abstracting over capturing into a HashMap (Captures) or a Vec.
 trait CaptureCollection<'a> {
     fn new2() -> Self;
-    fn insert2(&mut self, key: &'a str, value: String);
-    fn extend2(&mut self, other: Self);
+    fn insert2(&mut self, key: &'a str, value: Cow<'a, str>);
 }
 
 impl<'a> CaptureCollection<'a> for Captures<'a> {
@@ -25,178 +25,606 @@ impl<'a> CaptureCollection<'a> for Captures<'a> {
         Captures::new()
     }
 
-    fn insert2(&mut self, key: &'a str, value: String) {
+    fn insert2(&mut self, key: &'a str, value: Cow<'a, str>) {
         self.insert(key, value);
     }
-
-    fn extend2(&mut self, other: Self) {
-        self.extend(other)
-    }
 }
 
-impl<'a> CaptureCollection<'a> for Vec<String> {
+impl<'a> CaptureCollection<'a> for Vec<Cow<'a, str>> {
     fn new2() -> Self {
         Vec::new()
     }
 
-    fn insert2(&mut self, _key: &'a str, value: String) {
+    fn insert2(&mut self, _key: &'a str, value: Cow<'a, str>) {
         self.push(value)
     }
-
-    fn extend2(&mut self, other: Self) {
-        self.extend(other)
-    }
 }
 
 #[allow(clippy::trivially_copy_pass_by_ref)]
 pub(super) fn match_into_map<'a, 'b: 'a>(
     tokens: &'b [MatcherToken],
     settings: &'b MatcherSettings,
-) -> impl Fn(&'a str) -> IResult<&'a str, Captures<'b>> {
-    move |i: &str| matcher_impl(tokens, *settings, i)
+    section: Section,
+) -> impl Fn(&'a str) -> IResult<&'a str, Captures<'a>> {
+    move |i: &str| matcher_impl(tokens, *settings, section, i)
 }
 
 #[allow(clippy::trivially_copy_pass_by_ref)]
 pub(super) fn match_into_vec<'a, 'b: 'a>(
     tokens: &'b [MatcherToken],
     settings: &'b MatcherSettings,
-) -> impl Fn(&'a str) -> IResult<&'a str, Vec<String>> {
-    move |i: &str| matcher_impl(tokens, *settings, i)
+    section: Section,
+) -> impl Fn(&'a str) -> IResult<&'a str, Vec<Cow<'a, str>>> {
+    move |i: &str| matcher_impl(tokens, *settings, section, i)
+}
+
+/// Where a route stopped matching the tokens of a matcher.
+struct TokenFailure<'a, 'b> {
+    /// The index of the token, or of the optional section containing it, in the matcher.
+    index: usize,
+    /// The token that couldn't be matched.
+    token: &'b MatcherToken,
+    /// The part of the route that the token was matched against.
+    input: &'a str,
+    error: nom::Err<(&'a str, ErrorKind)>,
+}
+
+impl<'a, 'b> TokenFailure<'a, 'b> {
+    /// Describes the failure, given the route that the matcher started matching.
+    fn into_match_error(self, route: &str) -> MatchError {
+        let expected = match self.token {
+            MatcherToken::Exact(literal) => Some(literal.clone()),
+            MatcherToken::OneOf(alternatives) => Some(format!("({})", alternatives.join("|"))),
+            MatcherToken::Capture(_)
+            | MatcherToken::End
+            | MatcherToken::Optional(_)
+            | MatcherToken::Query { .. } => None,
+        };
+        MatchError {
+            token: self.index,
+            offset: route.len() - self.input.len(),
+            expected,
+        }
+    }
 }
 
-fn matcher_impl<'a, 'b: 'a, CAP: CaptureCollection<'b>>(
+/// Matches the tokens against the route.
+///
+/// The section is the part of the route that the first token starts in,
+/// which determines how captures are decoded when `settings.decode_captures` is set.
+fn matcher_impl<'a, 'b: 'a, CAP: CaptureCollection<'a>>(
     tokens: &'b [MatcherToken],
     settings: MatcherSettings,
-    mut i: &'a str,
+    section: Section,
+    i: &'a str,
 ) -> IResult<&'a str, CAP> {
+    match_or_fail(tokens, settings, section, i).map_err(|failure| failure.error)
+}
+
+/// Matches the tokens against the route, describing where the match failed if it did.
+pub(super) fn try_match_into_map<'a, 'b: 'a>(
+    tokens: &'b [MatcherToken],
+    settings: MatcherSettings,
+    i: &'a str,
+) -> Result<(&'a str, Captures<'a>), MatchError> {
+    match_or_fail(tokens, settings, Section::Path, i).map_err(|failure| failure.into_match_error(i))
+}
+
+/// Matches the tokens against the route in every way of including or skipping the optional
+/// sections, in the order `match_or_fail` tries them, yielding the captures of each way that matches.
+pub(super) fn match_expansions_into_map<'a, 'b: 'a>(
+    tokens: &'b [MatcherToken],
+    settings: MatcherSettings,
+    section: Section,
+    i: &'a str,
+) -> impl Iterator<Item = (&'a str, Captures<'a>)> + 'a {
+    let mut matches = vec![];
+    Search::new(tokens, settings, |rest, captures| {
+        matches.push((rest, collect_captures(captures)));
+        false
+    })
+    .run(section, i);
+    matches.into_iter()
+}
+
+/// Matches the tokens against the route.
+///
+/// Optional sections are included if the route can be matched with them,
+/// and the earlier sections are preferred when only some of them can be included.
+/// If none of the ways of including them match, the failure that got furthest into the route is kept.
+fn match_or_fail<'a, 'b: 'a, CAP: CaptureCollection<'a>>(
+    tokens: &'b [MatcherToken],
+    settings: MatcherSettings,
+    section: Section,
+    i: &'a str,
+) -> Result<(&'a str, CAP), TokenFailure<'a, 'b>> {
     trace!("Attempting to match path: {:?} using: {:?}", i, tokens);
 
-    let mut iter = tokens.iter().peekable();
+    let mut matched = None;
+    let furthest = Search::new(tokens, settings, |rest, captures| {
+        matched = Some((rest, collect_captures(captures)));
+        true
+    })
+    .run(section, i);
+    match matched {
+        Some(matched) => Ok(matched),
+        None => Err(furthest.expect("a route that doesn't match fails at some token")),
+    }
+}
 
-    let mut captures: CAP = CAP::new2();
+fn collect_captures<'a, CAP: CaptureCollection<'a>>(captures: &[(&'a str, Cow<'a, str>)]) -> CAP {
+    let mut collection = CAP::new2();
+    for (name, captured) in captures {
+        collection.insert2(name, captured.clone());
+    }
+    collection
+}
 
-    while let Some(token) = iter.next() {
-        i = match token {
-            MatcherToken::Exact(literal) => {
-                trace!("Matching '{}' against literal: '{}'", i, literal);
-                tag_possibly_case_sensitive(literal.as_str(), !settings.case_insensitive)(i)?.0
+impl<'a> CaptureCollection<'a> for Vec<(&'a str, Cow<'a, str>)> {
+    fn new2() -> Self {
+        Vec::new()
+    }
+
+    fn insert2(&mut self, key: &'a str, value: Cow<'a, str>) {
+        self.push((key, value))
+    }
+}
+
+/// A token of a matcher, with the tokens of its optional sections laid out in line.
+#[derive(Debug, Clone, Copy)]
+enum Step<'b> {
+    /// A token that came from the token, or the optional section, at `index` in the matcher.
+    Token {
+        index: usize,
+        token: &'b MatcherToken,
+    },
+    /// The start of an optional section, whose tokens are the steps up to `end`.
+    Optional { end: usize },
+}
+
+fn steps(tokens: &[MatcherToken]) -> Vec<Step<'_>> {
+    let mut steps = vec![];
+    for (index, token) in tokens.iter().enumerate() {
+        if let MatcherToken::Optional(section) = token {
+            let end = steps.len() + 1 + section.len();
+            steps.push(Step::Optional { end });
+            steps.extend(section.iter().map(|token| Step::Token { index, token }));
+        } else {
+            steps.push(Step::Token { index, token });
+        }
+    }
+    steps
+}
+
+/// Matches the steps of a matcher against a route a token at a time,
+/// backtracking to the last optional section when a token doesn't match.
+///
+/// Optional sections are included before they're skipped, so the ways of matching the route are
+/// found in the order they're preferred. The captures of each are passed to `on_match`,
+/// which returns whether to stop searching.
+///
+/// Whether the rest of a matcher can match only depends on the step it's at and the part of the
+/// route that's left, so those that can't are remembered and not tried again.
+/// This keeps matching polynomial in the length of the route, however many optional sections there are.
+struct Search<'a, 'b, F> {
+    steps: Vec<Step<'b>>,
+    settings: MatcherSettings,
+    on_match: F,
+    /// The captures made on the way to the current step.
+    captures: Vec<(&'a str, Cow<'a, str>)>,
+    /// The number of ways found to match the route so far.
+    matches: usize,
+    done: bool,
+    /// The steps, with the length of the route that's left, that don't lead to a match.
+    dead_ends: HashSet<(usize, usize, Section)>,
+    /// The failure that got furthest into the route.
+    furthest: Option<TokenFailure<'a, 'b>>,
+}
+
+impl<'a, 'b: 'a, F> Search<'a, 'b, F>
+where
+    F: FnMut(&'a str, &[(&'a str, Cow<'a, str>)]) -> bool,
+{
+    fn new(tokens: &'b [MatcherToken], settings: MatcherSettings, on_match: F) -> Self {
+        Search {
+            steps: steps(tokens),
+            settings,
+            on_match,
+            captures: vec![],
+            matches: 0,
+            done: false,
+            dead_ends: HashSet::new(),
+            furthest: None,
+        }
+    }
+
+    /// Searches for ways to match the route, returning the failure that got furthest into it.
+    fn run(mut self, section: Section, i: &'a str) -> Option<TokenFailure<'a, 'b>> {
+        self.search(0, section, i);
+        self.furthest
+    }
+
+    fn search(&mut self, step: usize, section: Section, i: &'a str) {
+        if self.done || self.dead_ends.contains(&(step, i.len(), section)) {
+            return;
+        }
+        let matches = self.matches;
+        let captures = self.captures.len();
+
+        match self.steps.get(step).copied() {
+            None => {
+                trace!("Route Matched");
+                self.matches += 1;
+                self.done = (self.on_match)(i, &self.captures);
             }
-            MatcherToken::Capture(capture) => match &capture {
-                CaptureVariant::Named(name) => capture_named(i, &mut iter, &name, &mut captures)?,
-                CaptureVariant::ManyNamed(name) => {
-                    capture_many_named(i, &mut iter, &name, &mut captures)?
-                }
-                CaptureVariant::NumberedNamed { sections, name } => {
-                    capture_numbered_named(i, &mut iter, Some((&name, &mut captures)), *sections)?
+            Some(Step::Optional { end }) => {
+                self.search(step + 1, section, i);
+                self.search(end, section, i);
+            }
+            Some(Step::Token { index, token }) => match token {
+                MatcherToken::Capture(capture) => {
+                    self.search_capture(index, token, capture, step, section, i)
                 }
-                CaptureVariant::Unnamed => capture_named(i, &mut iter, "", &mut captures)?,
-                CaptureVariant::ManyUnnamed => capture_many_named(i, &mut iter, "", &mut captures)?,
-                CaptureVariant::NumberedUnnamed { sections } => {
-                    capture_numbered_named(i, &mut iter, Some(("", &mut captures)), *sections)?
+                _ => {
+                    let mut section = section;
+                    match match_token(token, self.settings, &mut section, &mut self.captures, i) {
+                        Ok(ii) => self.search(step + 1, section, ii),
+                        Err(error) => self.fail(index, token, i, error),
+                    }
+                    self.captures.truncate(captures);
                 }
             },
-            MatcherToken::End => {
-                if !i.is_empty() {
-                    // this is approximately correct, but ultimately doesn't matter
-                    return Err(nom::Err::Failure((i, ErrorKind::Eof)));
-                } else {
-                    i
+        }
+
+        if !self.done && self.matches == matches {
+            self.dead_ends.insert((step, i.len(), section));
+        }
+    }
+
+    /// Matches a capture, trying each of the tokens that can end it.
+    ///
+    /// The capture ends where the token after it starts,
+    /// which depends on whether the optional sections after it are included.
+    fn search_capture(
+        &mut self,
+        index: usize,
+        token: &'b MatcherToken,
+        capture: &'b CaptureVariant,
+        step: usize,
+        section: Section,
+        i: &'a str,
+    ) {
+        let captures = self.captures.len();
+        for next in self.next_steps(step + 1) {
+            let delimiter = match self.steps.get(next) {
+                Some(Step::Token { token, .. }) => Some(*token),
+                _ => None,
+            };
+            match match_capture(capture, delimiter, self.settings, section, i) {
+                Ok((ii, (name, captured))) => {
+                    self.captures.push((name, captured));
+                    self.search(next, section, ii);
+                    self.captures.truncate(captures);
                 }
+                Err(error) => self.fail(index, token, i, error),
             }
+        }
+    }
+
+    /// The steps that can directly follow the one before `step`, from the most optional sections
+    /// included to the fewest. These are the first steps of the optional sections at `step`,
+    /// and the first step after them.
+    fn next_steps(&self, mut step: usize) -> Vec<usize> {
+        let mut next = vec![];
+        while let Some(Step::Optional { end }) = self.steps.get(step) {
+            next.push(step + 1);
+            step = *end;
+        }
+        next.push(step);
+        next
+    }
+
+    /// Keeps the failure if it got further into the route than the others.
+    fn fail(
+        &mut self,
+        index: usize,
+        token: &'b MatcherToken,
+        input: &'a str,
+        error: nom::Err<(&'a str, ErrorKind)>,
+    ) {
+        let is_further = match &self.furthest {
+            Some(furthest) => input.len() < furthest.input.len(),
+            None => true,
         };
+        if is_further {
+            self.furthest = Some(TokenFailure {
+                index,
+                token,
+                input,
+                error,
+            });
+        }
     }
-    trace!("Route Matched");
+}
 
-    Ok((i, captures))
+/// Matches a capture that is followed by the delimiter, returning the rest of the route,
+/// the name of the capture and what it captured.
+fn match_capture<'a, 'b: 'a>(
+    capture: &'b CaptureVariant,
+    delimiter: Option<&'b MatcherToken>,
+    settings: MatcherSettings,
+    section: Section,
+    i: &'a str,
+) -> IResult<&'a str, (&'a str, Cow<'a, str>)> {
+    let (name, many, (ii, captured)) = match capture {
+        CaptureVariant::Named(name) => {
+            (name.as_str(), false, capture_named(i, delimiter, settings)?)
+        }
+        CaptureVariant::ManyNamed(name) => (
+            name.as_str(),
+            true,
+            capture_many_named(i, delimiter, settings)?,
+        ),
+        CaptureVariant::NumberedNamed { sections, name } => (
+            name.as_str(),
+            true,
+            capture_numbered_named(i, delimiter, *sections, settings)?,
+        ),
+        CaptureVariant::Constrained { name, constraint } => {
+            let (ii, captured) = capture_named(i, delimiter, settings)?;
+            if !satisfies(constraint, captured) {
+                trace!("'{}' does not satisfy: {:?}", captured, constraint);
+                return Err(nom::Err::Error((i, ErrorKind::Verify)));
+            }
+            (name.as_str(), false, (ii, captured))
+        }
+        CaptureVariant::Unnamed => ("", false, capture_named(i, delimiter, settings)?),
+        CaptureVariant::ManyUnnamed => ("", true, capture_many_named(i, delimiter, settings)?),
+        CaptureVariant::NumberedUnnamed { sections } => (
+            "",
+            true,
+            capture_numbered_named(i, delimiter, *sections, settings)?,
+        ),
+    };
+    let captured = process_capture(captured, section.capture_kind(many), settings);
+    Ok((ii, (name, captured)))
 }
 
-fn capture_named<'a, 'b: 'a, CAP: CaptureCollection<'b>>(
+/// Matches a single token other than a capture, returning the rest of the route.
+fn match_token<'a, 'b: 'a, CAP: CaptureCollection<'a>>(
+    token: &'b MatcherToken,
+    settings: MatcherSettings,
+    section: &mut Section,
+    captures: &mut CAP,
     i: &'a str,
-    iter: &mut Peekable<Iter<MatcherToken>>,
-    capture_key: &'b str,
-    matches: &mut CAP,
 ) -> Result<&'a str, nom::Err<(&'a str, ErrorKind)>> {
-    log::trace!("Matching Named ({})", capture_key);
-    if let Some(_peaked_next_token) = iter.peek() {
-        let delimiter = next_delimiter(iter);
-        let (ii, captured) = consume_until(delimiter)(i)?;
-        matches.insert2(capture_key, captured);
-        Ok(ii)
+    let rest = match token {
+        MatcherToken::Exact(literal) => {
+            trace!("Matching '{}' against literal: '{}'", i, literal);
+            *section = section.after(literal);
+            tag_possibly_case_sensitive(literal.as_str(), !settings.case_insensitive)(i)?.0
+        }
+        MatcherToken::OneOf(alternatives) => {
+            trace!("Matching '{}' against alternatives: {:?}", i, alternatives);
+            alternative(alternatives, !settings.case_insensitive)(i)?.0
+        }
+        MatcherToken::End => {
+            if !i.is_empty() {
+                // this is approximately correct, but ultimately doesn't matter
+                return Err(nom::Err::Failure((i, ErrorKind::Eof)));
+            } else {
+                i
+            }
+        }
+        MatcherToken::Query { params, strict } => {
+            trace!("Matching '{}' against query: {:?}", i, params);
+            *section = Section::Query;
+            let (ii, query) = split_query(i);
+            match_query(query, params, *strict, settings, captures)
+                .map_err(|kind| nom::Err::Error((i, kind)))?;
+            ii
+        }
+        MatcherToken::Capture(_) | MatcherToken::Optional(_) => {
+            unreachable!("captures and optional sections are matched by the search")
+        }
+    };
+    Ok(rest)
+}
+
+/// Splits the query from the start of the route, returning the rest of the route and the query.
+///
+/// The query runs from after the `?` up to the fragment, and is empty if the route has none.
+fn split_query(i: &str) -> (&str, &str) {
+    if i.starts_with('?') {
+        let end = i.find('#').unwrap_or(i.len());
+        (&i[end..], &i[1..end])
     } else {
-        let (ii, captured) = map(valid_capture_characters, String::from)(i)?;
-        matches.insert2(capture_key, captured.to_string());
-        Ok(ii)
+        (i, &i[..0])
     }
 }
 
-fn capture_many_named<'a, 'b, CAP: CaptureCollection<'b>>(
+/// Matches the parameters against the `key=value` pairs of a query, regardless of their order.
+///
+/// Each parameter is matched against the first pair with its key,
+/// except for repeated captures (`{*:name}`), which take the values of every pair with the key.
+/// Those values are joined by `&`, and nothing is captured if there are none.
+/// They're joined as they appear in the query, without being decoded,
+/// so a value that contains an encoded `&` can still be told apart from the others.
+/// Optional parameters that are missing are skipped, without capturing anything.
+/// Pairs with keys that aren't parameters are ignored, unless the query is strict.
+fn match_query<'a, 'b: 'a, CAP: CaptureCollection<'a>>(
+    query: &'a str,
+    params: &'b [QueryParam],
+    strict: bool,
+    settings: MatcherSettings,
+    captures: &mut CAP,
+) -> Result<(), ErrorKind> {
+    let literals_match = |expected: &str, actual: &str| {
+        if settings.case_insensitive {
+            expected.to_lowercase() == actual.to_lowercase()
+        } else {
+            expected == actual
+        }
+    };
+    let pairs = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.find('=') {
+            Some(index) => (&pair[..index], &pair[index + 1..]),
+            None => (pair, &pair[pair.len()..]),
+        })
+        .collect::<Vec<_>>();
+
+    if strict {
+        let unknown = pairs
+            .iter()
+            .find(|(key, _)| !params.iter().any(|param| literals_match(&param.key, key)));
+        if let Some((key, _)) = unknown {
+            trace!("Query has a parameter that isn't allowed: '{}'", key);
+            return Err(ErrorKind::Verify);
+        }
+    }
+
+    for param in params {
+        let mut values = pairs
+            .iter()
+            .filter(|(key, _)| literals_match(&param.key, key))
+            .map(|(_, value)| *value);
+        let repeated = match &param.value {
+            QueryValue::Capture(CaptureVariant::ManyNamed(name)) => Some(name.as_str()),
+            QueryValue::Capture(CaptureVariant::ManyUnnamed) => Some(""),
+            QueryValue::Capture(_) | QueryValue::Exact(_) => None,
+        };
+        if let Some(name) = repeated {
+            let settings = MatcherSettings {
+                decode_captures: false,
+                ..settings
+            };
+            let values = values
+                .map(|value| process_capture(value, CaptureKind::Query, settings))
+                .collect::<Vec<_>>();
+            match values.len() {
+                0 => {}
+                1 => captures.insert2(name, values.into_iter().next().unwrap()),
+                _ => captures.insert2(name, Cow::Owned(values.join("&"))),
+            }
+            continue;
+        }
+
+        let value = match values.next() {
+            Some(value) => value,
+            None if param.optional => continue,
+            None => return Err(ErrorKind::Tag),
+        };
+        match &param.value {
+            QueryValue::Exact(literal) => {
+                if !literals_match(literal, value) {
+                    return Err(ErrorKind::Tag);
+                }
+            }
+            QueryValue::Capture(capture) => {
+                if value.is_empty() {
+                    return Err(ErrorKind::IsNot);
+                }
+                let name = match capture {
+                    CaptureVariant::Named(name)
+                    | CaptureVariant::ManyNamed(name)
+                    | CaptureVariant::NumberedNamed { name, .. } => name.as_str(),
+                    CaptureVariant::Constrained { name, constraint } => {
+                        if !satisfies(constraint, value) {
+                            trace!("'{}' does not satisfy: {:?}", value, constraint);
+                            return Err(ErrorKind::Verify);
+                        }
+                        name.as_str()
+                    }
+                    CaptureVariant::Unnamed
+                    | CaptureVariant::ManyUnnamed
+                    | CaptureVariant::NumberedUnnamed { .. } => "",
+                };
+                captures.insert2(name, process_capture(value, CaptureKind::Query, settings));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decodes and lowercases a captured section, if the settings call for it.
+///
+/// The section is only copied if that changes it.
+fn process_capture(captured: &str, kind: CaptureKind, settings: MatcherSettings) -> Cow<'_, str> {
+    let captured = if settings.decode_captures {
+        kind.decode(captured)
+    } else {
+        Cow::Borrowed(captured)
+    };
+    if settings.lowercase_captures && captured.chars().any(char::is_uppercase) {
+        Cow::Owned(captured.to_lowercase())
+    } else {
+        captured
+    }
+}
+
+fn capture_named<'a>(
     i: &'a str,
-    iter: &mut Peekable<Iter<MatcherToken>>,
-    capture_key: &'b str,
-    matches: &mut CAP,
-) -> Result<&'a str, nom::Err<(&'a str, ErrorKind)>> {
-    log::trace!("Matching ManyUnnamed ({})", capture_key);
-    if let Some(_peaked_next_token) = iter.peek() {
-        let delimiter = next_delimiter(iter);
-        let (ii, captured) = consume_until(delimiter)(i)?;
-        matches.insert2(&capture_key, captured);
-        Ok(ii)
+    delimiter: Option<&MatcherToken>,
+    settings: MatcherSettings,
+) -> IResult<&'a str, &'a str> {
+    log::trace!("Matching Named");
+    if let Some(delimiter) = delimiter {
+        consume_until(next_delimiter(delimiter, settings))(i)
+    } else {
+        valid_capture_characters(i)
+    }
+}
+
+fn capture_many_named<'a>(
+    i: &'a str,
+    delimiter: Option<&MatcherToken>,
+    settings: MatcherSettings,
+) -> IResult<&'a str, &'a str> {
+    log::trace!("Matching Many");
+    if let Some(delimiter) = delimiter {
+        consume_until(next_delimiter(delimiter, settings))(i)
     } else if i.is_empty() {
         // If the route string is empty, return an empty value.
-        matches.insert2(&capture_key, "".to_string());
-        Ok(i) // Match even if nothing is left
+        Ok((i, i)) // Match even if nothing is left
     } else {
-        let (ii, c) = map(valid_many_capture_characters, String::from)(i)?;
-        matches.insert2(&capture_key, c.to_string());
-        Ok(ii)
+        valid_many_capture_characters(i)
     }
 }
 
-fn capture_numbered_named<'a, 'b, CAP: CaptureCollection<'b>>(
+fn capture_numbered_named<'a>(
     mut i: &'a str,
-    iter: &mut Peekable<Iter<MatcherToken>>,
-    name_and_captures: Option<(&'b str, &mut CAP)>,
+    delimiter: Option<&MatcherToken>,
     mut sections: usize,
-) -> Result<&'a str, nom::Err<(&'a str, ErrorKind)>> {
-    log::trace!("Matching NumberedNamed ({})", sections);
-    let mut captured = "".to_string();
+    settings: MatcherSettings,
+) -> IResult<&'a str, &'a str> {
+    log::trace!("Matching Numbered ({})", sections);
+    // The sections are contiguous, so the capture can be sliced out once they have been consumed.
+    let start = i;
 
-    if let Some(_peaked_next_token) = iter.peek() {
+    if let Some(delimiter) = delimiter {
         while sections > 0 {
             if sections > 1 {
-                let (ii, c) = terminated(valid_capture_characters, tag("/"))(i)?;
-                i = ii;
-                captured += c;
-                captured += "/";
+                i = terminated(valid_capture_characters, tag("/"))(i)?.0;
             } else {
-                let delimiter = next_delimiter(iter);
-                let (ii, c) = consume_until(delimiter)(i)?;
-                i = ii;
-                captured += &c;
+                i = consume_until(next_delimiter(delimiter, settings))(i)?.0;
             }
             sections -= 1;
         }
     } else {
         while sections > 0 {
             if sections > 1 {
-                let (ii, c) = terminated(valid_capture_characters, tag("/"))(i)?;
-                i = ii;
-                captured += c;
-                captured += "/";
+                i = terminated(valid_capture_characters, tag("/"))(i)?.0;
             } else {
                 // Don't consume the next character on the last section
-                let (ii, c) = valid_capture_characters(i)?;
-                i = ii;
-                captured += c;
+                i = valid_capture_characters(i)?.0;
             }
             sections -= 1;
         }
     }
 
-    if let Some((name, captures)) = name_and_captures {
-        captures.insert2(&name, captured);
-    }
-    Ok(i)
+    Ok((i, &start[..start.len() - i.len()]))
 }
 
 /// Characters that don't interfere with parsing logic for capturing characters
@@ -226,8 +654,13 @@ mod integration_test {
             FieldNamingScheme::Unnamed,
         )
         .expect("Should parse");
-        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/a/path?lorem=ipsum")
-            .expect("should match");
+        matcher_impl::<Captures>(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "/a/path?lorem=ipsum",
+        )
+        .expect("should match");
     }
 
     #[test]
@@ -237,8 +670,13 @@ mod integration_test {
             FieldNamingScheme::Unnamed,
         )
         .expect("Should parse");
-        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/a/path/?lorem=ipsum")
-            .expect("should match");
+        matcher_impl::<Captures>(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "/a/path/?lorem=ipsum",
+        )
+        .expect("should match");
     }
 
     #[test]
@@ -248,8 +686,13 @@ mod integration_test {
             FieldNamingScheme::Unnamed,
         )
         .expect("Should parse");
-        matcher_impl::<Captures>(&x, MatcherSettings::default(), "?lorem=ipsum")
-            .expect("should match");
+        matcher_impl::<Captures>(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "?lorem=ipsum",
+        )
+        .expect("should match");
     }
 
     #[test]
@@ -259,8 +702,13 @@ mod integration_test {
             FieldNamingScheme::Unnamed,
         )
         .expect("Should parse");
-        let (_, matches) = matcher_impl::<Captures>(&x, MatcherSettings::default(), "?lorem=ipsum")
-            .expect("should match");
+        let (_, matches) = matcher_impl::<Captures>(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "?lorem=ipsum",
+        )
+        .expect("should match");
         assert_eq!(matches["ipsum"], "ipsum".to_string())
     }
 
@@ -271,10 +719,14 @@ mod integration_test {
             FieldNamingScheme::Unnamed,
         )
         .expect("Should parse");
-        let matches: Captures =
-            matcher_impl(&x, MatcherSettings::default(), "/anything/other/thing")
-                .expect("should match")
-                .1;
+        let matches: Captures = matcher_impl(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "/anything/other/thing",
+        )
+        .expect("should match")
+        .1;
         assert_eq!(matches["cap"], "anything/other".to_string())
     }
 
@@ -285,10 +737,14 @@ mod integration_test {
             FieldNamingScheme::Unnamed,
         )
         .expect("Should parse");
-        let matches: Captures =
-            matcher_impl(&x, MatcherSettings::default(), "/anything/thing/thing")
-                .expect("should match")
-                .1;
+        let matches: Captures = matcher_impl(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "/anything/thing/thing",
+        )
+        .expect("should match")
+        .1;
         assert_eq!(matches["cap"], "anything".to_string())
     }
 
@@ -299,10 +755,14 @@ mod integration_test {
             FieldNamingScheme::Unnamed,
         )
         .expect("Should parse");
-        let matches: Captures =
-            matcher_impl(&x, MatcherSettings::default(), "/anything/thing/thing")
-                .expect("should match")
-                .1;
+        let matches: Captures = matcher_impl(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "/anything/thing/thing",
+        )
+        .expect("should match")
+        .1;
         assert_eq!(matches["cap"], "anything".to_string())
     }
 
@@ -313,7 +773,8 @@ mod integration_test {
             FieldNamingScheme::Unnamed,
         )
         .expect("Should parse");
-        matcher_impl::<Captures>(&x, MatcherSettings::default(), "#test").expect("should match");
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), Section::Path, "#test")
+            .expect("should match");
     }
 
     #[test]
@@ -323,8 +784,13 @@ mod integration_test {
             FieldNamingScheme::Unnamed,
         )
         .expect("Should parse");
-        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/a/path/#test")
-            .expect("should match");
+        matcher_impl::<Captures>(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "/a/path/#test",
+        )
+        .expect("should match");
     }
 
     #[test]
@@ -334,8 +800,13 @@ mod integration_test {
             FieldNamingScheme::Unnamed,
         )
         .expect("Should parse");
-        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/a/path#test")
-            .expect("should match");
+        matcher_impl::<Captures>(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "/a/path#test",
+        )
+        .expect("should match");
     }
 
     #[test]
@@ -345,8 +816,13 @@ mod integration_test {
             FieldNamingScheme::Unnamed,
         )
         .expect("Should parse");
-        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/a/path?query=thing#test")
-            .expect("should match");
+        matcher_impl::<Captures>(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "/a/path?query=thing#test",
+        )
+        .expect("should match");
     }
 
     #[test]
@@ -356,8 +832,13 @@ mod integration_test {
             FieldNamingScheme::Unnamed,
         )
         .expect("Should parse");
-        matcher_impl::<Captures>(&x, MatcherSettings::default(), "/a/path?query=thing#test")
-            .expect("should match");
+        matcher_impl::<Captures>(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "/a/path?query=thing#test",
+        )
+        .expect("should match");
     }
 
     #[test]
@@ -367,8 +848,13 @@ mod integration_test {
             FieldNamingScheme::Unnamed,
         )
         .expect("Should parse");
-        matcher_impl::<Captures>(&x, MatcherSettings::default(), "literally_anything")
-            .expect("should match");
+        matcher_impl::<Captures>(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "literally_anything",
+        )
+        .expect("should match");
     }
 
     #[test]
@@ -382,7 +868,47 @@ mod integration_test {
             case_insensitive: true,
             ..Default::default()
         };
-        matcher_impl::<Captures>(&x, settings, "/HeLLo").expect("should match");
+        matcher_impl::<Captures>(&x, settings, Section::Path, "/HeLLo").expect("should match");
+    }
+
+    #[test]
+    fn case_insensitive_alternatives() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/(posts|articles)",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let settings = MatcherSettings {
+            case_insensitive: true,
+            ..Default::default()
+        };
+        matcher_impl::<Captures>(&x, settings, Section::Path, "/Articles").expect("should match");
+        matcher_impl::<Captures>(&x, MatcherSettings::default(), Section::Path, "/Articles")
+            .expect_err("should not match");
+    }
+
+    #[test]
+    fn constraint_rejects_section() {
+        let x = yew_router_route_parser::parse_str_and_optimize_tokens(
+            "/post/{id:u32}/edit",
+            FieldNamingScheme::Unnamed,
+        )
+        .expect("Should parse");
+        let (_, matches) = matcher_impl::<Captures>(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "/post/12/edit",
+        )
+        .expect("should match");
+        assert_eq!(matches["id"], "12".to_string());
+        matcher_impl::<Captures>(
+            &x,
+            MatcherSettings::default(),
+            Section::Path,
+            "/post/new/edit",
+        )
+        .expect_err("should not match");
     }
 
     #[test]
@@ -393,7 +919,7 @@ mod integration_test {
         )
         .expect("Should parse");
 
-        matcher_impl::<Captures>(&x, Default::default(), "/lorem/ipsum")
+        matcher_impl::<Captures>(&x, Default::default(), Section::Path, "/lorem/ipsum")
             .expect_err("should not match");
     }
 }
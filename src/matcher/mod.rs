@@ -1,14 +1,30 @@
 //! Module for matching route strings based on tokens generated from the yew_router_route_parser
 //! crate.
 
+mod constraint;
+mod deserialize;
+mod encoding;
 mod matcher_impl;
+mod route_set;
+mod serialize;
+mod trie;
 mod util;
 
+use self::encoding::Section;
 use nom::IResult;
-use std::collections::HashSet;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{borrow::Cow, collections::HashSet, fmt};
 use yew_router_route_parser::{parse_str_and_optimize_tokens, PrettyParseError};
 
-pub use yew_router_route_parser::{CaptureVariant, Captures, MatcherToken};
+pub use self::deserialize::{CaptureError, CapturesDeserializer};
+pub use self::encoding::CaptureKind;
+pub use self::route_set::RouteSet;
+pub use self::serialize::FillError;
+pub use self::trie::{Candidate, MatcherTrie};
+pub use yew_router_route_parser::{
+    CaptureVariant, Captures, CharClass, Constraint, MatcherToken, PatternItem, QueryParam,
+    QueryValue, Repetition, TypeConstraint,
+};
 
 /// Attempts to match routes, transform the route to Component props and render that Component.
 #[derive(Debug, PartialEq, Clone)]
@@ -20,29 +36,60 @@ pub struct RouteMatcher {
 }
 
 /// Settings used for the matcher.
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
 pub struct MatcherSettings {
     /// All literal matches do not care about case.
     pub case_insensitive: bool,
+    /// Percent-decode captured sections.
+    ///
+    /// Path and fragment captures are decoded as path segments,
+    /// while query captures are decoded as form values, where `+` stands for a space.
+    /// Repeated query captures (`{*:name}`) are left as they are, since their values are joined by `&`:
+    /// split them apart first, then decode each value with `CaptureKind::Query`.
+    pub decode_captures: bool,
+    /// Lowercase captured sections, so they don't depend on the case of the route.
+    ///
+    /// This is independent of `case_insensitive`, which only affects literals.
+    pub lowercase_captures: bool,
 }
 
-impl Default for MatcherSettings {
-    fn default() -> Self {
-        MatcherSettings {
-            case_insensitive: false,
+/// Describes where a route stopped matching a `RouteMatcher`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchError {
+    /// The index of the token in `RouteMatcher::tokens` that couldn't be matched.
+    /// Tokens within an optional section are indexed by the section.
+    pub token: usize,
+    /// The offset of the part of the route that the token was matched against.
+    pub offset: usize,
+    /// The literal that was expected, if the token that couldn't be matched is one.
+    pub expected: Option<String>,
+}
+
+impl fmt::Display for MatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "token {} didn't match at offset {}",
+            self.token, self.offset
+        )?;
+        if let Some(expected) = &self.expected {
+            write!(f, ", expected '{}'", expected)?;
         }
+        Ok(())
     }
 }
 
+impl std::error::Error for MatchError {}
+
 impl RouteMatcher {
     /// Attempt to create a RouteMatcher from a "matcher string".
-    pub fn try_from(i: &str) -> Result<Self, PrettyParseError> {
+    pub fn try_from(i: &str) -> Result<Self, PrettyParseError<'_>> {
         let settings = MatcherSettings::default();
         Self::new(i, settings)
     }
 
     /// Creates a new Matcher with settings.
-    pub fn new(i: &str, settings: MatcherSettings) -> Result<Self, PrettyParseError> {
+    pub fn new(i: &str, settings: MatcherSettings) -> Result<Self, PrettyParseError<'_>> {
         Ok(RouteMatcher {
             tokens: parse_str_and_optimize_tokens(
                 i,
@@ -57,37 +104,117 @@ impl RouteMatcher {
         &'b self,
         i: &'a str,
     ) -> IResult<&'a str, Captures<'a>> {
-        matcher_impl::match_into_map(&self.tokens, &self.settings)(i)
+        matcher_impl::match_into_map(&self.tokens, &self.settings, Section::Path)(i)
+    }
+
+    /// Match a route string, collecting the results into a map,
+    /// or describing where the route stopped matching if it didn't.
+    ///
+    /// If the matcher has optional sections, the failure is from whichever way of including them
+    /// got furthest into the route.
+    pub fn try_capture_route_into_map<'a, 'b: 'a>(
+        &'b self,
+        i: &'a str,
+    ) -> Result<(&'a str, Captures<'a>), MatchError> {
+        matcher_impl::try_match_into_map(&self.tokens, self.settings, i)
+    }
+
+    /// Match a route string in every way of including or skipping its optional sections,
+    /// collecting the results of each way that matches into a map.
+    ///
+    /// The ways are produced in the order they're preferred, so the first is the one that
+    /// `capture_route_into_map` produces. This lets a caller move on to the next way if it can't
+    /// use the captures of one, like a field that can't be converted.
+    pub fn capture_route_into_maps<'a, 'b: 'a>(
+        &'b self,
+        i: &'a str,
+    ) -> impl Iterator<Item = (&'a str, Captures<'a>)> + 'a {
+        matcher_impl::match_expansions_into_map(&self.tokens, self.settings, Section::Path, i)
+    }
+
+    /// Match a route string, deserializing the captures into any type that implements `Deserialize`.
+    ///
+    /// The captures are deserialized as a map of their names to their values,
+    /// as described by `CapturesDeserializer`.
+    /// When the matcher decodes its captures, the values of repeated query captures are decoded
+    /// after they're split apart, so a value can contain an encoded `&`.
+    pub fn capture_into<T: DeserializeOwned>(&self, i: &str) -> Result<T, CaptureError> {
+        let (_, captures) = self
+            .try_capture_route_into_map(i)
+            .map_err(CaptureError::Match)?;
+        let mut deserializer = CapturesDeserializer::new(captures);
+        if self.settings.decode_captures {
+            deserializer = deserializer.decode_repeated(self.repeated_query_capture_names());
+        }
+        T::deserialize(deserializer)
+    }
+
+    /// The names of the captures that take the values of every query parameter with their key.
+    fn repeated_query_capture_names(&self) -> impl Iterator<Item = &str> {
+        self.tokens
+            .iter()
+            .filter_map(|token| match token {
+                MatcherToken::Query { params, .. } => Some(params),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|param| match &param.value {
+                QueryValue::Capture(CaptureVariant::ManyNamed(name)) => Some(name.as_str()),
+                _ => None,
+            })
+    }
+
+    /// Produce a route from the matcher by filling each named capture with the field of the same
+    /// name from any type that implements `Serialize`.
+    ///
+    /// Values are percent-encoded for the section of the route they're written to,
+    /// and sequences fill captures that span many segments or repeated query parameters.
+    /// Optional sections are written only if all of their captures have values,
+    /// and `OneOf` tokens are filled with their first alternative.
+    pub fn fill<T: Serialize + ?Sized>(&self, value: &T) -> Result<String, FillError> {
+        serialize::fill(&self.tokens, value)
     }
 
     /// Match a route string, collecting the results into a vector.
+    ///
+    /// Captures within optional sections that were skipped are left out,
+    /// so the position of a capture can depend on which sections matched.
     pub fn capture_route_into_vec<'a, 'b: 'a>(
         &'b self,
         i: &'a str,
-    ) -> IResult<&'a str, Vec<String>> {
-        matcher_impl::match_into_vec(&self.tokens, &self.settings)(i)
+    ) -> IResult<&'a str, Vec<Cow<'a, str>>> {
+        matcher_impl::match_into_vec(&self.tokens, &self.settings, Section::Path)(i)
     }
 
     /// Gets a set of all names that will be captured.
     /// This is useful in determining if a given struct will be able to be populated by a given path
     /// matcher before being given a concrete path to match.
     pub fn capture_names(&self) -> HashSet<&str> {
+        fn capture_name(capture: &CaptureVariant) -> Option<&str> {
+            match capture {
+                CaptureVariant::ManyNamed(name)
+                | CaptureVariant::Named(name)
+                | CaptureVariant::NumberedNamed { name, .. }
+                | CaptureVariant::Constrained { name, .. } => Some(name),
+                CaptureVariant::Unnamed
+                | CaptureVariant::ManyUnnamed
+                | CaptureVariant::NumberedUnnamed { .. } => None,
+            }
+        }
         fn capture_names_impl(tokens: &[MatcherToken]) -> HashSet<&str> {
             tokens
                 .iter()
                 .fold(HashSet::new(), |mut acc: HashSet<&str>, token| {
                     match token {
-                        MatcherToken::Exact(_) | MatcherToken::End => {}
-                        MatcherToken::Capture(capture) => match &capture {
-                            CaptureVariant::ManyNamed(name)
-                            | CaptureVariant::Named(name)
-                            | CaptureVariant::NumberedNamed { name, .. } => {
-                                acc.insert(&name);
-                            }
-                            CaptureVariant::Unnamed
-                            | CaptureVariant::ManyUnnamed
-                            | CaptureVariant::NumberedUnnamed { .. } => {}
-                        },
+                        MatcherToken::Exact(_) | MatcherToken::OneOf(_) | MatcherToken::End => {}
+                        MatcherToken::Capture(capture) => acc.extend(capture_name(capture)),
+                        MatcherToken::Optional(section) => acc.extend(capture_names_impl(section)),
+                        MatcherToken::Query { params, .. } => {
+                            acc.extend(params.iter().filter_map(|param| match &param.value {
+                                QueryValue::Capture(capture) => capture_name(capture),
+                                QueryValue::Exact(_) => None,
+                            }))
+                        }
                     }
                     acc
                 })
@@ -164,6 +291,15 @@ mod tests {
         assert_eq!(matches["lorem"], "ipsum".to_string())
     }
 
+    #[test]
+    fn capture_before_end_captures_rest() {
+        let path_matcher = RouteMatcher::try_from("/users/{id}!").expect("should build");
+        let (_, matches) = path_matcher
+            .capture_route_into_map("/users/5")
+            .expect("should parse");
+        assert_eq!(matches["id"], "5".to_string())
+    }
+
     #[test]
     fn match_with_trailing_match_many() {
         let tokens = vec![
@@ -260,6 +396,297 @@ mod tests {
             .expect("should parse");
     }
 
+    #[test]
+    fn captures_borrow_from_route() {
+        let path_matcher = RouteMatcher::try_from("/{first}/{*:rest}").expect("should parse");
+        let route = "/lorem/ipsum/dolor";
+        let (_, matches) = path_matcher
+            .capture_route_into_vec(route)
+            .expect("should parse");
+        assert_eq!(matches, vec!["lorem", "ipsum/dolor"]);
+        assert_eq!(matches[0].as_ptr(), route[1..].as_ptr());
+        assert_eq!(matches[1].as_ptr(), route[7..].as_ptr());
+    }
+
+    #[test]
+    fn decode_captures() {
+        let settings = MatcherSettings {
+            decode_captures: true,
+            ..Default::default()
+        };
+        let path_matcher =
+            RouteMatcher::new("/{name}/{*:rest}?q={query}#{frag}", settings).expect("should parse");
+        let (_, matches) = path_matcher
+            .capture_route_into_map("/J%C3%BCrgen/a+b/c%20d?q=rust+%26+c%2B%2B#x%20y")
+            .expect("should parse");
+        assert_eq!(matches["name"], "Jürgen");
+        assert_eq!(matches["rest"], "a+b/c d");
+        assert_eq!(matches["query"], "rust & c++");
+        assert_eq!(matches["frag"], "x y");
+    }
+
+    #[test]
+    fn lowercase_captures() {
+        let settings = MatcherSettings {
+            lowercase_captures: true,
+            ..Default::default()
+        };
+        let path_matcher =
+            RouteMatcher::new("/users/{name}?tab={tab}", settings).expect("should parse");
+        let (_, matches) = path_matcher
+            .capture_route_into_map("/users/Alice?tab=Posts")
+            .expect("should parse");
+        assert_eq!(matches["name"], "alice");
+        assert_eq!(matches["tab"], "posts");
+        path_matcher
+            .capture_route_into_map("/Users/Alice")
+            .expect_err("literals should still be case sensitive");
+    }
+
+    #[test]
+    fn captures_are_not_decoded_by_default() {
+        let path_matcher = RouteMatcher::try_from("/{name}").expect("should parse");
+        let (_, matches) = path_matcher
+            .capture_route_into_vec("/J%C3%BCrgen")
+            .expect("should parse");
+        assert_eq!(matches, vec!["J%C3%BCrgen"]);
+    }
+
+    #[test]
+    fn optional_sections() {
+        let path_matcher =
+            RouteMatcher::try_from("/users[/{id:u32}][/edit]!").expect("should parse");
+        let captures = |route| {
+            path_matcher
+                .capture_route_into_map(route)
+                .map(|(_, matches)| matches)
+        };
+        assert_eq!(captures("/users/5/edit").expect("should parse")["id"], "5");
+        assert_eq!(captures("/users/5").expect("should parse")["id"], "5");
+        assert!(!captures("/users/edit")
+            .expect("should parse")
+            .contains_key("id"));
+        assert!(captures("/users").expect("should parse").is_empty());
+        captures("/users/edit/5").expect_err("should not parse");
+    }
+
+    #[test]
+    fn every_matching_expansion_in_order() {
+        let path_matcher = RouteMatcher::try_from("/users[/{id}][/edit]!").expect("should parse");
+        let ids: Vec<Option<String>> = path_matcher
+            .capture_route_into_maps("/users/edit")
+            .map(|(_, matches)| matches.get("id").map(ToString::to_string))
+            .collect();
+        assert_eq!(ids, vec![Some("edit".to_string()), None]);
+        assert_eq!(path_matcher.capture_route_into_maps("/posts").count(), 0);
+    }
+
+    #[test]
+    fn skipped_optional_sections_are_left_out_of_vec() {
+        let path_matcher = RouteMatcher::try_from("[/{lang:en|fr}]/{page}").expect("should parse");
+        let (_, matches) = path_matcher
+            .capture_route_into_vec("/about")
+            .expect("should parse");
+        assert_eq!(matches, vec!["about"]);
+        let (_, matches) = path_matcher
+            .capture_route_into_vec("/fr/about")
+            .expect("should parse");
+        assert_eq!(matches, vec!["fr", "about"]);
+    }
+
+    #[test]
+    fn many_optional_sections_fail_quickly() {
+        // There are 2^64 ways of including or skipping the sections, so trying each would never finish.
+        let mut tokens = vec![];
+        for _ in 0..64 {
+            tokens.extend(vec![
+                RouteParserToken::OptionalBegin,
+                RouteParserToken::Separator,
+                RouteParserToken::Capture(RefCaptureVariant::Unnamed),
+                RouteParserToken::OptionalEnd,
+            ]);
+        }
+        tokens.extend(vec![
+            RouteParserToken::Separator,
+            RouteParserToken::Exact("end"),
+            RouteParserToken::End,
+        ]);
+        let path_matcher = RouteMatcher::from(tokens);
+        let route = format!("{}/nope", "/x".repeat(64));
+
+        let start = std::time::Instant::now();
+        path_matcher
+            .capture_route_into_map(&route)
+            .expect_err("should not parse");
+        assert_eq!(path_matcher.capture_route_into_maps(&route).count(), 0);
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn alternatives() {
+        let path_matcher =
+            RouteMatcher::try_from("/(posts|articles)/{slug}").expect("should parse");
+        let (_, matches) = path_matcher
+            .capture_route_into_map("/articles/hello")
+            .expect("should parse");
+        assert_eq!(matches["slug"], "hello");
+        path_matcher
+            .capture_route_into_map("/post/hello")
+            .expect_err("should not parse");
+    }
+
+    #[test]
+    fn delimiters_follow_case_sensitivity() {
+        let settings = MatcherSettings {
+            case_insensitive: true,
+            ..MatcherSettings::default()
+        };
+        let path_matcher =
+            RouteMatcher::new("/{user}(.Posts|.Articles)", settings).expect("should parse");
+        let (_, matches) = path_matcher
+            .capture_route_into_map("/alice.posts")
+            .expect("should parse");
+        assert_eq!(matches["user"], "alice");
+
+        let path_matcher = RouteMatcher::new("/{file}.JSON", settings).expect("should parse");
+        let (_, matches) = path_matcher
+            .capture_route_into_map("/data.json")
+            .expect("should parse");
+        assert_eq!(matches["file"], "data");
+    }
+
+    #[test]
+    fn alternatives_as_constraint() {
+        let path_matcher = RouteMatcher::try_from("/{lang:(en|de|fr)}/home").expect("should parse");
+        let (_, matches) = path_matcher
+            .capture_route_into_map("/de/home")
+            .expect("should parse");
+        assert_eq!(matches["lang"], "de");
+        path_matcher
+            .capture_route_into_map("/es/home")
+            .expect_err("should not parse");
+    }
+
+    #[test]
+    fn query_in_any_order() {
+        let path_matcher =
+            RouteMatcher::try_from("/search?q={q}&page={page}").expect("should parse");
+        let (_, matches) = path_matcher
+            .capture_route_into_map("/search?page=2&utm_source=x&q=rust")
+            .expect("should parse");
+        assert_eq!(matches["q"], "rust");
+        assert_eq!(matches["page"], "2");
+        path_matcher
+            .capture_route_into_map("/search?q=rust")
+            .expect_err("should not parse");
+    }
+
+    #[test]
+    fn strict_query_rejects_unknown_parameters() {
+        let path_matcher = RouteMatcher::try_from("/search?q={q}!").expect("should parse");
+        path_matcher
+            .capture_route_into_map("/search?q=rust")
+            .expect("should parse");
+        path_matcher
+            .capture_route_into_map("/search?q=rust&page=2")
+            .expect_err("should not parse");
+    }
+
+    #[test]
+    fn optional_query_parameter() {
+        let mut path_matcher =
+            RouteMatcher::try_from("/list?page={page}&sort={sort}").expect("should parse");
+        if let MatcherToken::Query { params, .. } = &mut path_matcher.tokens[1] {
+            params[1].optional = true;
+        }
+        let (_, matches) = path_matcher
+            .capture_route_into_map("/list?page=2")
+            .expect("should parse");
+        assert_eq!(matches["page"], "2");
+        assert!(!matches.contains_key("sort"));
+        path_matcher
+            .capture_route_into_map("/list?sort=new")
+            .expect_err("should not parse");
+    }
+
+    #[test]
+    fn repeated_query_capture() {
+        let path_matcher = RouteMatcher::try_from("/filter?tag={*:tags}").expect("should parse");
+        let (_, matches) = path_matcher
+            .capture_route_into_map("/filter?tag=a&page=2&tag=b")
+            .expect("should parse");
+        assert_eq!(matches["tags"], "a&b");
+        let (_, matches) = path_matcher
+            .capture_route_into_map("/filter?tag=a")
+            .expect("should parse");
+        assert!(matches!(matches["tags"], Cow::Borrowed("a")));
+        let (_, matches) = path_matcher
+            .capture_route_into_map("/filter")
+            .expect("should parse");
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn repeated_query_capture_is_split_before_decoding() {
+        let settings = MatcherSettings {
+            decode_captures: true,
+            ..MatcherSettings::default()
+        };
+        let path_matcher =
+            RouteMatcher::new("/filter?tag={*:tags}", settings).expect("should parse");
+        let (_, matches) = path_matcher
+            .capture_route_into_map("/filter?tag=a%26b&tag=c+d")
+            .expect("should parse");
+        assert_eq!(matches["tags"], "a%26b&c+d");
+        let tags: Vec<Cow<str>> = matches["tags"]
+            .split('&')
+            .map(|tag| CaptureKind::Query.decode(tag))
+            .collect();
+        assert_eq!(tags, vec!["a&b", "c d"]);
+    }
+
+    #[test]
+    fn query_captures_are_in_matcher_order() {
+        let path_matcher = RouteMatcher::try_from("/{}?a={}&b={}#{}").expect("should parse");
+        let (_, matches) = path_matcher
+            .capture_route_into_vec("/x?b=2&a=1#frag")
+            .expect("should parse");
+        assert_eq!(matches, vec!["x", "1", "2", "frag"]);
+    }
+
+    #[test]
+    fn match_error_describes_mismatched_literal() {
+        let path_matcher = RouteMatcher::try_from("/{lang}/(posts|pages)").expect("should parse");
+        let error = path_matcher
+            .try_capture_route_into_map("/en/notes")
+            .expect_err("should not parse");
+        assert_eq!(
+            error,
+            MatchError {
+                token: 3,
+                offset: 4,
+                expected: Some("(posts|pages)".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn match_error_is_from_furthest_expansion() {
+        let path_matcher =
+            RouteMatcher::try_from("/users[/{id:u32}][/edit]!").expect("should parse");
+        let error = path_matcher
+            .try_capture_route_into_map("/users/x")
+            .expect_err("should not parse");
+        assert_eq!(
+            error,
+            MatchError {
+                token: 1,
+                offset: 7,
+                expected: None,
+            }
+        );
+    }
+
     #[test]
     fn match_many_named() {
         let tokens = vec![
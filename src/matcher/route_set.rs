@@ -0,0 +1,142 @@
+//! A collection of routes that are only known at runtime, each associated with a value.
+
+use crate::matcher::{Captures, MatcherSettings, MatcherTrie, RouteMatcher};
+use yew_router_route_parser::PrettyParseError;
+
+/// Associates values with routes that are parsed from strings at runtime,
+/// such as page definitions that are loaded from elsewhere.
+///
+/// Routes are tried in the order they were inserted, and the first one that matches a path
+/// provides its value, in the same way as the variants of a derived `Switch`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RouteSet<T> {
+    matchers: MatcherTrie,
+    values: Vec<T>,
+    settings: MatcherSettings,
+}
+
+impl<T> Default for RouteSet<T> {
+    fn default() -> Self {
+        RouteSet {
+            matchers: MatcherTrie::new(vec![]),
+            values: vec![],
+            settings: MatcherSettings::default(),
+        }
+    }
+}
+
+impl<T> RouteSet<T> {
+    /// Creates an empty set of routes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty set of routes, whose matchers are created with the settings.
+    pub fn with_settings(settings: MatcherSettings) -> Self {
+        RouteSet {
+            settings,
+            ..Self::default()
+        }
+    }
+
+    /// Parses the "matcher string" with `RouteMatcher::new` and the settings of the set,
+    /// and adds it after the routes that are already in the set.
+    ///
+    /// The value is dropped if the string can't be parsed.
+    pub fn insert<'a>(&mut self, matcher: &'a str, value: T) -> Result<(), PrettyParseError<'a>> {
+        self.matchers
+            .push(RouteMatcher::new(matcher, self.settings)?);
+        self.values.push(value);
+        Ok(())
+    }
+
+    /// Gets the value of the first route that matches the path, along with its captures.
+    pub fn match_path<'a, 'b: 'a>(&'b self, path: &'a str) -> Option<(&'b T, Captures<'a>)> {
+        self.matchers
+            .candidates(path)
+            .into_iter()
+            .find_map(|candidate| {
+                let (_, captures) = candidate.capture_route_into_map().ok()?;
+                Some((&self.values[candidate.index()], captures))
+            })
+    }
+
+    /// The matchers of the routes, in the order they were inserted.
+    pub fn matchers(&self) -> &[RouteMatcher] {
+        self.matchers.matchers()
+    }
+
+    /// The number of routes in the set.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Checks if the set has no routes.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_inserted_route_matches() {
+        let mut routes = RouteSet::new();
+        routes.insert("/docs/intro", "intro").expect("should parse");
+        routes
+            .insert("/docs/{*:path}", "docs")
+            .expect("should parse");
+        routes.insert("/{page}", "page").expect("should parse");
+
+        let (value, captures) = routes.match_path("/docs/intro").expect("should match");
+        assert_eq!(*value, "intro");
+        assert!(captures.is_empty());
+
+        let (value, captures) = routes
+            .match_path("/docs/guide/routing")
+            .expect("should match");
+        assert_eq!(*value, "docs");
+        assert_eq!(captures["path"], "guide/routing");
+
+        let (value, captures) = routes.match_path("/about").expect("should match");
+        assert_eq!(*value, "page");
+        assert_eq!(captures["page"], "about");
+        assert!(routes.match_path("").is_none());
+    }
+
+    #[test]
+    fn routes_are_matched_with_the_settings_of_the_set() {
+        let settings = MatcherSettings {
+            case_insensitive: true,
+            decode_captures: true,
+            ..Default::default()
+        };
+        let mut routes = RouteSet::with_settings(settings);
+        routes
+            .insert("/users/{name}", "user")
+            .expect("should parse");
+        assert_eq!(routes.matchers()[0].settings, settings);
+
+        let (value, captures) = routes
+            .match_path("/USERS/J%C3%BCrgen")
+            .expect("should match");
+        assert_eq!(*value, "user");
+        assert_eq!(captures["name"], "Jürgen");
+
+        let mut routes = RouteSet::new();
+        routes
+            .insert("/users/{name}", "user")
+            .expect("should parse");
+        assert!(routes.match_path("/USERS/J%C3%BCrgen").is_none());
+    }
+
+    #[test]
+    fn insert_reports_parse_error() {
+        let mut routes = RouteSet::new();
+        let error = routes.insert("/docs/{", 1).expect_err("should not parse");
+        assert_eq!(error.input, "/docs/{");
+        assert!(routes.is_empty());
+    }
+}
@@ -0,0 +1,735 @@
+//! Fills the captures of a matcher from any type that implements `Serialize`.
+
+use crate::matcher::{
+    constraint::satisfies,
+    encoding::{CaptureKind, Section},
+    CaptureVariant, MatcherToken, QueryParam, QueryValue,
+};
+use serde::ser::{self, Impossible, Serialize};
+use std::{collections::HashMap, fmt};
+
+/// Describes why a matcher couldn't be filled from a value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FillError {
+    /// The value has no field for a capture that the route requires.
+    MissingCapture(String),
+    /// The route requires a capture that isn't named, so no field can fill it.
+    UnnamedCapture,
+    /// The value of a field doesn't satisfy the constraint on its capture.
+    Constraint {
+        /// The name of the capture.
+        capture: String,
+        /// The value, as it would appear in the route.
+        value: String,
+    },
+    /// The value couldn't be serialized into the captures.
+    Serialize(String),
+}
+
+impl fmt::Display for FillError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FillError::MissingCapture(name) => write!(f, "no value for the capture '{}'", name),
+            FillError::UnnamedCapture => f.write_str("an unnamed capture can't be filled"),
+            FillError::Constraint { capture, value } => write!(
+                f,
+                "'{}' doesn't satisfy the constraint of the capture '{}'",
+                value, capture
+            ),
+            FillError::Serialize(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for FillError {}
+
+impl ser::Error for FillError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        FillError::Serialize(msg.to_string())
+    }
+}
+
+/// The values of the fields of a serialized type, by the names of the fields.
+///
+/// Fields without a value, like `None`s and empty strings, are left out.
+type Fields = HashMap<String, Vec<String>>;
+
+/// Serializes the value into its fields, then renders the tokens with them.
+pub(super) fn fill<T: Serialize + ?Sized>(
+    tokens: &[MatcherToken],
+    value: &T,
+) -> Result<String, FillError> {
+    let fields = value.serialize(FieldsSerializer)?;
+    let mut route = String::new();
+    fill_tokens(tokens, &fields, Section::Path, &mut route)?;
+    Ok(route)
+}
+
+/// Writes the tokens to the route, returning the section of the route that follows them.
+fn fill_tokens(
+    tokens: &[MatcherToken],
+    fields: &Fields,
+    mut section: Section,
+    route: &mut String,
+) -> Result<Section, FillError> {
+    for token in tokens {
+        match token {
+            MatcherToken::Exact(literal) => {
+                route.push_str(literal);
+                section = section.after(literal);
+            }
+            MatcherToken::OneOf(alternatives) => {
+                let literal = alternatives.first().map(String::as_str).unwrap_or("");
+                route.push_str(literal);
+                section = section.after(literal);
+            }
+            MatcherToken::Capture(capture) => {
+                let kind = match capture {
+                    CaptureVariant::ManyNamed(_) | CaptureVariant::ManyUnnamed => {
+                        section.capture_kind(true)
+                    }
+                    CaptureVariant::NumberedNamed { sections, .. }
+                    | CaptureVariant::NumberedUnnamed { sections } => {
+                        section.capture_kind(*sections > 1)
+                    }
+                    CaptureVariant::Named(_)
+                    | CaptureVariant::Constrained { .. }
+                    | CaptureVariant::Unnamed => section.capture_kind(false),
+                };
+                match fill_capture(capture, kind, fields)? {
+                    Some(value) => route.push_str(&value),
+                    None => return Err(missing(capture)),
+                }
+            }
+            MatcherToken::Optional(optional) => {
+                if !has_captures(optional) {
+                    continue;
+                }
+                let mut filled = String::new();
+                match fill_tokens(optional, fields, section, &mut filled) {
+                    Ok(after) => {
+                        route.push_str(&filled);
+                        section = after;
+                    }
+                    Err(FillError::MissingCapture(_)) | Err(FillError::UnnamedCapture) => {}
+                    Err(error) => return Err(error),
+                }
+            }
+            MatcherToken::Query { params, .. } => {
+                let pairs = fill_query(params, fields)?;
+                if !pairs.is_empty() {
+                    route.push('?');
+                    route.push_str(&pairs.join("&"));
+                }
+                section = Section::Query;
+            }
+            MatcherToken::End => {}
+        }
+    }
+    Ok(section)
+}
+
+/// Encodes the value of the capture, if the fields have one.
+///
+/// A capture that spans many segments of the path joins the values of a sequence with `/`,
+/// but every other capture expects a single value.
+fn fill_capture(
+    capture: &CaptureVariant,
+    kind: CaptureKind,
+    fields: &Fields,
+) -> Result<Option<String>, FillError> {
+    let name = match capture_name(capture) {
+        Some(name) => name,
+        None => return Err(FillError::UnnamedCapture),
+    };
+    let values = match fields.get(name) {
+        Some(values) => values,
+        None => return Ok(None),
+    };
+    let value = match (kind, values.as_slice()) {
+        (_, [value]) => kind.encode(value),
+        (CaptureKind::Segments, values) => values
+            .iter()
+            .map(|value| kind.encode(value))
+            .collect::<Vec<_>>()
+            .join("/"),
+        _ => {
+            return Err(FillError::Serialize(format!(
+                "the capture '{}' takes a single value, but was given {}",
+                name,
+                values.len()
+            )))
+        }
+    };
+    check_constraint(capture, name, &value)?;
+    Ok(Some(value))
+}
+
+/// Renders the parameters of a query as `key=value` pairs.
+///
+/// Parameters without a value are left out if they are optional or can be repeated.
+fn fill_query(params: &[QueryParam], fields: &Fields) -> Result<Vec<String>, FillError> {
+    let mut pairs = vec![];
+    for param in params {
+        let key = CaptureKind::Query.encode(&param.key);
+        let capture = match &param.value {
+            QueryValue::Exact(literal) => {
+                pairs.push(format!("{}={}", key, CaptureKind::Query.encode(literal)));
+                continue;
+            }
+            QueryValue::Capture(capture) => capture,
+        };
+        let repeated = matches!(
+            capture,
+            CaptureVariant::ManyNamed(_) | CaptureVariant::ManyUnnamed
+        );
+        let name = match capture_name(capture) {
+            Some(name) => name,
+            None if param.optional || repeated => continue,
+            None => return Err(FillError::UnnamedCapture),
+        };
+        let values = match fields.get(name) {
+            Some(values) if repeated || values.len() == 1 => values,
+            Some(values) => {
+                return Err(FillError::Serialize(format!(
+                    "the query parameter '{}' takes a single value, but was given {}",
+                    param.key,
+                    values.len()
+                )))
+            }
+            None if param.optional || repeated => continue,
+            None => return Err(FillError::MissingCapture(name.to_string())),
+        };
+        for value in values {
+            let value = CaptureKind::Query.encode(value);
+            check_constraint(capture, name, &value)?;
+            pairs.push(format!("{}={}", key, value));
+        }
+    }
+    Ok(pairs)
+}
+
+/// Checks the encoded value against the constraint of the capture, if it has one.
+fn check_constraint(capture: &CaptureVariant, name: &str, value: &str) -> Result<(), FillError> {
+    match capture {
+        CaptureVariant::Constrained { constraint, .. } if !satisfies(constraint, value) => {
+            Err(FillError::Constraint {
+                capture: name.to_string(),
+                value: value.to_string(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn capture_name(capture: &CaptureVariant) -> Option<&str> {
+    match capture {
+        CaptureVariant::Named(name)
+        | CaptureVariant::ManyNamed(name)
+        | CaptureVariant::NumberedNamed { name, .. }
+        | CaptureVariant::Constrained { name, .. } => Some(name),
+        CaptureVariant::Unnamed
+        | CaptureVariant::ManyUnnamed
+        | CaptureVariant::NumberedUnnamed { .. } => None,
+    }
+}
+
+fn missing(capture: &CaptureVariant) -> FillError {
+    match capture_name(capture) {
+        Some(name) => FillError::MissingCapture(name.to_string()),
+        None => FillError::UnnamedCapture,
+    }
+}
+
+/// Checks if an optional section has any captures that would need to be filled.
+fn has_captures(tokens: &[MatcherToken]) -> bool {
+    tokens.iter().any(|token| match token {
+        MatcherToken::Capture(_) => true,
+        MatcherToken::Optional(section) => has_captures(section),
+        MatcherToken::Query { params, .. } => params
+            .iter()
+            .any(|param| matches!(param.value, QueryValue::Capture(_))),
+        MatcherToken::Exact(_) | MatcherToken::OneOf(_) | MatcherToken::End => false,
+    })
+}
+
+fn unsupported(kind: &str) -> FillError {
+    FillError::Serialize(format!("{} can't fill the captures of a route", kind))
+}
+
+/// Serializes a struct or a map into its fields.
+struct FieldsSerializer;
+
+macro_rules! reject {
+    ($kind:expr => $($method:ident: $ty:ty,)*) => {
+        $(
+            fn $method(self, _value: $ty) -> Result<Self::Ok, Self::Error> {
+                Err(unsupported($kind))
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for FieldsSerializer {
+    type Ok = Fields;
+    type Error = FillError;
+    type SerializeSeq = Impossible<Fields, FillError>;
+    type SerializeTuple = Impossible<Fields, FillError>;
+    type SerializeTupleStruct = Impossible<Fields, FillError>;
+    type SerializeTupleVariant = Impossible<Fields, FillError>;
+    type SerializeMap = FieldsCollector;
+    type SerializeStruct = FieldsCollector;
+    type SerializeStructVariant = Impossible<Fields, FillError>;
+
+    reject! { "a single value" =>
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+        serialize_str: &str,
+        serialize_bytes: &[u8],
+    }
+
+    fn serialize_none(self) -> Result<Fields, FillError> {
+        Ok(Fields::new())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Fields, FillError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Fields, FillError> {
+        Ok(Fields::new())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Fields, FillError> {
+        Ok(Fields::new())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Fields, FillError> {
+        Err(unsupported("an enum"))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Fields, FillError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Fields, FillError> {
+        Err(unsupported("an enum"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, FillError> {
+        Err(unsupported("a sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, FillError> {
+        Err(unsupported("a tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, FillError> {
+        Err(unsupported("a tuple struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, FillError> {
+        Err(unsupported("an enum"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<FieldsCollector, FillError> {
+        Ok(FieldsCollector::default())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<FieldsCollector, FillError> {
+        Ok(FieldsCollector::default())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, FillError> {
+        Err(unsupported("an enum"))
+    }
+}
+
+/// Collects the fields of a struct or the entries of a map.
+#[derive(Default)]
+struct FieldsCollector {
+    fields: Fields,
+    /// The last key of a map that was serialized.
+    key: Option<String>,
+}
+
+impl FieldsCollector {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), FillError> {
+        let values = value
+            .serialize(ValueSerializer)?
+            .into_iter()
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>();
+        if !values.is_empty() {
+            self.fields.insert(key, values);
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for FieldsCollector {
+    type Ok = Fields;
+    type Error = FillError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), FillError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Fields, FillError> {
+        Ok(self.fields)
+    }
+}
+
+impl ser::SerializeMap for FieldsCollector {
+    type Ok = Fields;
+    type Error = FillError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), FillError> {
+        let mut keys = key.serialize(ValueSerializer)?;
+        if keys.len() != 1 {
+            return Err(unsupported("a key that isn't a single value"));
+        }
+        self.key = keys.pop();
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FillError> {
+        let key = self
+            .key
+            .take()
+            .expect("a value is only serialized after its key");
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Fields, FillError> {
+        Ok(self.fields)
+    }
+}
+
+/// Serializes the value of a field into the values of its capture.
+///
+/// Sequences produce a value for each of their elements, and missing values produce none.
+struct ValueSerializer;
+
+macro_rules! serialize_displayed {
+    ($($method:ident: $ty:ty,)*) => {
+        $(
+            fn $method(self, value: $ty) -> Result<Self::Ok, Self::Error> {
+                Ok(vec![value.to_string()])
+            }
+        )*
+    };
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Vec<String>;
+    type Error = FillError;
+    type SerializeSeq = ValuesCollector;
+    type SerializeTuple = ValuesCollector;
+    type SerializeTupleStruct = ValuesCollector;
+    type SerializeTupleVariant = Impossible<Vec<String>, FillError>;
+    type SerializeMap = Impossible<Vec<String>, FillError>;
+    type SerializeStruct = Impossible<Vec<String>, FillError>;
+    type SerializeStructVariant = Impossible<Vec<String>, FillError>;
+
+    serialize_displayed! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64,
+        serialize_char: char,
+        serialize_str: &str,
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<Vec<String>, FillError> {
+        Err(unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<Vec<String>, FillError> {
+        Ok(vec![])
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Vec<String>, FillError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Vec<String>, FillError> {
+        Ok(vec![])
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Vec<String>, FillError> {
+        Ok(vec![])
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Vec<String>, FillError> {
+        Ok(vec![variant.to_string()])
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Vec<String>, FillError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Vec<String>, FillError> {
+        Err(unsupported("an enum variant with fields"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<ValuesCollector, FillError> {
+        Ok(ValuesCollector(vec![]))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<ValuesCollector, FillError> {
+        Ok(ValuesCollector(vec![]))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<ValuesCollector, FillError> {
+        Ok(ValuesCollector(vec![]))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, FillError> {
+        Err(unsupported("an enum variant with fields"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, FillError> {
+        Err(unsupported("a nested map"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, FillError> {
+        Err(unsupported("a nested struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, FillError> {
+        Err(unsupported("an enum variant with fields"))
+    }
+}
+
+/// Collects the values of the elements of a sequence.
+struct ValuesCollector(Vec<String>);
+
+impl ValuesCollector {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FillError> {
+        self.0.extend(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for ValuesCollector {
+    type Ok = Vec<String>;
+    type Error = FillError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FillError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Vec<String>, FillError> {
+        Ok(self.0)
+    }
+}
+
+impl ser::SerializeTuple for ValuesCollector {
+    type Ok = Vec<String>;
+    type Error = FillError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FillError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Vec<String>, FillError> {
+        Ok(self.0)
+    }
+}
+
+impl ser::SerializeTupleStruct for ValuesCollector {
+    type Ok = Vec<String>;
+    type Error = FillError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), FillError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Vec<String>, FillError> {
+        Ok(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matcher::{FillError, RouteMatcher};
+    use serde_derive::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Post<'a> {
+        lang: &'a str,
+        slug: &'a str,
+        id: u32,
+        draft: Option<bool>,
+    }
+
+    #[test]
+    fn fills_path_with_escaped_segments() {
+        let matcher = RouteMatcher::try_from("/{lang}/posts/{id:u32}/{slug}[/{draft}]")
+            .expect("should parse");
+        let post = Post {
+            lang: "en",
+            slug: "a/b c?",
+            id: 7,
+            draft: None,
+        };
+        assert_eq!(
+            matcher.fill(&post).expect("should fill"),
+            "/en/posts/7/a%2Fb%20c%3F"
+        );
+        let draft = Post {
+            draft: Some(true),
+            ..post
+        };
+        assert_eq!(
+            matcher.fill(&draft).expect("should fill"),
+            "/en/posts/7/a%2Fb%20c%3F/true"
+        );
+    }
+
+    #[derive(Serialize)]
+    struct Search {
+        q: String,
+        tags: Vec<&'static str>,
+    }
+
+    #[test]
+    fn fills_query_and_round_trips() {
+        let matcher = RouteMatcher::try_from("/search?q={q}&tag={*:tags}").expect("should parse");
+        let search = Search {
+            q: "rust & c++".to_string(),
+            tags: vec!["web", "wasm"],
+        };
+        let route = matcher.fill(&search).expect("should fill");
+        assert_eq!(route, "/search?q=rust+%26+c%2B%2B&tag=web&tag=wasm");
+
+        let decoding = RouteMatcher {
+            settings: crate::matcher::MatcherSettings {
+                decode_captures: true,
+                ..matcher.settings
+            },
+            ..matcher
+        };
+        let (_, captures) = decoding
+            .try_capture_route_into_map(&route)
+            .expect("should match");
+        assert_eq!(captures["q"], "rust & c++");
+    }
+
+    #[test]
+    fn fails_without_required_capture() {
+        let matcher = RouteMatcher::try_from("/users/{id}/{tab}").expect("should parse");
+        let mut fields = BTreeMap::new();
+        fields.insert("id", "5");
+        assert_eq!(
+            matcher.fill(&fields),
+            Err(FillError::MissingCapture("tab".to_string()))
+        );
+
+        let matcher = RouteMatcher::try_from("/users/{id:u32}").expect("should parse");
+        assert!(matches!(
+            matcher.fill(&fields),
+            Ok(ref route) if route == "/users/5"
+        ));
+        fields.insert("id", "me");
+        assert!(matches!(
+            matcher.fill(&fields),
+            Err(FillError::Constraint { .. })
+        ));
+    }
+}
@@ -0,0 +1,244 @@
+//! Dispatches a route to a set of matchers in one pass over their leading literals.
+
+use crate::matcher::{encoding::Section, matcher_impl, Captures, MatcherToken, RouteMatcher};
+use nom::IResult;
+use std::{borrow::Cow, collections::BTreeMap};
+
+/// A collection of matchers that are indexed by the literal each of them starts with.
+///
+/// When many matchers share a literal prefix, such as `/api/v1/`, that prefix is only checked once
+/// per route, and matchers whose leading literal can't match the route are never attempted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatcherTrie {
+    matchers: Vec<RouteMatcher>,
+    case_sensitive: Node,
+    case_insensitive: Node,
+}
+
+/// A node in the trie, keyed by the characters of the leading literals.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Node {
+    children: BTreeMap<char, Node>,
+    /// Indices of the matchers whose leading literal ends at this node.
+    matchers: Vec<usize>,
+}
+
+impl Node {
+    fn insert(&mut self, key: impl Iterator<Item = char>, index: usize) {
+        let node = key.fold(self, |node, c| node.children.entry(c).or_default());
+        node.matchers.push(index);
+    }
+
+    /// Walks the path, collecting every matcher whose leading literal is a prefix of it,
+    /// along with the number of bytes of the path that literal covers.
+    fn collect(&self, path: &str, case_insensitive: bool, found: &mut Vec<(usize, usize)>) {
+        let mut node = self;
+        found.extend(node.matchers.iter().map(|index| (*index, 0)));
+        for (offset, c) in path.char_indices() {
+            let next = if case_insensitive {
+                c.to_lowercase()
+                    .try_fold(node, |node, c| node.children.get(&c))
+            } else {
+                node.children.get(&c)
+            };
+            node = match next {
+                Some(next) => next,
+                None => return,
+            };
+            let consumed = offset + c.len_utf8();
+            found.extend(node.matchers.iter().map(|index| (*index, consumed)));
+        }
+    }
+}
+
+impl MatcherTrie {
+    /// Creates a trie from a list of matchers.
+    ///
+    /// The order of the matchers is significant: candidates are produced in the same order the
+    /// matchers were provided in.
+    pub fn new(matchers: Vec<RouteMatcher>) -> Self {
+        let mut trie = MatcherTrie {
+            matchers: Vec::with_capacity(matchers.len()),
+            case_sensitive: Node::default(),
+            case_insensitive: Node::default(),
+        };
+        for matcher in matchers {
+            trie.push(matcher);
+        }
+        trie
+    }
+
+    /// Adds a matcher after the ones already in the trie.
+    pub fn push(&mut self, matcher: RouteMatcher) {
+        let index = self.matchers.len();
+        let literal = match matcher.tokens.first() {
+            Some(MatcherToken::Exact(literal)) => literal.as_str(),
+            _ => "",
+        };
+        if matcher.settings.case_insensitive {
+            self.case_insensitive
+                .insert(literal.chars().flat_map(char::to_lowercase), index);
+        } else {
+            self.case_sensitive.insert(literal.chars(), index);
+        }
+        self.matchers.push(matcher);
+    }
+
+    /// The matchers held by this trie, in the order they were provided.
+    pub fn matchers(&self) -> &[RouteMatcher] {
+        &self.matchers
+    }
+
+    /// Gets the matchers whose leading literal matches the start of the route, in order.
+    ///
+    /// The leading literal has already been checked for every candidate,
+    /// so capturing from a candidate only considers the remainder of its matcher.
+    pub fn candidates<'a, 'b>(&'b self, route: &'a str) -> Vec<Candidate<'a, 'b>> {
+        let mut found = vec![];
+        self.case_sensitive.collect(route, false, &mut found);
+        self.case_insensitive.collect(route, true, &mut found);
+        found.sort_by_key(|(index, _)| *index);
+        found
+            .into_iter()
+            .map(|(index, consumed)| {
+                let matcher = &self.matchers[index];
+                let (skip_tokens, section) = match matcher.tokens.first() {
+                    Some(MatcherToken::Exact(literal)) => (1, Section::Path.after(literal)),
+                    _ => (0, Section::Path),
+                };
+                Candidate {
+                    index,
+                    matcher,
+                    rest: &route[consumed..],
+                    skip_tokens,
+                    section,
+                }
+            })
+            .collect()
+    }
+}
+
+/// A matcher whose leading literal has matched a route.
+#[derive(Debug, Clone, Copy)]
+pub struct Candidate<'a, 'b> {
+    index: usize,
+    matcher: &'b RouteMatcher,
+    rest: &'a str,
+    skip_tokens: usize,
+    /// The section of the route that the rest of the matcher starts in.
+    section: Section,
+}
+
+impl<'a, 'b: 'a> Candidate<'a, 'b> {
+    /// The position of the matcher in the list the trie was created from.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The matcher that produced this candidate.
+    pub fn matcher(&self) -> &'b RouteMatcher {
+        self.matcher
+    }
+
+    /// Match the rest of the route, collecting the results into a map.
+    pub fn capture_route_into_map(&self) -> IResult<&'a str, Captures<'a>> {
+        let tokens = &self.matcher.tokens[self.skip_tokens..];
+        matcher_impl::match_into_map(tokens, &self.matcher.settings, self.section)(self.rest)
+    }
+
+    /// Match the rest of the route in every way of including or skipping the optional sections,
+    /// collecting the results of each way that matches into a map.
+    pub fn capture_route_into_maps(&self) -> impl Iterator<Item = (&'a str, Captures<'a>)> + 'a {
+        let tokens = &self.matcher.tokens[self.skip_tokens..];
+        matcher_impl::match_expansions_into_map(
+            tokens,
+            self.matcher.settings,
+            self.section,
+            self.rest,
+        )
+    }
+
+    /// Match the rest of the route, collecting the results into a vector.
+    pub fn capture_route_into_vec(&self) -> IResult<&'a str, Vec<Cow<'a, str>>> {
+        let tokens = &self.matcher.tokens[self.skip_tokens..];
+        matcher_impl::match_into_vec(tokens, &self.matcher.settings, self.section)(self.rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::MatcherSettings;
+
+    fn trie(matchers: &[&str], case_insensitive: bool) -> MatcherTrie {
+        let settings = MatcherSettings {
+            case_insensitive,
+            ..Default::default()
+        };
+        MatcherTrie::new(
+            matchers
+                .iter()
+                .map(|m| RouteMatcher::new(m, settings).expect("should parse"))
+                .collect(),
+        )
+    }
+
+    fn indices(trie: &MatcherTrie, route: &str) -> Vec<usize> {
+        trie.candidates(route)
+            .iter()
+            .map(Candidate::index)
+            .collect()
+    }
+
+    #[test]
+    fn only_matching_prefixes_are_candidates() {
+        let trie = trie(&["/api/v1/users", "/api/v1/posts", "/about"], false);
+        assert_eq!(indices(&trie, "/api/v1/posts"), vec![1]);
+        assert_eq!(indices(&trie, "/about"), vec![2]);
+        assert!(indices(&trie, "/nothing").is_empty());
+    }
+
+    #[test]
+    fn candidates_keep_declaration_order() {
+        let trie = trie(&["/variant/stuff", "{cap}", "/variant", "/"], false);
+        assert_eq!(indices(&trie, "/variant/stuff"), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn case_insensitive_prefix() {
+        let trie = trie(&["/lorem/ipsum"], true);
+        assert_eq!(indices(&trie, "/LoReM/IPSUM"), vec![0]);
+        let trie = self::trie(&["/lorem/ipsum"], false);
+        assert!(indices(&trie, "/LoReM/IPSUM").is_empty());
+    }
+
+    #[test]
+    fn candidate_captures_rest_of_route() {
+        let trie = trie(&["/api/v1/{resource}/{id}", "/api/{*:rest}"], false);
+        let candidates = trie.candidates("/api/v1/users/42");
+        let (_, captures) = candidates[0]
+            .capture_route_into_map()
+            .expect("should match");
+        assert_eq!(captures["resource"], "users".to_string());
+        assert_eq!(captures["id"], "42".to_string());
+        let (_, captures) = candidates[1]
+            .capture_route_into_vec()
+            .expect("should match");
+        assert_eq!(captures, vec!["v1/users/42"]);
+    }
+
+    #[test]
+    fn candidate_decodes_in_section_of_leading_literal() {
+        let settings = MatcherSettings {
+            decode_captures: true,
+            ..Default::default()
+        };
+        let trie = MatcherTrie::new(vec![
+            RouteMatcher::new("?q={query}", settings).expect("should parse")
+        ]);
+        let (_, captures) = trie.candidates("?q=a+b")[0]
+            .capture_route_into_vec()
+            .expect("should match");
+        assert_eq!(captures, vec!["a b"]);
+    }
+}
@@ -1,13 +1,11 @@
+use crate::matcher::MatcherSettings;
 use nom::{
     bytes::complete::{tag, tag_no_case},
-    character::complete::anychar,
-    combinator::{cond, map, peek, rest},
+    combinator::{cond, map},
     error::{ErrorKind, ParseError},
-    multi::many_till,
     sequence::pair,
     IResult,
 };
-use std::{iter::Peekable, rc::Rc, slice::Iter};
 use yew_router_route_parser::MatcherToken;
 
 /// Allows a configurable tag that can optionally be case insensitive.
@@ -24,37 +22,49 @@ pub fn tag_possibly_case_sensitive<'a, 'b: 'a>(
     )
 }
 
-/// Similar to alt, but works on a vector of tags.
-#[allow(unused)]
-pub fn alternative(alternatives: Vec<String>) -> impl Fn(&str) -> IResult<&str, &str> {
-    move |i: &str| {
-        for alternative in &alternatives {
-            if let done @ IResult::Ok(..) = tag(alternative.as_str())(i) {
-                return done;
-            }
-        }
-        Err(nom::Err::Error((i, ErrorKind::Tag))) // nothing found.
+/// Similar to alt, but works on a slice of tags, and matches the longest tag that it can.
+pub fn alternative<'a, 'b>(
+    alternatives: &'b [String],
+    is_sensitive: bool,
+) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> + 'b {
+    move |i: &'a str| {
+        alternatives
+            .iter()
+            .filter_map(|alternative| {
+                let matched: IResult<&str, &str> = if is_sensitive {
+                    tag(alternative.as_str())(i)
+                } else {
+                    tag_no_case(alternative.as_str())(i)
+                };
+                matched.ok()
+            })
+            .max_by_key(|(_, matched)| matched.len())
+            .ok_or(nom::Err::Error((i, ErrorKind::Tag))) // nothing found.
     }
 }
 
 /// Consumes the input until the provided parser succeeds.
-/// The consumed input is returned in the form of an allocated string.
+/// The consumed input is returned as a slice of the input.
 /// # Note
 /// `stop_parser` only peeks its input.
-pub fn consume_until<'a, F, E>(stop_parser: F) -> impl Fn(&'a str) -> IResult<&'a str, String, E>
+pub fn consume_until<'a, F, E>(stop_parser: F) -> impl Fn(&'a str) -> IResult<&'a str, &'a str, E>
 where
     E: ParseError<&'a str>,
     F: Fn(&'a str) -> IResult<&'a str, &'a str, E>,
 {
-    // In order for the returned fn to be Fn instead of FnOnce, wrap the inner fn in an RC.
-    let f = Rc::new(many_till(
-        anychar,
-        peek(stop_parser), // once this succeeds, stop folding.
-    ));
-    move |i: &str| {
-        let (i, (first, _stop)): (&str, (Vec<char>, &str)) = (f)(i)?;
-        let ret = first.into_iter().collect();
-        Ok((i, ret))
+    move |i: &'a str| {
+        let stop = i
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain(Some(i.len()))
+            .find(|index| stop_parser(&i[*index..]).is_ok()); // once this succeeds, stop.
+        match stop {
+            Some(index) => Ok((&i[index..], &i[..index])),
+            None => Err(nom::Err::Error(E::from_error_kind(
+                &i[i.len()..],
+                ErrorKind::Eof,
+            ))),
+        }
     }
 }
 
@@ -62,23 +72,46 @@ where
 /// characters used to terminate a forward search.
 ///
 /// # Panics
-/// This function assumes that the next item after a Capture must be an Exact.
+/// This function assumes that the next item after a Capture must be an Exact,
+/// and that optional sections have already been laid out in line.
 /// If this is violated, this function will panic.
+///
+/// Literals are found with the same case sensitivity that they're matched with.
 pub fn next_delimiter<'a>(
-    iter: &mut Peekable<Iter<MatcherToken>>,
+    token: &MatcherToken,
+    settings: MatcherSettings,
 ) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> {
-    let t: MatcherToken = iter
-        .peek()
-        .copied()
-        .cloned()
-        .expect("There must be at least one token to peak in next_delimiter");
+    let t: MatcherToken = token.clone();
 
     move |i: &'a str| match &t {
+        MatcherToken::Exact(sequence) if settings.case_insensitive => {
+            tag_no_case(sequence.as_str())(i)
+        }
         MatcherToken::Exact(sequence) => tag(sequence.as_str())(i),
-        MatcherToken::End => rest(i),
+        MatcherToken::OneOf(alternatives) => {
+            alternative(alternatives, !settings.case_insensitive)(i)
+        }
+        MatcherToken::End => {
+            if i.is_empty() {
+                Ok((i, i))
+            } else {
+                Err(nom::Err::Error((i, ErrorKind::Eof)))
+            }
+        }
+        MatcherToken::Query { .. } => {
+            // The query may be left out entirely, so whatever follows it also ends the capture.
+            if i.is_empty() || i.starts_with('?') || i.starts_with('#') {
+                Ok((i, &i[..0]))
+            } else {
+                Err(nom::Err::Error((i, ErrorKind::Tag)))
+            }
+        }
         MatcherToken::Capture(_) => {
             panic!("underlying parser should not allow two captures in a row")
         }
+        MatcherToken::Optional(_) => {
+            panic!("optional sections should be laid out in line before they are matched")
+        }
     }
 }
 
@@ -90,7 +123,7 @@ mod tests {
     fn consume_until_simple() {
         let parser = consume_until::<_, ()>(tag("z"));
         let parsed = parser("abcz").expect("Should parse");
-        assert_eq!(parsed, ("z", "abc".to_string()))
+        assert_eq!(parsed, ("z", "abc"))
     }
 
     #[test]
@@ -102,26 +135,46 @@ mod tests {
 
     #[test]
     fn alternative_simple() {
-        let parser = alternative(
-            vec!["c", "d", "abc"]
-                .into_iter()
-                .map(String::from)
-                .collect(),
-        );
+        let alternatives = vec!["c", "d", "abc"]
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        let parser = alternative(&alternatives, true);
         let parsed = parser("abcz").expect("Should parse");
         assert_eq!(parsed, ("z", "abc"))
     }
 
+    #[test]
+    fn alternative_prefers_longest() {
+        let alternatives = vec!["post", "posts"]
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        let parser = alternative(&alternatives, true);
+        let parsed = parser("posts/1").expect("Should parse");
+        assert_eq!(parsed, ("/1", "posts"))
+    }
+
+    #[test]
+    fn alternative_case_insensitive() {
+        let alternatives = vec!["posts", "articles"]
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        alternative(&alternatives, true)("Articles").expect_err("Should not match");
+        let parsed = alternative(&alternatives, false)("Articles").expect("Should match");
+        assert_eq!(parsed, ("", "Articles"))
+    }
+
     #[test]
     fn alternative_and_consume_until() {
-        let parser = consume_until(alternative(
-            vec!["c", "d", "abc"]
-                .into_iter()
-                .map(String::from)
-                .collect(),
-        ));
+        let alternatives = vec!["c", "d", "abc"]
+            .into_iter()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        let parser = consume_until(alternative(&alternatives, true));
         let parsed = parser("first_stuff_abc").expect("should parse");
-        assert_eq!(parsed, ("abc", "first_stuff_".to_string()))
+        assert_eq!(parsed, ("abc", "first_stuff_"))
     }
 
     #[test]
@@ -0,0 +1,665 @@
+//! Describes routes as the `paths` of an OpenAPI 3 document.
+//!
+//! The types in this module implement `Serialize` in the shape that OpenAPI expects,
+//! so they can be written out as JSON or YAML with the serde format of your choice.
+//!
+//! # Example
+//! ```
+//! use yew_router_min::{matcher::RouteMatcher, openapi};
+//!
+//! let matcher = RouteMatcher::try_from("/users/{id:u32}?tab={tab}").unwrap();
+//! let paths = openapi::paths_from_matcher(&matcher);
+//! let parameters = &paths["/users/{id}"].parameters;
+//! assert_eq!(parameters[0].name, "id");
+//! assert_eq!(parameters[1].name, "tab");
+//! ```
+
+use crate::{
+    matcher::{CaptureVariant, Constraint, MatcherToken, QueryValue, RouteMatcher, TypeConstraint},
+    route::{CaptureInfo, RouteInfo},
+};
+use serde_derive::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// The `paths` of an OpenAPI document, from path templates to what they accept.
+pub type Paths = BTreeMap<String, PathItem>;
+
+/// A path template, along with the parameters that are shared by all of its operations.
+///
+/// Routes don't say which HTTP methods they accept, so operations are left for the caller to add.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct PathItem {
+    /// The path parameters, in the order they appear in the path, followed by the query parameters.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<Parameter>,
+}
+
+/// A parameter that is taken from the path or the query.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Parameter {
+    /// The name of the capture, or the key of the query parameter.
+    pub name: String,
+    /// Where the parameter is taken from.
+    #[serde(rename = "in")]
+    pub location: ParameterLocation,
+    /// Whether the parameter has to be given, which path parameters always do.
+    pub required: bool,
+    /// The values the parameter accepts.
+    pub schema: Schema,
+}
+
+/// Where a parameter is taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterLocation {
+    /// A section of the path, written as `{name}` in the path template.
+    Path,
+    /// A parameter of the query.
+    Query,
+}
+
+/// The values that a parameter accepts.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Schema {
+    /// The type of the values.
+    #[serde(rename = "type")]
+    pub kind: SchemaType,
+    /// A refinement of the type, like `int64` or `uuid`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<&'static str>,
+    /// The smallest value that is accepted, which is `0` for unsigned integers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<i64>,
+    /// The schema of the elements of an array.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<Box<Schema>>,
+    /// The only values that are accepted, if they are restricted to literals.
+    #[serde(rename = "enum", skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+}
+
+/// The type of the values that a `Schema` accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaType {
+    /// Any string.
+    String,
+    /// A whole number.
+    Integer,
+    /// Any number.
+    Number,
+    /// `true` or `false`.
+    Boolean,
+    /// A list of values, which are repeated query parameters.
+    Array,
+}
+
+impl Schema {
+    fn new(kind: SchemaType) -> Self {
+        Schema {
+            kind,
+            format: None,
+            minimum: None,
+            items: None,
+            values: vec![],
+        }
+    }
+
+    fn with_format(kind: SchemaType, format: &'static str) -> Self {
+        Schema {
+            format: Some(format),
+            ..Schema::new(kind)
+        }
+    }
+
+    fn literal(literal: &str) -> Self {
+        Schema {
+            values: vec![literal.to_string()],
+            ..Schema::new(SchemaType::String)
+        }
+    }
+
+    fn array(items: Schema) -> Self {
+        Schema {
+            items: Some(Box::new(items)),
+            ..Schema::new(SchemaType::Array)
+        }
+    }
+
+    fn from_type_constraint(ty: TypeConstraint) -> Self {
+        let unsigned = |format| Schema {
+            minimum: Some(0),
+            ..Schema::with_format(SchemaType::Integer, format)
+        };
+        match ty {
+            TypeConstraint::U8 | TypeConstraint::U16 => unsigned("int32"),
+            TypeConstraint::U32
+            | TypeConstraint::U64
+            | TypeConstraint::U128
+            | TypeConstraint::Usize => unsigned("int64"),
+            TypeConstraint::I8 | TypeConstraint::I16 | TypeConstraint::I32 => {
+                Schema::with_format(SchemaType::Integer, "int32")
+            }
+            TypeConstraint::I64 | TypeConstraint::I128 | TypeConstraint::Isize => {
+                Schema::with_format(SchemaType::Integer, "int64")
+            }
+            TypeConstraint::Bool => Schema::new(SchemaType::Boolean),
+            TypeConstraint::Date => Schema::with_format(SchemaType::String, "date"),
+            TypeConstraint::Uuid => Schema::with_format(SchemaType::String, "uuid"),
+        }
+    }
+
+    /// The schema of a field, from its type as written in the struct or variant,
+    /// along with whether the field has to be given.
+    ///
+    /// Types are recognized by their last path segment,
+    /// and types that aren't recognized are taken to be strings.
+    fn from_field_type(field_type: &str) -> (Self, bool) {
+        if let Some(inner) = wrapped(field_type, "Option") {
+            return (Schema::from_field_type(inner).0, false);
+        }
+        if let Some(inner) = wrapped(field_type, "Vec") {
+            return (Schema::array(Schema::from_field_type(inner).0), false);
+        }
+        let name = field_type.rsplit("::").next().unwrap_or(field_type);
+        let schema = match name {
+            "f32" => Schema::with_format(SchemaType::Number, "float"),
+            "f64" => Schema::with_format(SchemaType::Number, "double"),
+            name => TypeConstraint::from_name(name)
+                .map(Schema::from_type_constraint)
+                .unwrap_or_else(|| Schema::new(SchemaType::String)),
+        };
+        (schema, true)
+    }
+}
+
+/// Gets `T` from a type that is written `Wrapper<T>`, or with any path before `Wrapper`.
+fn wrapped<'a>(field_type: &'a str, wrapper: &str) -> Option<&'a str> {
+    let inner = field_type.strip_suffix('>')?;
+    let (path, inner) = inner.split_at(inner.find('<')?);
+    let name = path.rsplit("::").next().unwrap_or(path);
+    if name == wrapper {
+        Some(&inner[1..])
+    } else {
+        None
+    }
+}
+
+/// The most path templates that are produced for one matcher.
+///
+/// Each optional section doubles the templates and each choice between literals multiplies them,
+/// so the templates past this many are left out.
+pub const MAX_TEMPLATES: usize = 1024;
+
+/// How deep the routes of fields are expanded in `paths_from_routes`.
+///
+/// A type can have a field of its own type, so past this depth,
+/// the field is described as a path parameter instead of by its routes.
+pub const MAX_NESTING: usize = 8;
+
+/// Describes the paths that the matcher accepts.
+///
+/// An optional section, or a choice between literals, produces a path template for each way of
+/// matching it, since OpenAPI path templates can't express either.
+/// Only the first `MAX_TEMPLATES` of them are described.
+/// Unnamed captures are named by their position among the captures, and fragments are left out.
+/// Without fields to go by, captures are strings unless they are constrained to a type.
+///
+/// Captures of more than one section, like `{*:path}` or `{2:dir}`, are written as `{path}` and
+/// `{dir}`, but OpenAPI path parameters can't contain `/`, so the path templates only describe the
+/// routes where those captures take a single section.
+pub fn paths_from_matcher(matcher: &RouteMatcher) -> Paths {
+    collect_paths(templates(&matcher.tokens, &[], 0))
+}
+
+/// Describes the paths that the structs or variants of a derived `Switch` implementation accept,
+/// as listed by `Switch::routes`.
+///
+/// This works like `paths_from_matcher`, but the schemas of the parameters come from the types
+/// of their fields, and query parameters for `Option` and `Vec` fields aren't required.
+/// A field whose type has routes of its own, like one captured with `#[rest]`,
+/// is expanded into a path template for each of its routes, up to `MAX_NESTING` fields deep.
+///
+/// Routes whose matcher strings don't parse, which can only be built by hand,
+/// are left out instead of failing the whole document.
+pub fn paths_from_routes(routes: &[RouteInfo]) -> Paths {
+    collect_paths(
+        routes
+            .iter()
+            .flat_map(|route| route_templates(route, 0))
+            .collect(),
+    )
+}
+
+/// Builds the templates of a route, whose fields are nested `depth` fields deep.
+fn route_templates(route: &RouteInfo, depth: usize) -> Vec<Template> {
+    match RouteMatcher::try_from(route.matcher) {
+        Ok(matcher) => templates(&matcher.tokens, route.captures, depth),
+        Err(_) => vec![],
+    }
+}
+
+fn collect_paths(templates: Vec<Template>) -> Paths {
+    let mut paths = Paths::new();
+    for template in templates {
+        let path = if template.path.starts_with('/') {
+            template.path
+        } else {
+            format!("/{}", template.path)
+        };
+        // The first route that produces a path template is the one that would match it.
+        paths.entry(path).or_insert(PathItem {
+            parameters: template.parameters,
+        });
+    }
+    paths
+}
+
+/// A path template that is being built, along with its parameters.
+#[derive(Clone, Default)]
+struct Template {
+    path: String,
+    parameters: Vec<Parameter>,
+    /// Whether the template has reached the fragment, which is left out of it.
+    fragment: bool,
+}
+
+/// Builds a template for every way the tokens can match a route, up to `MAX_TEMPLATES`.
+fn templates(tokens: &[MatcherToken], captures: &[CaptureInfo], depth: usize) -> Vec<Template> {
+    let tokens = name_captures_by_position(tokens);
+    let fields = capture_fields(&tokens, captures);
+    extend_templates(vec![Template::default()], &tokens, &fields, depth)
+}
+
+/// The fields that captures populate, by the names of the captures.
+type Fields<'a> = HashMap<String, &'a CaptureInfo>;
+
+/// Finds the field of each capture.
+///
+/// Captures are named after their fields, except in tuple structs and variants,
+/// where the fields are named by position even if the matcher string names their captures.
+fn capture_fields<'a>(tokens: &[MatcherToken], captures: &'a [CaptureInfo]) -> Fields<'a> {
+    fn in_order<'t>(tokens: &'t [MatcherToken], found: &mut Vec<&'t CaptureVariant>) {
+        for token in tokens {
+            match token {
+                MatcherToken::Capture(capture) => found.push(capture),
+                MatcherToken::Query { params, .. } => {
+                    found.extend(params.iter().filter_map(|param| match &param.value {
+                        QueryValue::Capture(capture) => Some(capture),
+                        QueryValue::Exact(_) => None,
+                    }))
+                }
+                MatcherToken::Optional(section) => in_order(section, found),
+                MatcherToken::Exact(_) | MatcherToken::OneOf(_) | MatcherToken::End => {}
+            }
+        }
+    }
+    let mut found = vec![];
+    in_order(tokens, &mut found);
+    found
+        .into_iter()
+        .enumerate()
+        .filter_map(|(position, capture)| {
+            let name = capture_name(capture);
+            let position = position.to_string();
+            let info = captures
+                .iter()
+                .find(|info| info.name == name)
+                .or_else(|| captures.iter().find(|info| info.name == position))?;
+            Some((name.to_string(), info))
+        })
+        .collect()
+}
+
+fn extend_templates(
+    mut templates: Vec<Template>,
+    tokens: &[MatcherToken],
+    fields: &Fields,
+    depth: usize,
+) -> Vec<Template> {
+    for token in tokens {
+        templates = match token {
+            MatcherToken::Exact(literal) => templates
+                .into_iter()
+                .map(|template| template.with_literal(literal))
+                .collect(),
+            MatcherToken::OneOf(alternatives) => templates
+                .into_iter()
+                .flat_map(|template| {
+                    alternatives
+                        .iter()
+                        .map(move |alternative| template.clone().with_literal(alternative))
+                })
+                .take(MAX_TEMPLATES)
+                .collect(),
+            MatcherToken::Capture(capture) => {
+                let nested = nested_templates(capture, fields, depth);
+                templates
+                    .into_iter()
+                    .flat_map(|template| template.with_capture(capture, &nested, fields))
+                    .take(MAX_TEMPLATES)
+                    .collect()
+            }
+            MatcherToken::Optional(section) => {
+                let included = extend_templates(templates.clone(), section, fields, depth);
+                included
+                    .into_iter()
+                    .chain(templates)
+                    .take(MAX_TEMPLATES)
+                    .collect()
+            }
+            MatcherToken::Query { params, .. } => templates
+                .into_iter()
+                .map(|mut template| {
+                    for param in params {
+                        template.parameters.push(query_parameter(
+                            &param.key,
+                            &param.value,
+                            param.optional,
+                            fields,
+                        ));
+                    }
+                    template
+                })
+                .collect(),
+            MatcherToken::End => templates,
+        };
+    }
+    templates
+}
+
+impl Template {
+    fn with_literal(mut self, literal: &str) -> Self {
+        if !self.fragment {
+            match literal.find('#') {
+                Some(start) => {
+                    self.path.push_str(&literal[..start]);
+                    self.fragment = true;
+                }
+                None => self.path.push_str(literal),
+            }
+        }
+        self
+    }
+
+    /// Adds a path parameter for the capture,
+    /// or a template for each of the nested templates of its field's type, if it has any.
+    fn with_capture(
+        self,
+        capture: &CaptureVariant,
+        nested: &[Template],
+        fields: &Fields,
+    ) -> Vec<Self> {
+        if self.fragment {
+            return vec![self];
+        }
+        if !nested.is_empty() {
+            return nested
+                .iter()
+                .map(|nested| {
+                    let mut template = self.clone();
+                    template.path.push_str(&nested.path);
+                    template
+                        .parameters
+                        .extend(nested.parameters.iter().cloned());
+                    template
+                })
+                .collect();
+        }
+
+        let name = capture_name(capture);
+        let info = fields.get(name);
+
+        let mut template = self;
+        template.path.push_str(&format!("{{{}}}", name));
+        template.parameters.push(Parameter {
+            name: name.to_string(),
+            location: ParameterLocation::Path,
+            required: true,
+            schema: match info {
+                Some(info) => Schema::from_field_type(info.field_type).0,
+                None => constraint_schema(capture),
+            },
+        });
+        vec![template]
+    }
+}
+
+/// The templates of the routes of the capture's field's type,
+/// or none if it has no routes or the field is nested `MAX_NESTING` fields deep.
+fn nested_templates(capture: &CaptureVariant, fields: &Fields, depth: usize) -> Vec<Template> {
+    if depth >= MAX_NESTING {
+        return vec![];
+    }
+    match fields.get(capture_name(capture)) {
+        Some(info) => (info.nested)()
+            .iter()
+            .flat_map(|route| route_templates(route, depth + 1))
+            .take(MAX_TEMPLATES)
+            .collect(),
+        None => vec![],
+    }
+}
+
+fn query_parameter(key: &str, value: &QueryValue, optional: bool, fields: &Fields) -> Parameter {
+    let (schema, required) = match value {
+        QueryValue::Exact(literal) => (Schema::literal(literal), true),
+        QueryValue::Capture(capture) => {
+            let name = capture_name(capture);
+            match fields.get(name) {
+                Some(info) => Schema::from_field_type(info.field_type),
+                None => match capture {
+                    CaptureVariant::ManyNamed(_) => {
+                        (Schema::array(Schema::new(SchemaType::String)), false)
+                    }
+                    capture => (constraint_schema(capture), true),
+                },
+            }
+        }
+    };
+    Parameter {
+        name: key.to_string(),
+        location: ParameterLocation::Query,
+        required: required && !optional,
+        schema,
+    }
+}
+
+fn constraint_schema(capture: &CaptureVariant) -> Schema {
+    match capture {
+        CaptureVariant::Constrained {
+            constraint: Constraint::Type(ty),
+            ..
+        } => Schema::from_type_constraint(*ty),
+        _ => Schema::new(SchemaType::String),
+    }
+}
+
+fn capture_name(capture: &CaptureVariant) -> &str {
+    match capture {
+        CaptureVariant::Named(name)
+        | CaptureVariant::ManyNamed(name)
+        | CaptureVariant::NumberedNamed { name, .. }
+        | CaptureVariant::Constrained { name, .. } => name,
+        CaptureVariant::Unnamed
+        | CaptureVariant::ManyUnnamed
+        | CaptureVariant::NumberedUnnamed { .. } => {
+            unreachable!("captures are named by position before templates are built")
+        }
+    }
+}
+
+/// Names every unnamed capture after its position among all of the captures,
+/// the same way the captures of tuple structs and variants are named.
+fn name_captures_by_position(tokens: &[MatcherToken]) -> Vec<MatcherToken> {
+    fn name_capture(capture: &CaptureVariant, position: &mut usize) -> CaptureVariant {
+        let name = position.to_string();
+        *position += 1;
+        match capture {
+            CaptureVariant::Unnamed => CaptureVariant::Named(name),
+            CaptureVariant::ManyUnnamed => CaptureVariant::ManyNamed(name),
+            CaptureVariant::NumberedUnnamed { sections } => CaptureVariant::NumberedNamed {
+                sections: *sections,
+                name,
+            },
+            capture => capture.clone(),
+        }
+    }
+    fn name_captures(tokens: &[MatcherToken], position: &mut usize) -> Vec<MatcherToken> {
+        tokens
+            .iter()
+            .map(|token| match token {
+                MatcherToken::Capture(capture) => {
+                    MatcherToken::Capture(name_capture(capture, position))
+                }
+                MatcherToken::Optional(section) => {
+                    MatcherToken::Optional(name_captures(section, position))
+                }
+                MatcherToken::Query { params, strict } => MatcherToken::Query {
+                    params: params
+                        .iter()
+                        .map(|param| {
+                            let mut param = param.clone();
+                            if let QueryValue::Capture(capture) = &param.value {
+                                param.value = QueryValue::Capture(name_capture(capture, position));
+                            }
+                            param
+                        })
+                        .collect(),
+                    strict: *strict,
+                },
+                token => token.clone(),
+            })
+            .collect()
+    }
+    name_captures(tokens, &mut 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::route::CaptureInfoKind;
+    use serde_json::json;
+
+    fn paths(matcher: &str) -> Paths {
+        paths_from_matcher(&RouteMatcher::try_from(matcher).expect("should parse"))
+    }
+
+    #[test]
+    fn writes_openapi_json() {
+        let paths = paths("/users/{id:u32}?sort=new&tag={*:tags}#details");
+        assert_eq!(
+            serde_json::to_value(&paths).expect("should serialize"),
+            json!({
+                "/users/{id}": {
+                    "parameters": [
+                        {
+                            "name": "id",
+                            "in": "path",
+                            "required": true,
+                            "schema": {"type": "integer", "format": "int64", "minimum": 0}
+                        },
+                        {
+                            "name": "sort",
+                            "in": "query",
+                            "required": true,
+                            "schema": {"type": "string", "enum": ["new"]}
+                        },
+                        {
+                            "name": "tag",
+                            "in": "query",
+                            "required": false,
+                            "schema": {"type": "array", "items": {"type": "string"}}
+                        }
+                    ]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn expands_optional_sections_and_alternatives() {
+        let paths = paths("/(posts|pages)[/{slug}]");
+        let templates: Vec<&str> = paths.keys().map(String::as_str).collect();
+        assert_eq!(
+            templates,
+            vec!["/pages", "/pages/{slug}", "/posts", "/posts/{slug}"]
+        );
+        assert!(paths["/posts"].parameters.is_empty());
+    }
+
+    #[test]
+    fn names_unnamed_captures_by_position() {
+        let paths = paths("/{}/{*}");
+        let parameters = &paths["/{0}/{1}"].parameters;
+        assert_eq!(parameters.len(), 2);
+        assert_eq!(parameters[1].name, "1");
+    }
+
+    #[test]
+    fn leaves_out_routes_that_do_not_parse() {
+        let routes = [
+            RouteInfo {
+                name: "Broken",
+                matcher: "/broken/{",
+                captures: &[],
+                end: false,
+            },
+            RouteInfo {
+                name: "Users",
+                matcher: "/users",
+                captures: &[],
+                end: false,
+            },
+        ];
+        let paths = paths_from_routes(&routes);
+        let templates: Vec<&str> = paths.keys().map(String::as_str).collect();
+        assert_eq!(templates, vec!["/users"]);
+    }
+
+    #[test]
+    fn limits_the_templates_of_a_matcher() {
+        let paths = paths("/(a|b|c|d)/(a|b|c|d)/(a|b|c|d)/(a|b|c|d)/(a|b|c|d)/(a|b|c|d)");
+        assert_eq!(paths.len(), MAX_TEMPLATES);
+        assert!(paths.contains_key("/a/a/a/a/a/a"));
+    }
+
+    #[test]
+    fn stops_expanding_a_type_nested_in_itself() {
+        // `Page(Page)`, as if with `#[to = "/page{*}"]`.
+        static PAGE: [RouteInfo; 1] = [RouteInfo {
+            name: "Page",
+            matcher: "/page{*}",
+            captures: &[CaptureInfo {
+                name: "0",
+                kind: CaptureInfoKind::ManyNamed,
+                field_type: "Page",
+                nested: page_routes,
+            }],
+            end: false,
+        }];
+        fn page_routes() -> &'static [RouteInfo] {
+            &PAGE
+        }
+
+        let paths = paths_from_routes(page_routes());
+        let deepest = format!("{}{{0}}", "/page".repeat(MAX_NESTING + 1));
+        assert_eq!(paths.keys().collect::<Vec<_>>(), vec![&deepest]);
+    }
+
+    #[test]
+    fn schemas_from_field_types() {
+        assert_eq!(
+            Schema::from_field_type("Option<i32>"),
+            (Schema::with_format(SchemaType::Integer, "int32"), false)
+        );
+        assert_eq!(
+            Schema::from_field_type("std::vec::Vec<bool>"),
+            (Schema::array(Schema::new(SchemaType::Boolean)), false)
+        );
+        assert_eq!(
+            Schema::from_field_type("String"),
+            (Schema::new(SchemaType::String), true)
+        );
+    }
+}
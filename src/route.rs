@@ -1,6 +1,10 @@
 //! Parses routes into enums or structs.
 
-/// Derivable routing trait that allows instances of implementors to be constructed from Routes.
+use crate::matcher::{CaptureKind, MatchError};
+use std::fmt;
+
+/// Derivable routing trait that allows instances of implementors to be constructed from Routes,
+/// and to be turned back into the route that would produce them.
 ///
 /// # Note
 /// Don't try to implement this yourself, rely on the derive macro.
@@ -38,17 +42,320 @@
 ///     TestEnum::from_path("/capture/unnamed/lorem"),
 ///     Some(TestEnum::CaptureUnnamed("lorem".to_string()))
 /// );
+///
+/// assert_eq!(
+///     TestEnum::CaptureNumber { num: 22 }.to_path(),
+///     "/capture/number/22".to_string()
+/// );
 /// ```
 pub trait Switch: Sized {
     /// Based on a route, possibly produce an itself.
+    ///
+    /// This is also the entry point for converting sections captured from a route into fields,
+    /// so the captured sections are borrowed from the route instead of being copied out of it.
     fn from_path(path: &str) -> Option<Self>;
 
+    /// Builds the route that would produce this item when passed to `from_path`.
+    fn to_path(&self) -> String;
+
+    /// Based on a route, possibly produce an itself, describing why it couldn't if it doesn't.
+    ///
+    /// Derived implementations list why each struct or variant they attempted failed to match,
+    /// so this is slower than `from_path`, and is meant for diagnosing routes that don't work.
+    fn try_from_path(path: &str) -> Result<Self, SwitchError> {
+        Self::from_path(path).ok_or_else(|| SwitchError {
+            route: path.to_string(),
+            attempts: vec![],
+        })
+    }
+
     /// Parses route.
+    ///
+    /// This is a convenience for routes that are already owned, and defers to `from_path`.
     fn from_route(part: String) -> Option<Self> {
         Self::from_path(&part)
     }
+
+    /// Converts a section captured from a route into an item, percent-decoding it first.
+    ///
+    /// Derived implementations match the capture as a route of their own, without decoding it,
+    /// so that each capture is only decoded once, by the type that finally consumes it.
+    fn from_capture(capture: &str, kind: CaptureKind) -> Option<Self> {
+        Self::from_path(&kind.decode(capture))
+    }
+
+    /// Writes this item as a section of a route, percent-encoding it so `from_capture` can read it back.
+    fn to_capture(&self, kind: CaptureKind) -> String {
+        kind.encode(&self.to_path())
+    }
+
+    /// Describes every struct or variant that this can be produced as, in the order they are tried.
+    ///
+    /// Derived implementations list one entry per struct or variant,
+    /// while other implementations, like the one for types that implement `FromStr`, list none.
+    fn routes() -> &'static [RouteInfo] {
+        &[]
+    }
+}
+
+/// Describes a struct or variant that a derived `Switch` implementation can produce.
+///
+/// # Example
+/// ```
+/// use yew_router_min::{route::CaptureInfoKind, Switch};
+/// #[derive(Switch, Clone)]
+/// enum AppRoute {
+///     #[to = "/users/{id}!"]
+///     User { id: u32 },
+///     #[to = "/files/{*:path}"]
+///     Files { path: String },
+/// }
+///
+/// let routes = AppRoute::routes();
+/// assert_eq!(routes[0].name, "User");
+/// assert_eq!(routes[0].matcher, "/users/{id}!");
+/// assert!(routes[0].end);
+/// assert_eq!(routes[1].captures[0].kind, CaptureInfoKind::ManyNamed);
+/// assert_eq!(routes[1].captures[0].field_type, "String");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RouteInfo {
+    /// The name of the struct or variant.
+    pub name: &'static str,
+    /// The route matcher string of the struct or variant, as written in its attributes.
+    ///
+    /// `#[rest]` and `#[end]` attributes are written as `{*}` and `!`,
+    /// and the prefix of an enum is included.
+    pub matcher: &'static str,
+    /// The captures of the matcher, in order, including those in optional sections and the query.
+    pub captures: &'static [CaptureInfo],
+    /// Whether the matcher ends with `!`, so it only matches whole routes.
+    pub end: bool,
+}
+
+/// Describes a capture of a struct or variant, and the field it populates.
+#[derive(Debug, Clone, Copy)]
+pub struct CaptureInfo {
+    /// The name of the capture, which is the position of the field for tuple structs and variants.
+    pub name: &'static str,
+    /// How much of the route the capture can span.
+    pub kind: CaptureInfoKind,
+    /// The type of the field, as it is written in the struct or variant.
+    pub field_type: &'static str,
+    /// Describes the routes of the field's type,
+    /// or of the type within an `Option` or `Vec` field, so nested routes can be expanded into a tree.
+    pub nested: fn() -> &'static [RouteInfo],
+}
+
+/// How much of the route a capture can span.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureInfoKind {
+    /// `{name}`, which captures a single section, and may be constrained.
+    Named,
+    /// `{*:name}`, which captures any number of sections.
+    ManyNamed,
+    /// `{3:name}`, which captures a fixed number of sections.
+    NumberedNamed {
+        /// The number of sections.
+        sections: usize,
+    },
+}
+
+/// Describes why `Switch::try_from_path` couldn't produce an item from a route.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwitchError {
+    /// The route that was switched on.
+    pub route: String,
+    /// Every struct or variant that was attempted, in the order they were attempted in.
+    ///
+    /// This is empty for implementations of `Switch` that weren't derived.
+    pub attempts: Vec<SwitchAttempt>,
+}
+
+/// A struct or variant that was attempted, and why it didn't match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwitchAttempt {
+    /// The name of the struct or variant.
+    pub name: &'static str,
+    /// Why the route didn't produce the struct or variant.
+    pub failure: SwitchFailure,
+}
+
+/// The reason a route didn't produce a struct or variant.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SwitchFailure {
+    /// The route didn't match the matcher of the struct or variant.
+    Match(MatchError),
+    /// The route matched, but a field couldn't be converted from what was captured for it.
+    Field {
+        /// The name of the field, or its position for unnamed fields.
+        field: &'static str,
+        /// The section that was captured for the field, or `None` if nothing was captured.
+        capture: Option<String>,
+    },
+}
+
+impl fmt::Display for SwitchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no route matched '{}'", self.route)?;
+        for attempt in &self.attempts {
+            write!(f, "\n  {}: {}", attempt.name, attempt.failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SwitchFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SwitchFailure::Match(error) => error.fmt(f),
+            SwitchFailure::Field {
+                field,
+                capture: Some(capture),
+            } => write!(
+                f,
+                "field '{}' couldn't be converted from '{}'",
+                field, capture
+            ),
+            SwitchFailure::Field {
+                field,
+                capture: None,
+            } => write!(f, "nothing was captured for field '{}'", field),
+        }
+    }
+}
+
+impl std::error::Error for SwitchError {}
+
+/// The path that an app is deployed under, which is left out of the matchers of its routes.
+///
+/// The same routes can then be served from `/`, `/staging/` or `/tenant-x/app/`
+/// by passing the base path to wherever routes are switched on or built.
+///
+/// # Example
+/// ```
+/// use yew_router_min::{route::BasePath, Switch};
+/// #[derive(Debug, Switch, PartialEq)]
+/// enum AppRoute {
+///     #[to = "/users/{id}"]
+///     User { id: u32 },
+///     #[to = "/"]
+///     Home,
+/// }
+///
+/// let base = BasePath::new("/tenant-x/app/");
+/// assert_eq!(
+///     base.from_path("/tenant-x/app/users/5"),
+///     Ok(AppRoute::User { id: 5 })
+/// );
+/// assert_eq!(base.from_path("/tenant-x/app"), Ok(AppRoute::Home));
+/// assert!(base.from_path::<AppRoute>("/users/5").is_err());
+/// assert_eq!(base.to_path(&AppRoute::User { id: 5 }), "/tenant-x/app/users/5");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BasePath {
+    /// The base path with a leading `/` and without a trailing one, or empty for the root.
+    base: String,
+}
+
+impl BasePath {
+    /// Creates a base path, adding a leading `/` and removing a trailing one if needed.
+    pub fn new(base: &str) -> Self {
+        let base = base.trim_matches('/');
+        if base.is_empty() {
+            BasePath::default()
+        } else {
+            BasePath {
+                base: format!("/{}", base),
+            }
+        }
+    }
+
+    /// The base path, which is empty if it's the root.
+    pub fn as_str(&self) -> &str {
+        &self.base
+    }
+
+    /// Removes the base path from the start of the path, or returns `None` if the path is outside it.
+    ///
+    /// The base path only matches whole segments, and the base path itself becomes `/`.
+    pub fn strip<'a>(&self, path: &'a str) -> Option<&'a str> {
+        let rest = path.strip_prefix(self.base.as_str())?;
+        if rest.is_empty() {
+            Some("/")
+        } else if rest.starts_with(&['/', '?', '#'][..]) {
+            Some(rest)
+        } else {
+            None
+        }
+    }
+
+    /// Removes the base path from the path, then produces an item from the rest with `Switch::from_path`.
+    pub fn from_path<T: Switch>(&self, path: &str) -> Result<T, BasePathError> {
+        let rest = self.strip_or_fail(path)?;
+        T::from_path(rest).ok_or_else(|| BasePathError::NoMatch {
+            route: rest.to_string(),
+        })
+    }
+
+    /// Removes the base path from the path,
+    /// then produces an item from the rest with `Switch::try_from_path`.
+    ///
+    /// If the rest of the path doesn't produce an item, the error from `Switch::try_from_path`
+    /// is returned as it is.
+    pub fn try_from_path<T: Switch>(&self, path: &str) -> Result<T, BasePathError> {
+        let rest = self.strip_or_fail(path)?;
+        T::try_from_path(rest).map_err(BasePathError::Switch)
+    }
+
+    /// Builds the route of the item, under the base path.
+    pub fn to_path<T: Switch>(&self, item: &T) -> String {
+        format!("{}{}", self.base, item.to_path())
+    }
+
+    fn strip_or_fail<'a>(&self, path: &'a str) -> Result<&'a str, BasePathError> {
+        self.strip(path).ok_or_else(|| BasePathError::OutsideBase {
+            base: self.base.clone(),
+            route: path.to_string(),
+        })
+    }
+}
+
+/// Describes why a `BasePath` couldn't produce an item from a route.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BasePathError {
+    /// The route doesn't start with the base path.
+    OutsideBase {
+        /// The base path.
+        base: String,
+        /// The route that was switched on.
+        route: String,
+    },
+    /// The route is under the base path, but the rest of it didn't produce an item
+    /// with `Switch::from_path`.
+    NoMatch {
+        /// The rest of the route, after the base path.
+        route: String,
+    },
+    /// The route is under the base path, but the rest of it didn't produce an item
+    /// with `Switch::try_from_path`, which described why.
+    Switch(SwitchError),
 }
 
+impl fmt::Display for BasePathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BasePathError::OutsideBase { base, route } => {
+                write!(f, "'{}' is outside the base path '{}'", route, base)
+            }
+            BasePathError::NoMatch { route } => write!(f, "no route matched '{}'", route),
+            BasePathError::Switch(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for BasePathError {}
+
 /// Wrapper that requires that an implementor of Switch must start with a `/`.
 ///
 /// This is needed for any non-derived type provided by yew-router to be used by itself.
@@ -60,26 +367,66 @@ pub struct LeadingSlash<T>(pub T);
 
 impl<U: Switch> Switch for LeadingSlash<U> {
     fn from_path(part: &str) -> Option<Self> {
-        if part.starts_with('/') {
-            U::from_path(&part[1..]).map(LeadingSlash)
-        } else {
-            None
-        }
+        part.strip_prefix('/')
+            .and_then(U::from_path)
+            .map(LeadingSlash)
+    }
+
+    fn to_path(&self) -> String {
+        format!("/{}", self.0.to_path())
+    }
+
+    fn from_capture(capture: &str, kind: CaptureKind) -> Option<Self> {
+        capture
+            .strip_prefix('/')
+            .and_then(|capture| U::from_capture(capture, kind))
+            .map(LeadingSlash)
+    }
+
+    fn to_capture(&self, kind: CaptureKind) -> String {
+        format!("/{}", self.0.to_capture(kind))
+    }
+
+    fn routes() -> &'static [RouteInfo] {
+        U::routes()
     }
 }
 
 /// Allows a section to match, providing a None value,
 /// if its contents are entirely missing, or starts with a '/'.
 #[derive(Debug, PartialEq, Clone, Copy)]
-pub struct AllowMissing<T: std::fmt::Debug>(pub Option<T>);
+pub struct AllowMissing<T: fmt::Debug>(pub Option<T>);
 
-impl<U: Switch + std::fmt::Debug> Switch for AllowMissing<U> {
+impl<U: Switch + fmt::Debug> Switch for AllowMissing<U> {
     fn from_path(part: &str) -> Option<Self> {
-        let inner = U::from_path(&part);
+        Self::from_inner(part, U::from_path(part))
+    }
+
+    fn to_path(&self) -> String {
+        self.0.as_ref().map(Switch::to_path).unwrap_or_default()
+    }
+
+    fn from_capture(capture: &str, kind: CaptureKind) -> Option<Self> {
+        Self::from_inner(capture, U::from_capture(capture, kind))
+    }
+
+    fn to_capture(&self, kind: CaptureKind) -> String {
+        self.0
+            .as_ref()
+            .map(|inner| inner.to_capture(kind))
+            .unwrap_or_default()
+    }
+
+    fn routes() -> &'static [RouteInfo] {
+        U::routes()
+    }
+}
 
+impl<U: fmt::Debug> AllowMissing<U> {
+    fn from_inner(part: &str, inner: Option<U>) -> Option<Self> {
         if inner.is_some() {
             Some(AllowMissing(inner))
-        } else if part == ""
+        } else if part.is_empty()
             || part.starts_with('/')
             || part.starts_with('?')
             || part.starts_with('&')
@@ -92,8 +439,12 @@ impl<U: Switch + std::fmt::Debug> Switch for AllowMissing<U> {
     }
 }
 
-impl<T: std::str::FromStr> Switch for T {
+impl<T: std::str::FromStr + ToString> Switch for T {
     fn from_path(s: &str) -> Option<Self> {
         ::std::str::FromStr::from_str(s).ok()
     }
+
+    fn to_path(&self) -> String {
+        self.to_string()
+    }
 }
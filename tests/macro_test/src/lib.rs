@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod tests {
-    use yew_router::Switch;
+    use yew_router_min::Switch;
 
     #[test]
     fn single_enum_variant() {
@@ -10,29 +10,10 @@ mod tests {
             Variant,
         }
         let route = String::from("/variant");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant)
     }
 
-    #[test]
-    fn single_enum_variant_unnamed_without_corresponding_capture_group() {
-        #[derive(Debug, Switch, PartialEq, Clone)]
-        pub enum Test {
-            #[to = "/variant"]
-            Variant(String),
-        }
-        let route = String::from("/variant");
-        assert!(
-            Test::switch(route).is_none(),
-            "there should not be a way to ever create this variant."
-        );
-        let route = String::from("/variant/some/stuff");
-        assert!(
-            Test::switch(route).is_none(),
-            "there should not be a way to ever create this variant."
-        );
-    }
-
     #[test]
     fn single_enum_variant_named_capture() {
         #[derive(Debug, Switch, PartialEq, Clone)]
@@ -41,7 +22,7 @@ mod tests {
             Variant { item: String },
         }
         let route = String::from("/variant/thing");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(
             switched,
             Test::Variant {
@@ -58,7 +39,7 @@ mod tests {
             Variant(String),
         }
         let route = String::from("/variant/thing");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant("thing".to_string()))
     }
 
@@ -70,7 +51,7 @@ mod tests {
             Variant(String, String),
         }
         let route = String::from("/variant/thing/other");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(
             switched,
             Test::Variant("thing".to_string(), "other".to_string())
@@ -85,7 +66,7 @@ mod tests {
             Variant { item1: String, item2: String },
         }
         let route = String::from("/variant/thing/other");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(
             switched,
             Test::Variant {
@@ -103,7 +84,7 @@ mod tests {
             Variant { item: String },
         }
         let route = String::from("/variantthing");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(
             switched,
             Test::Variant {
@@ -120,7 +101,7 @@ mod tests {
             Variant { item: String },
         }
         let route = String::from("/variantthingstuff");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(
             switched,
             Test::Variant {
@@ -137,7 +118,7 @@ mod tests {
             Variant,
         }
         let route = String::from("/variant/");
-        assert!(Test::switch(route).is_none());
+        assert!(Test::from_route(route).is_none());
     }
 
     #[test]
@@ -150,7 +131,7 @@ mod tests {
             Variant2,
         }
         let route = String::from("/variant/stuff");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(
             switched,
             Test::Variant2,
@@ -165,10 +146,11 @@ mod tests {
             #[to = "/variant"]
             Variant1,
             #[to = "/variant/stuff"]
+            #[switch(allow_shadowed)]
             Variant2,
         }
         let route = String::from("/variant/stuff");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(
             switched,
             Test::Variant1,
@@ -176,6 +158,46 @@ mod tests {
         )
     }
 
+    #[test]
+    fn multiple_enum_variant_capture_eager_matching() {
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        #[switch(allow_shadowed)]
+        pub enum Test {
+            #[to = "/variant/{item}"]
+            Variant1 { item: String },
+            #[to = "/variant/stuff"]
+            Variant2,
+        }
+        let route = String::from("/variant/stuff");
+        let switched = Test::from_route(route).expect("should produce item");
+        assert_eq!(
+            switched,
+            Test::Variant1 {
+                item: "stuff".to_string()
+            },
+            "The first variant should match first"
+        );
+        assert_eq!(Test::Variant2.to_path(), "/variant/stuff");
+    }
+
+    #[test]
+    fn multiple_enum_variant_capture_conversion_precedence() {
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/variant/{item}"]
+            Variant1 { item: u32 },
+            #[to = "/variant/stuff"]
+            Variant2,
+        }
+        let route = String::from("/variant/stuff");
+        let switched = Test::from_route(route).expect("should produce item");
+        assert_eq!(
+            switched,
+            Test::Variant2,
+            "The first variant should be passed over"
+        )
+    }
+
     #[test]
     fn single_enum_variant_convert_usize() {
         #[derive(Debug, Switch, PartialEq, Clone)]
@@ -184,7 +206,7 @@ mod tests {
             Variant(usize),
         }
         let route = String::from("/variant/42");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant(42))
     }
 
@@ -196,7 +218,7 @@ mod tests {
             Variant(usize),
         }
         let route = String::from("/variant/-42");
-        assert!(Test::switch(route).is_none());
+        assert!(Test::from_route(route).is_none());
     }
 
     #[test]
@@ -207,7 +229,7 @@ mod tests {
             Variant(isize),
         }
         let route = String::from("/variant/-42");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant(-42))
     }
 
@@ -219,23 +241,21 @@ mod tests {
             Variant(Option<String>),
         }
         let route = String::from("/variant");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant(None))
     }
 
-    // TODO allow missing is a little broken at the moment.
-    //    #[test]
-    //    fn single_enum_variant_missing_section_produces_none() {
-    //    use yew_router::switch::AllowMissing;
-    //        #[derive(Debug, Switch, PartialEq)]
-    //        pub enum Test {
-    //            #[to = "/variant/{cap}"]
-    //            Variant(AllowMissing<String>),
-    //        }
-    //        let route = String::from("/variant/");
-    //        let switched = Test::switch(route).expect("should produce item");
-    //        assert_eq!(switched, Test::Variant(AllowMissing(None)))
-    //    }
+    #[test]
+    fn single_enum_variant_missing_section_produces_none() {
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/variant?cap={cap}"]
+            Variant(Option<String>),
+        }
+        let route = String::from("/variant");
+        let switched = Test::from_route(route).expect("should produce item");
+        assert_eq!(switched, Test::Variant(None))
+    }
 
     #[test]
     fn leading_slash() {
@@ -245,7 +265,7 @@ mod tests {
             Variant,
         }
         let route = String::from("/");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant)
     }
 
@@ -257,7 +277,7 @@ mod tests {
             Variant(String),
         }
         let route = String::from("hello");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant("hello".to_string()))
     }
 
@@ -269,7 +289,7 @@ mod tests {
             Variant(String),
         }
         let route = String::from("hello");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant("hello".to_string()))
     }
 
@@ -281,7 +301,7 @@ mod tests {
             Variant(String),
         }
         let route = String::from("hello/there");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant("hello/there".to_string()))
     }
 
@@ -293,7 +313,7 @@ mod tests {
             Variant(String),
         }
         let route = String::from("hello/there");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant("hello/there".to_string()))
     }
 
@@ -305,7 +325,7 @@ mod tests {
             Variant(String),
         }
         let route = String::from("hello/there");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant("hello/there".to_string()))
     }
 
@@ -317,7 +337,7 @@ mod tests {
             Variant(String),
         }
         let route = String::from("hello/there");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant("hello/there".to_string()))
     }
 
@@ -329,7 +349,7 @@ mod tests {
             Variant(String),
         }
         let route = String::from("?query=lorem");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant("lorem".to_string()))
     }
 
@@ -341,7 +361,7 @@ mod tests {
             Variant(String),
         }
         let route = String::from("?query=lorem");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant("lorem".to_string()))
     }
 
@@ -353,7 +373,7 @@ mod tests {
             Variant,
         }
         let route = String::from("#fragment");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant)
     }
 
@@ -365,7 +385,7 @@ mod tests {
             Variant(String, String),
         }
         let route = String::from("#loremipsumdolor");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(
             switched,
             Test::Variant("lorem".to_string(), "dolor".to_string())
@@ -380,7 +400,7 @@ mod tests {
             Variant(String, String),
         }
         let route = String::from("#loremipsumdolor");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(
             switched,
             Test::Variant("lorem".to_string(), "dolor".to_string())
@@ -395,7 +415,7 @@ mod tests {
             Variant,
         }
         let route = String::from("/escape!");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant)
     }
 
@@ -407,10 +427,53 @@ mod tests {
             Variant,
         }
         let route = String::from("/escape{}a");
-        let switched = Test::switch(route).expect("should produce item");
+        let switched = Test::from_route(route).expect("should produce item");
         assert_eq!(switched, Test::Variant)
     }
 
+    #[test]
+    fn generic_enum_matches_repeatedly() {
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test<T: std::str::FromStr + ToString> {
+            #[to = "/variant/{item}"]
+            Variant(T),
+        }
+        for _ in 0..2 {
+            let switched = Test::<usize>::from_path("/variant/42").expect("should produce item");
+            assert_eq!(switched, Test::Variant(42));
+            let switched = Test::<String>::from_path("/variant/42").expect("should produce item");
+            assert_eq!(switched, Test::Variant("42".to_string()));
+        }
+    }
+
+    #[test]
+    fn shared_prefix_keeps_declaration_order() {
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum Test {
+            #[to = "/api/v1/users/{id}"]
+            User(usize),
+            #[to = "/api/v1/{*:rest}"]
+            Fallback(String),
+            #[to = "/api/v1/posts"]
+            Posts,
+            #[to = "{*:rest}"]
+            Other(String),
+        }
+        assert_eq!(Test::from_path("/api/v1/users/2"), Some(Test::User(2)));
+        assert_eq!(
+            Test::from_path("/api/v1/posts"),
+            Some(Test::Fallback("posts".to_string()))
+        );
+        assert_eq!(
+            Test::from_path("/API/V1/posts"),
+            Some(Test::Fallback("posts".to_string()))
+        );
+        assert_eq!(
+            Test::from_path("/api/v2"),
+            Some(Test::Other("/api/v2".to_string()))
+        );
+    }
+
     mod fragment_routing_tests {
         use super::*;
 
@@ -422,7 +485,7 @@ mod tests {
                 Variant,
             }
             let route = String::from("#/lorem");
-            Test::switch(route).expect("should produce item");
+            Test::from_route(route).expect("should produce item");
         }
 
         #[test]
@@ -433,7 +496,7 @@ mod tests {
                 Variant,
             }
             let route = String::from("#/lorem=ipsum");
-            Test::switch(route).expect("should produce item");
+            Test::from_route(route).expect("should produce item");
         }
 
         #[test]
@@ -444,7 +507,7 @@ mod tests {
                 Variant { ipsum: String },
             }
             let route = String::from("#/lorem=dolor");
-            let switched = Test::switch(route).expect("should produce item");
+            let switched = Test::from_route(route).expect("should produce item");
             assert_eq!(
                 switched,
                 Test::Variant {
@@ -453,4 +516,1027 @@ mod tests {
             )
         }
     }
+
+    mod to_path_tests {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum AppRoute {
+            #[to = "/profile/{id}"]
+            Profile(u32),
+            #[to = "/forum{*:rest}"]
+            Forum(ForumRoute),
+            #[to = "/"]
+            Index,
+        }
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum ForumRoute {
+            #[to = "/{subforum}/{thread_slug}"]
+            SubForumAndThread {
+                subforum: String,
+                thread_slug: String,
+            },
+            #[to = "/{subforum}"]
+            SubForum { subforum: String },
+        }
+
+        #[test]
+        fn unit_variant() {
+            assert_eq!(AppRoute::Index.to_path(), "/".to_string());
+        }
+
+        #[test]
+        fn unnamed_capture() {
+            assert_eq!(AppRoute::Profile(42).to_path(), "/profile/42".to_string());
+        }
+
+        #[test]
+        fn named_captures() {
+            let route = ForumRoute::SubForumAndThread {
+                subforum: "test".to_string(),
+                thread_slug: "12".to_string(),
+            };
+            assert_eq!(route.to_path(), "/test/12".to_string());
+        }
+
+        #[test]
+        fn nested_rest_delegates_to_inner() {
+            let route = AppRoute::Forum(ForumRoute::SubForum {
+                subforum: "test".to_string(),
+            });
+            assert_eq!(route.to_path(), "/forum/test".to_string());
+        }
+
+        #[test]
+        fn round_trip() {
+            let routes = vec![
+                AppRoute::Index,
+                AppRoute::Profile(7),
+                AppRoute::Forum(ForumRoute::SubForum {
+                    subforum: "test".to_string(),
+                }),
+                AppRoute::Forum(ForumRoute::SubForumAndThread {
+                    subforum: "test".to_string(),
+                    thread_slug: "12".to_string(),
+                }),
+            ];
+            for route in routes {
+                assert_eq!(AppRoute::from_path(&route.to_path()), Some(route));
+            }
+        }
+
+        #[test]
+        fn end_is_not_written() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            pub enum Test {
+                #[to = "/variant/{item}!"]
+                Variant { item: String },
+            }
+            let route = Test::Variant {
+                item: "thing".to_string(),
+            };
+            assert_eq!(route.to_path(), "/variant/thing".to_string());
+        }
+
+        #[test]
+        fn query_and_fragment() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            pub enum Test {
+                #[to = "/search?q={query}&page={page}#{section}"]
+                Variant {
+                    query: String,
+                    page: usize,
+                    section: String,
+                },
+            }
+            let route = Test::Variant {
+                query: "lorem".to_string(),
+                page: 2,
+                section: "results".to_string(),
+            };
+            assert_eq!(
+                route.to_path(),
+                "/search?q=lorem&page=2#results".to_string()
+            );
+            assert_eq!(Test::from_path(&route.to_path()), Some(route));
+        }
+
+        #[test]
+        fn struct_with_rest() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            #[to = "/app"]
+            #[rest]
+            pub struct Test(AppRoute);
+
+            let route = Test(AppRoute::Profile(3));
+            assert_eq!(route.to_path(), "/app/profile/3".to_string());
+            assert_eq!(Test::from_path(&route.to_path()), Some(route));
+        }
+    }
+
+    mod percent_encoding_tests {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum AppRoute {
+            #[to = "/user/{name}"]
+            User { name: String },
+            #[to = "/search?q={query}"]
+            Search { query: String },
+            #[to = "/docs/{*:path}"]
+            Docs { path: String },
+            #[to = "/forum{*:rest}"]
+            Forum(ForumRoute),
+        }
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum ForumRoute {
+            #[to = "/{subforum}"]
+            SubForum { subforum: String },
+        }
+
+        #[test]
+        fn path_capture_is_decoded() {
+            assert_eq!(
+                AppRoute::from_path("/user/J%C3%BCrgen"),
+                Some(AppRoute::User {
+                    name: "Jürgen".to_string()
+                })
+            );
+        }
+
+        #[test]
+        fn query_capture_is_form_decoded() {
+            assert_eq!(
+                AppRoute::from_path("/search?q=rust+%26+c%2B%2B"),
+                Some(AppRoute::Search {
+                    query: "rust & c++".to_string()
+                })
+            );
+        }
+
+        #[test]
+        fn captures_are_encoded() {
+            let route = AppRoute::User {
+                name: "a/b c".to_string(),
+            };
+            assert_eq!(route.to_path(), "/user/a%2Fb%20c".to_string());
+            let route = AppRoute::Search {
+                query: "a b&c".to_string(),
+            };
+            assert_eq!(route.to_path(), "/search?q=a+b%26c".to_string());
+            let route = AppRoute::Docs {
+                path: "guide/100% rust".to_string(),
+            };
+            assert_eq!(route.to_path(), "/docs/guide/100%25%20rust".to_string());
+        }
+
+        #[test]
+        fn nested_route_is_decoded_once() {
+            let route = AppRoute::Forum(ForumRoute::SubForum {
+                subforum: "50% off".to_string(),
+            });
+            assert_eq!(route.to_path(), "/forum/50%25%20off".to_string());
+            assert_eq!(AppRoute::from_path(&route.to_path()), Some(route));
+        }
+
+        #[test]
+        fn round_trip() {
+            let routes = vec![
+                AppRoute::User {
+                    name: "Jürgen ?#".to_string(),
+                },
+                AppRoute::Search {
+                    query: "1+1 = 2".to_string(),
+                },
+                AppRoute::Docs {
+                    path: "a b/c%d".to_string(),
+                },
+            ];
+            for route in routes {
+                assert_eq!(AppRoute::from_path(&route.to_path()), Some(route));
+            }
+        }
+    }
+
+    mod constraint_tests {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum PostRoute {
+            #[to = "/post/{id:u32}"]
+            Post { id: String },
+            #[to = "/post/{action}"]
+            Action { action: String },
+        }
+
+        #[test]
+        fn failed_constraint_moves_to_next_variant() {
+            assert_eq!(
+                PostRoute::from_path("/post/12"),
+                Some(PostRoute::Post {
+                    id: "12".to_string()
+                })
+            );
+            assert_eq!(
+                PostRoute::from_path("/post/new"),
+                Some(PostRoute::Action {
+                    action: "new".to_string()
+                })
+            );
+        }
+
+        #[test]
+        fn pattern_constraint() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            pub enum Test {
+                #[to = "/file/{name:[a-z0-9_-]+}"]
+                File { name: String },
+                #[to = "/file/{other}"]
+                Other(String),
+            }
+            assert_eq!(
+                Test::from_path("/file/my_file-2"),
+                Some(Test::File {
+                    name: "my_file-2".to_string()
+                })
+            );
+            assert_eq!(
+                Test::from_path("/file/Upper"),
+                Some(Test::Other("Upper".to_string()))
+            );
+        }
+
+        #[test]
+        fn constraints_in_tuple_variant_and_query() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            pub enum Test {
+                #[to = "/d/{date:date}?lang={lang:en|fr}"]
+                Day(String, String),
+            }
+            assert_eq!(
+                Test::from_path("/d/2020-02-29?lang=fr"),
+                Some(Test::Day("2020-02-29".to_string(), "fr".to_string()))
+            );
+            assert_eq!(Test::from_path("/d/2020-02-30?lang=fr"), None);
+            assert_eq!(Test::from_path("/d/2020-02-29?lang=de"), None);
+            let route = Test::Day("2020-02-29".to_string(), "en".to_string());
+            assert_eq!(route.to_path(), "/d/2020-02-29?lang=en".to_string());
+        }
+    }
+    mod optional_section_tests {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum UserRoute {
+            #[to = "/users[/{id:u32}][/{action}]!"]
+            Users {
+                id: Option<u32>,
+                action: Option<String>,
+            },
+        }
+
+        #[test]
+        fn skipped_sections_produce_none() {
+            assert_eq!(
+                UserRoute::from_path("/users"),
+                Some(UserRoute::Users {
+                    id: None,
+                    action: None
+                })
+            );
+            assert_eq!(
+                UserRoute::from_path("/users/5"),
+                Some(UserRoute::Users {
+                    id: Some(5),
+                    action: None
+                })
+            );
+            assert_eq!(
+                UserRoute::from_path("/users/5/edit"),
+                Some(UserRoute::Users {
+                    id: Some(5),
+                    action: Some("edit".to_string())
+                })
+            );
+            assert_eq!(
+                UserRoute::from_path("/users/edit"),
+                Some(UserRoute::Users {
+                    id: None,
+                    action: Some("edit".to_string())
+                })
+            );
+        }
+
+        #[test]
+        fn later_sections_are_tried_when_fields_dont_convert() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            pub enum Test {
+                #[to = "/users[/{id}][/edit]!"]
+                Users { id: Option<u32> },
+            }
+            assert_eq!(
+                Test::from_path("/users/edit"),
+                Some(Test::Users { id: None })
+            );
+            assert_eq!(
+                Test::try_from_path("/users/edit"),
+                Ok(Test::Users { id: None })
+            );
+            assert_eq!(
+                Test::from_path("/users/5/edit"),
+                Some(Test::Users { id: Some(5) })
+            );
+            assert_eq!(Test::from_path("/users/new"), None);
+
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            #[to = "/users[/{id}][/edit]!"]
+            pub struct Users {
+                id: Option<u32>,
+            }
+            assert_eq!(Users::from_path("/users/edit"), Some(Users { id: None }));
+            assert_eq!(Users::try_from_path("/users/edit"), Ok(Users { id: None }));
+        }
+
+        #[test]
+        fn none_fields_are_left_out_of_path() {
+            let route = UserRoute::Users {
+                id: None,
+                action: Some("new".to_string()),
+            };
+            assert_eq!(route.to_path(), "/users/new".to_string());
+            let route = UserRoute::Users {
+                id: Some(5),
+                action: None,
+            };
+            assert_eq!(route.to_path(), "/users/5".to_string());
+        }
+
+        #[test]
+        fn tuple_fields_stay_in_position() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            pub enum Test {
+                #[to = "[/{lang:en|fr}]/docs/{page}"]
+                Docs(Option<String>, String),
+            }
+            assert_eq!(
+                Test::from_path("/docs/intro"),
+                Some(Test::Docs(None, "intro".to_string()))
+            );
+            assert_eq!(
+                Test::from_path("/fr/docs/intro"),
+                Some(Test::Docs(Some("fr".to_string()), "intro".to_string()))
+            );
+            let route = Test::Docs(Some("en".to_string()), "intro".to_string());
+            assert_eq!(route.to_path(), "/en/docs/intro".to_string());
+        }
+
+        #[test]
+        fn literal_sections_are_left_out_of_path() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            #[to = "/inbox[/]!"]
+            pub struct Inbox;
+            assert_eq!(Inbox::from_path("/inbox/"), Some(Inbox));
+            assert_eq!(Inbox::from_path("/inbox"), Some(Inbox));
+            assert_eq!(Inbox.to_path(), "/inbox".to_string());
+        }
+    }
+
+    mod query_tests {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum SearchRoute {
+            #[to = "/search?q={q}&page={page}"]
+            Search { q: String, page: u32 },
+            #[to = "/feed?sort=new!"]
+            NewestFeed,
+            #[to = "/feed"]
+            Feed,
+        }
+
+        #[test]
+        fn parameters_match_in_any_order() {
+            let expected = Some(SearchRoute::Search {
+                q: "rust".to_string(),
+                page: 2,
+            });
+            assert_eq!(SearchRoute::from_path("/search?q=rust&page=2"), expected);
+            assert_eq!(SearchRoute::from_path("/search?page=2&q=rust"), expected);
+            assert_eq!(
+                SearchRoute::from_path("/search?page=2&ref=home&q=rust"),
+                expected
+            );
+            assert_eq!(SearchRoute::from_path("/search?q=rust"), None);
+        }
+
+        #[test]
+        fn strict_query_rejects_unknown_parameters() {
+            assert_eq!(
+                SearchRoute::from_path("/feed?sort=new"),
+                Some(SearchRoute::NewestFeed)
+            );
+            assert_eq!(
+                SearchRoute::from_path("/feed?sort=new&ref=home"),
+                Some(SearchRoute::Feed)
+            );
+        }
+
+        #[test]
+        fn parameters_are_written_in_matcher_order() {
+            let route = SearchRoute::Search {
+                q: "rust".to_string(),
+                page: 2,
+            };
+            assert_eq!(route.to_path(), "/search?q=rust&page=2".to_string());
+            assert_eq!(
+                SearchRoute::NewestFeed.to_path(),
+                "/feed?sort=new".to_string()
+            );
+        }
+
+        #[test]
+        fn missing_parameters_produce_none() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            #[to = "/list?page={page}&sort={sort}"]
+            pub struct List {
+                page: u32,
+                sort: Option<String>,
+            }
+            assert_eq!(
+                List::from_path("/list?page=2"),
+                Some(List {
+                    page: 2,
+                    sort: None
+                })
+            );
+            assert_eq!(
+                List::from_path("/list?sort=new&page=2"),
+                Some(List {
+                    page: 2,
+                    sort: Some("new".to_string())
+                })
+            );
+            assert_eq!(List::from_path("/list?sort=new"), None);
+
+            let list = List {
+                page: 2,
+                sort: None,
+            };
+            assert_eq!(list.to_path(), "/list?page=2".to_string());
+        }
+
+        #[test]
+        fn repeated_parameters_populate_vec() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            #[to = "/filter?tag={*:tags}&id={*:ids}"]
+            pub struct Filter {
+                tags: Vec<String>,
+                ids: Vec<u32>,
+            }
+            assert_eq!(
+                Filter::from_path("/filter?tag=a&id=1&tag=b+c&id=2"),
+                Some(Filter {
+                    tags: vec!["a".to_string(), "b c".to_string()],
+                    ids: vec![1, 2],
+                })
+            );
+            assert_eq!(
+                Filter::from_path("/filter"),
+                Some(Filter {
+                    tags: vec![],
+                    ids: vec![],
+                })
+            );
+            assert_eq!(Filter::from_path("/filter?id=1&id=x"), None);
+
+            let filter = Filter {
+                tags: vec!["a".to_string(), "b c".to_string()],
+                ids: vec![3],
+            };
+            assert_eq!(filter.to_path(), "/filter?tag=a&tag=b+c&id=3".to_string());
+            assert_eq!(Filter::from_path(&filter.to_path()), Some(filter));
+
+            let filter = Filter {
+                tags: vec!["a&b".to_string(), "c".to_string()],
+                ids: vec![],
+            };
+            assert_eq!(filter.to_path(), "/filter?tag=a%26b&tag=c".to_string());
+            assert_eq!(Filter::from_path(&filter.to_path()), Some(filter));
+        }
+
+        #[test]
+        fn repeated_parameters_populate_unnamed_vec() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            #[to = "/filter?tag={*}"]
+            pub struct Filter(Vec<String>);
+            assert_eq!(
+                Filter::from_path("/filter?tag=a&tag=b"),
+                Some(Filter(vec!["a".to_string(), "b".to_string()]))
+            );
+            assert_eq!(Filter(vec![]).to_path(), "/filter".to_string());
+        }
+
+        #[test]
+        fn tuple_fields_follow_matcher_order() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            #[to = "/{}?a={}&b={}"]
+            pub struct Test(String, String, String);
+            assert_eq!(
+                Test::from_path("/x?b=2&a=1"),
+                Some(Test("x".to_string(), "1".to_string(), "2".to_string()))
+            );
+        }
+    }
+
+    mod alternation_tests {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum BlogRoute {
+            #[to = "/(posts|articles)/{slug}"]
+            Post { slug: String },
+            #[to = "/{lang:(en|de|fr)}/home"]
+            Home { lang: String },
+        }
+
+        #[test]
+        fn any_alternative_matches() {
+            assert_eq!(
+                BlogRoute::from_path("/posts/hello"),
+                Some(BlogRoute::Post {
+                    slug: "hello".to_string()
+                })
+            );
+            assert_eq!(
+                BlogRoute::from_path("/articles/hello"),
+                Some(BlogRoute::Post {
+                    slug: "hello".to_string()
+                })
+            );
+            assert_eq!(
+                BlogRoute::from_path("/de/home"),
+                Some(BlogRoute::Home {
+                    lang: "de".to_string()
+                })
+            );
+            assert_eq!(BlogRoute::from_path("/notes/hello"), None);
+            assert_eq!(BlogRoute::from_path("/es/home"), None);
+        }
+
+        #[test]
+        fn first_alternative_is_written_to_path() {
+            let route = BlogRoute::Post {
+                slug: "hello".to_string(),
+            };
+            assert_eq!(route.to_path(), "/posts/hello".to_string());
+        }
+    }
+
+    mod try_from_path_tests {
+        use super::*;
+        use yew_router_min::{
+            matcher::MatchError,
+            route::{SwitchAttempt, SwitchFailure},
+        };
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum ItemRoute {
+            #[to = "/item/{id}"]
+            Item { id: u32 },
+            #[to = "/item/{name}"]
+            Named { name: String },
+            #[to = "/list"]
+            List,
+        }
+
+        #[test]
+        fn failed_field_falls_through_to_later_variant() {
+            assert_eq!(
+                ItemRoute::from_path("/item/lamp"),
+                Some(ItemRoute::Named {
+                    name: "lamp".to_string()
+                })
+            );
+            assert_eq!(
+                ItemRoute::try_from_path("/item/lamp"),
+                Ok(ItemRoute::Named {
+                    name: "lamp".to_string()
+                })
+            );
+        }
+
+        #[test]
+        fn lists_attempted_variants() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            pub enum Test {
+                #[to = "/item/{id}"]
+                Item { id: u32 },
+                #[to = "/list"]
+                List,
+            }
+
+            let error = Test::try_from_path("/item/lamp").expect_err("should not switch");
+            assert_eq!(error.route, "/item/lamp");
+            assert_eq!(
+                error.attempts,
+                vec![
+                    SwitchAttempt {
+                        name: "Item",
+                        failure: SwitchFailure::Field {
+                            field: "id",
+                            capture: Some("lamp".to_string()),
+                        },
+                    },
+                    SwitchAttempt {
+                        name: "List",
+                        failure: SwitchFailure::Match(MatchError {
+                            token: 0,
+                            offset: 0,
+                            expected: Some("/list".to_string()),
+                        }),
+                    },
+                ]
+            );
+        }
+
+        #[test]
+        fn struct_lists_itself() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            #[to = "/page/{number}"]
+            pub struct Page(usize);
+
+            assert_eq!(Page::try_from_path("/page/3"), Ok(Page(3)));
+            let error = Page::try_from_path("/post/3").expect_err("should not switch");
+            assert_eq!(
+                error.attempts,
+                vec![SwitchAttempt {
+                    name: "Page",
+                    failure: SwitchFailure::Match(MatchError {
+                        token: 0,
+                        offset: 0,
+                        expected: Some("/page/".to_string()),
+                    }),
+                }]
+            );
+        }
+    }
+
+    mod case_sensitivity_tests {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        #[switch(case_sensitive)]
+        pub enum SensitiveRoute {
+            #[to = "/about!"]
+            About,
+            #[to = "/About!"]
+            AboutCapitalized,
+            #[to = "/user/{name}"]
+            #[switch(case_insensitive, lowercase_captures)]
+            User { name: String },
+        }
+
+        #[test]
+        fn enum_setting_is_inherited() {
+            assert_eq!(
+                SensitiveRoute::from_path("/about"),
+                Some(SensitiveRoute::About)
+            );
+            assert_eq!(
+                SensitiveRoute::from_path("/About"),
+                Some(SensitiveRoute::AboutCapitalized)
+            );
+            assert_eq!(SensitiveRoute::from_path("/ABOUT"), None);
+        }
+
+        #[test]
+        fn variant_overrides_enum_setting() {
+            assert_eq!(
+                SensitiveRoute::from_path("/USER/Alice"),
+                Some(SensitiveRoute::User {
+                    name: "alice".to_string()
+                })
+            );
+        }
+
+        #[test]
+        fn captures_keep_their_case_by_default() {
+            #[derive(Debug, Switch, PartialEq, Clone)]
+            #[to = "/user/{name}"]
+            pub struct User {
+                name: String,
+            }
+
+            assert_eq!(
+                User::from_path("/User/Alice"),
+                Some(User {
+                    name: "Alice".to_string()
+                })
+            );
+        }
+    }
+
+    mod specificity_tests {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        #[switch(rank = "specificity")]
+        pub enum UserRoute {
+            #[to = "/users!"]
+            Users,
+            #[to = "/users/{id}"]
+            User { id: String },
+            #[to = "/users/{id}/edit"]
+            Edit { id: String },
+            #[to = "/users/new"]
+            New,
+            #[to = "/files/{*:path}"]
+            Files { path: String },
+            #[to = "/files/{id:u32}"]
+            File { id: u32 },
+        }
+
+        #[test]
+        fn most_specific_variant_is_chosen() {
+            assert_eq!(UserRoute::from_path("/users"), Some(UserRoute::Users));
+            assert_eq!(UserRoute::from_path("/users/new"), Some(UserRoute::New));
+            assert_eq!(
+                UserRoute::from_path("/users/5"),
+                Some(UserRoute::User {
+                    id: "5".to_string()
+                })
+            );
+            assert_eq!(
+                UserRoute::from_path("/users/5/edit"),
+                Some(UserRoute::Edit {
+                    id: "5".to_string()
+                })
+            );
+        }
+
+        #[test]
+        fn fixed_captures_rank_above_many_captures() {
+            assert_eq!(
+                UserRoute::from_path("/files/5"),
+                Some(UserRoute::File { id: 5 })
+            );
+            assert_eq!(
+                UserRoute::from_path("/files/docs/notes.txt"),
+                Some(UserRoute::Files {
+                    path: "docs/notes.txt".to_string()
+                })
+            );
+        }
+    }
+
+    mod base_path_tests {
+        use super::*;
+        use yew_router_min::route::{BasePath, BasePathError};
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum AppRoute {
+            #[to = "/users/{id}"]
+            User { id: u32 },
+            #[to = "/!"]
+            Home,
+        }
+
+        #[test]
+        fn base_is_stripped_and_added_back() {
+            let base = BasePath::new("staging");
+            assert_eq!(base.as_str(), "/staging");
+            let route = base
+                .from_path::<AppRoute>("/staging/users/3")
+                .expect("should match");
+            assert_eq!(route, AppRoute::User { id: 3 });
+            assert_eq!(base.to_path(&route), "/staging/users/3");
+            assert_eq!(base.from_path("/staging/"), Ok(AppRoute::Home));
+            assert_eq!(base.to_path(&AppRoute::Home), "/staging/");
+        }
+
+        #[test]
+        fn root_base_leaves_routes_alone() {
+            let base = BasePath::new("/");
+            assert_eq!(base.from_path("/users/3"), Ok(AppRoute::User { id: 3 }));
+            assert_eq!(base.to_path(&AppRoute::User { id: 3 }), "/users/3");
+        }
+
+        #[test]
+        fn outside_base_is_distinct_from_no_match() {
+            let base = BasePath::new("/tenant-x/app");
+            assert_eq!(
+                base.from_path::<AppRoute>("/tenant-xy/app/users/3"),
+                Err(BasePathError::OutsideBase {
+                    base: "/tenant-x/app".to_string(),
+                    route: "/tenant-xy/app/users/3".to_string(),
+                })
+            );
+            assert_eq!(
+                base.from_path::<AppRoute>("/tenant-x/app/posts"),
+                Err(BasePathError::NoMatch {
+                    route: "/posts".to_string()
+                })
+            );
+        }
+
+        #[test]
+        fn try_from_path_returns_the_switch_error_under_the_base() {
+            let base = BasePath::new("/tenant-x/app");
+            assert_eq!(
+                base.try_from_path::<AppRoute>("/tenant-x/app/posts"),
+                Err(BasePathError::Switch(
+                    AppRoute::try_from_path("/posts").expect_err("should not match")
+                ))
+            );
+            assert_eq!(
+                base.try_from_path::<AppRoute>("/posts"),
+                Err(BasePathError::OutsideBase {
+                    base: "/tenant-x/app".to_string(),
+                    route: "/posts".to_string(),
+                })
+            );
+        }
+    }
+
+    mod prefix_tests {
+        use super::*;
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        #[switch(prefix = "/api/v2")]
+        pub enum ApiRoute {
+            #[to = "/users/{id}"]
+            User { id: u32 },
+            #[to = "{*:rest}"]
+            Other { rest: String },
+        }
+
+        #[test]
+        fn prefix_is_matched_before_each_variant() {
+            assert_eq!(
+                ApiRoute::from_path("/api/v2/users/4"),
+                Some(ApiRoute::User { id: 4 })
+            );
+            assert_eq!(
+                ApiRoute::from_path("/api/v2/posts"),
+                Some(ApiRoute::Other {
+                    rest: "/posts".to_string()
+                })
+            );
+            assert_eq!(ApiRoute::from_path("/users/4"), None);
+        }
+
+        #[test]
+        fn prefix_is_written_by_to_path() {
+            assert_eq!(ApiRoute::User { id: 4 }.to_path(), "/api/v2/users/4");
+            let other = ApiRoute::Other {
+                rest: "/posts".to_string(),
+            };
+            assert_eq!(other.to_path(), "/api/v2/posts");
+        }
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        #[switch(prefix = "/api/")]
+        pub enum SlashRoute {
+            #[to = "/users"]
+            Users,
+        }
+
+        #[test]
+        fn prefix_trailing_slash_is_joined_with_variant_slash() {
+            assert_eq!(SlashRoute::from_path("/api/users"), Some(SlashRoute::Users));
+            assert_eq!(SlashRoute::from_path("/api//users"), None);
+            assert_eq!(SlashRoute::Users.to_path(), "/api/users");
+            assert_eq!(SlashRoute::routes()[0].matcher, "/api/users");
+        }
+    }
+
+    mod route_info_tests {
+        use super::*;
+        use yew_router_min::route::{CaptureInfoKind, RouteInfo};
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        #[switch(prefix = "/admin")]
+        pub enum AdminRoute {
+            #[to = "/users/{id:u32}[/{tab}]!"]
+            User { id: u32, tab: Option<String> },
+            #[to = "/search?tag={*:tags}"]
+            Search { tags: Vec<String> },
+            #[to = "/files/{2:dir}/"]
+            #[rest]
+            Files(String, String),
+            #[to = "/settings"]
+            #[rest]
+            Settings(SettingsRoute),
+        }
+
+        #[derive(Debug, Switch, PartialEq, Clone)]
+        pub enum SettingsRoute {
+            #[to = "/profile!"]
+            Profile,
+            #[to = "/keys/{key}"]
+            Key { key: String },
+        }
+
+        #[test]
+        fn lists_every_variant() {
+            let routes = AdminRoute::routes();
+            let names: Vec<&str> = routes.iter().map(|route| route.name).collect();
+            assert_eq!(names, vec!["User", "Search", "Files", "Settings"]);
+
+            let user = &routes[0];
+            assert_eq!(user.matcher, "/admin/users/{id:u32}[/{tab}]!");
+            assert!(user.end);
+            assert_eq!(user.captures.len(), 2);
+            assert_eq!(user.captures[0].name, "id");
+            assert_eq!(user.captures[0].kind, CaptureInfoKind::Named);
+            assert_eq!(user.captures[0].field_type, "u32");
+            assert_eq!(user.captures[1].field_type, "Option<String>");
+            assert!(!routes[1].end);
+            assert_eq!(routes[1].captures[0].kind, CaptureInfoKind::ManyNamed);
+            assert_eq!(routes[1].captures[0].field_type, "Vec<String>");
+        }
+
+        #[test]
+        fn tuple_captures_are_named_by_position() {
+            let files = &AdminRoute::routes()[2];
+            assert_eq!(files.matcher, "/admin/files/{2:dir}/{*}");
+            let captures: Vec<(&str, CaptureInfoKind)> = files
+                .captures
+                .iter()
+                .map(|capture| (capture.name, capture.kind))
+                .collect();
+            assert_eq!(
+                captures,
+                vec![
+                    ("0", CaptureInfoKind::NumberedNamed { sections: 2 }),
+                    ("1", CaptureInfoKind::ManyNamed)
+                ]
+            );
+        }
+
+        #[test]
+        fn nested_routes_expand_into_tree() {
+            fn describe(routes: &[RouteInfo], depth: usize, lines: &mut Vec<String>) {
+                for route in routes {
+                    lines.push(format!("{}{}", "  ".repeat(depth), route.matcher));
+                    for capture in route.captures {
+                        describe((capture.nested)(), depth + 1, lines);
+                    }
+                }
+            }
+            let mut lines = vec![];
+            describe(AdminRoute::routes(), 0, &mut lines);
+            assert_eq!(
+                lines,
+                vec![
+                    "/admin/users/{id:u32}[/{tab}]!",
+                    "/admin/search?tag={*:tags}",
+                    "/admin/files/{2:dir}/{*}",
+                    "/admin/settings{*}",
+                    "  /profile!",
+                    "  /keys/{key}",
+                ]
+            );
+            assert!(u32::routes().is_empty());
+        }
+
+        #[test]
+        fn routes_describe_openapi_paths() {
+            use yew_router_min::openapi::{paths_from_routes, ParameterLocation, SchemaType};
+
+            let paths = paths_from_routes(AdminRoute::routes());
+            let templates: Vec<&str> = paths.keys().map(String::as_str).collect();
+            assert_eq!(
+                templates,
+                vec![
+                    "/admin/files/{dir}/{1}",
+                    "/admin/search",
+                    "/admin/settings/keys/{key}",
+                    "/admin/settings/profile",
+                    "/admin/users/{id}",
+                    "/admin/users/{id}/{tab}",
+                ]
+            );
+
+            let user = &paths["/admin/users/{id}/{tab}"].parameters;
+            assert_eq!(user[0].schema.kind, SchemaType::Integer);
+            assert_eq!(user[0].schema.minimum, Some(0));
+            assert_eq!(user[1].schema.kind, SchemaType::String);
+            assert!(user[1].required);
+
+            let tag = &paths["/admin/search"].parameters[0];
+            assert_eq!(tag.name, "tag");
+            assert_eq!(tag.location, ParameterLocation::Query);
+            assert_eq!(tag.schema.kind, SchemaType::Array);
+            assert!(!tag.required);
+
+            let files = &paths["/admin/files/{dir}/{1}"].parameters;
+            assert_eq!(files.len(), 2);
+            assert!(files
+                .iter()
+                .all(|param| param.location == ParameterLocation::Path));
+        }
+    }
 }